@@ -80,13 +80,12 @@ fn main() {
     };
 
     let mut buffer = Vec::<u8>::new();
-    {
-        let mut ser = macroserde_msgpack::Serializer::new(&mut buffer);
-        stuff.serialize(&mut ser).unwrap();
-    }
+    let mut ser = macroserde_msgpack::Serializer::new(&mut buffer);
+    stuff.serialize(&mut ser).unwrap();
+    ser.finish().unwrap();
     println!("{:x?}", buffer);
 
-    let mut de = macroserde_msgpack::Deserializer::new(buffer.as_slice());
+    let mut de = macroserde_msgpack::Deserializer::from_slice(&buffer);
     let person = Person::deserialize(&mut de).unwrap();
     println!("{:#?}", person);
     /*
@@ -0,0 +1,141 @@
+// The types of the README example, shared by the tests of the format crates.
+
+use macroserde::de::*;
+use macroserde::macroserde;
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    pub enum Month {
+        #[default]
+        January = 1 @ "JAN",
+        February = 2 @ "FEB",
+        March = 3 @ "MAR",
+        April = 4 @ "APR",
+        May = 5 @ "MAY",
+        June = 6 @ "JUN",
+        July = 7 @ "JUL",
+        August = 8 @ "AUG",
+        September = 9 @ "SEP",
+        October = 10 @ "OCT",
+        November = 11 @ "NOV",
+        December = 12 @ "DEC",
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    pub struct Date {
+        pub day: u8 = 1,
+        pub month: Month = 2,
+        pub year: u32 = 3,
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    pub union Occupation {
+        #[default]
+        Unemployed = 1,
+        Employed(String) = 2 @ "hasJob",
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    pub struct Person {
+        pub name: String = 1,
+        pub age: i16 = 2,
+        pub birth_date: Date = 3,
+        pub pets: Vec<String> = 4,
+        pub height: Option<f32> = 5,
+        pub car_brand: Option<String> = 88 @ "carBrand",
+        pub is_cool: bool = 6 @ "IsCool",
+        pub occupation: Occupation = 7,
+    }
+}
+
+// Optional values that formats must tell apart: a missing struct, and a null
+// string from an empty one.
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    pub struct Contact {
+        pub name: String = 1,
+        pub birth_date: Option<Date> = 3,
+        pub car_brand: Option<String> = 88 @ "carBrand",
+    }
+}
+
+// Formats whose documents must be structs hold the contacts in one.
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    pub struct AddressBook {
+        pub contacts: Vec<Contact> = 1,
+    }
+}
+
+// The flat fields of `Person`, for formats whose records only hold scalars.
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    pub struct Record {
+        pub name: String = 1,
+        pub age: i16 = 2,
+        pub month: Month = 3,
+        pub height: Option<f32> = 5,
+        pub car_brand: Option<String> = 88 @ "carBrand",
+        pub is_cool: bool = 6 @ "IsCool",
+    }
+}
+
+pub fn person() -> Person {
+    Person {
+        name: "Steven".to_owned(),
+        age: 27,
+        height: Some(1.735),
+        car_brand: None,
+        is_cool: true,
+        birth_date: Date {
+            day: 19,
+            month: Month::October,
+            year: 1993,
+        },
+        pets: vec!["Bouboul".to_owned(), "Monsieur Puppy".to_owned()],
+        occupation: Occupation::Employed("Engineer".to_owned()),
+    }
+}
+
+pub fn contacts() -> Vec<Contact> {
+    vec![
+        Contact {
+            name: "Steven".to_owned(),
+            birth_date: Some(Date {
+                day: 19,
+                month: Month::October,
+                year: 1993,
+            }),
+            car_brand: Some(String::new()),
+        },
+        Contact {
+            name: String::new(),
+            birth_date: None,
+            car_brand: None,
+        },
+    ]
+}
+
+pub fn address_book() -> AddressBook {
+    AddressBook {
+        contacts: contacts(),
+    }
+}
+
+pub fn record() -> Record {
+    let person = person();
+    Record {
+        name: person.name,
+        age: person.age,
+        month: person.birth_date.month,
+        height: person.height,
+        car_brand: person.car_brand,
+        is_cool: person.is_cool,
+    }
+}
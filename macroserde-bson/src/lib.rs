@@ -0,0 +1,491 @@
+use macroserde::ext::Timestamp;
+use macroserde::{de, ser};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io;
+use std::io::{BufRead, Read};
+
+const TYPE_DOUBLE: u8 = 0x01;
+const TYPE_STRING: u8 = 0x02;
+const TYPE_DOCUMENT: u8 = 0x03;
+const TYPE_ARRAY: u8 = 0x04;
+const TYPE_BINARY: u8 = 0x05;
+const TYPE_UNDEFINED: u8 = 0x06;
+const TYPE_OBJECT_ID: u8 = 0x07;
+const TYPE_BOOL: u8 = 0x08;
+const TYPE_DATETIME: u8 = 0x09;
+const TYPE_NULL: u8 = 0x0a;
+const TYPE_INT32: u8 = 0x10;
+const TYPE_TIMESTAMP: u8 = 0x11;
+const TYPE_INT64: u8 = 0x12;
+
+const SUBTYPE_GENERIC: u8 = 0x00;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRepr {
+    /// `"10"`
+    Id,
+    /// `"carBrand"`
+    Name,
+    /// `"10:carBrand"`
+    IdAndName,
+}
+
+// A document is written once complete since it starts with its length.
+pub struct Serializer<W: io::Write> {
+    write: W,
+    key_repr: KeyRepr,
+    buffer: Vec<u8>,
+    // Offset of each open document in the buffer, along with the index of the
+    // next element for arrays.
+    documents: Vec<(usize, Option<usize>)>,
+    // Key of the next element.
+    key: String,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(w: W) -> Self {
+        Self {
+            write: w,
+            key_repr: KeyRepr::Name,
+            buffer: Vec::new(),
+            documents: Vec::new(),
+            key: String::new(),
+        }
+    }
+
+    pub fn with_key_repr(mut self, key_repr: KeyRepr) -> Self {
+        self.key_repr = key_repr;
+        self
+    }
+
+    // Fails if a document was left incomplete by an error.
+    pub fn finish(self) -> Result<W, ser::SerializeError> {
+        if !self.documents.is_empty() {
+            return Err(ser::SerializeError);
+        }
+        Ok(self.write)
+    }
+
+    // Writes the type and key of an element, BSON values only exist inside a
+    // document.
+    fn write_element(&mut self, element_type: u8) -> Result<(), ser::SerializeError> {
+        if self.documents.is_empty() {
+            return match element_type {
+                TYPE_DOCUMENT => Ok(()),
+                _ => Err(ser::SerializeError),
+            };
+        }
+        if self.key.contains('\0') {
+            return Err(ser::SerializeError);
+        }
+        self.buffer.push(element_type);
+        self.buffer.extend_from_slice(self.key.as_bytes());
+        self.buffer.push(0);
+        Ok(())
+    }
+
+    fn start_document(
+        &mut self,
+        element_type: u8,
+        is_array: bool,
+    ) -> Result<(), ser::SerializeError> {
+        self.write_element(element_type)?;
+        self.documents
+            .push((self.buffer.len(), if is_array { Some(0) } else { None }));
+        self.buffer.extend_from_slice(&[0; 4]);
+        Ok(())
+    }
+
+    fn end_document(&mut self) -> Result<(), ser::SerializeError> {
+        self.buffer.push(0);
+        let (start, _) = self.documents.pop().ok_or(ser::SerializeError)?;
+        let len = i32::try_from(self.buffer.len() - start).map_err(|_| ser::SerializeError)?;
+        self.buffer[start..(start + 4)].copy_from_slice(&len.to_le_bytes());
+        if self.documents.is_empty() {
+            self.write.write_all(&self.buffer)?;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write> ser::Serializer for Serializer<W> {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.write_element(TYPE_NULL)
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.write_element(TYPE_BOOL)?;
+        self.buffer.push(value as u8);
+        Ok(())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        match i32::try_from(value) {
+            Ok(value) => {
+                self.write_element(TYPE_INT32)?;
+                self.buffer.extend_from_slice(&value.to_le_bytes());
+            }
+            Err(_) => {
+                self.write_element(TYPE_INT64)?;
+                self.buffer.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    // BSON integers are signed, larger values can't be written.
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        let value = i64::try_from(value).map_err(|_| ser::SerializeError)?;
+        self.serialize_signed(value)
+    }
+
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.write_element(TYPE_DOUBLE)?;
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        let len = i32::try_from(value.len() + 1).map_err(|_| ser::SerializeError)?;
+        self.write_element(TYPE_STRING)?;
+        self.buffer.extend_from_slice(&len.to_le_bytes());
+        self.buffer.extend_from_slice(value.as_bytes());
+        self.buffer.push(0);
+        Ok(())
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), ser::SerializeError> {
+        let len = i32::try_from(value.len()).map_err(|_| ser::SerializeError)?;
+        self.write_element(TYPE_BINARY)?;
+        self.buffer.extend_from_slice(&len.to_le_bytes());
+        self.buffer.push(SUBTYPE_GENERIC);
+        self.buffer.extend_from_slice(value);
+        Ok(())
+    }
+
+    fn serialize_enum(
+        &mut self,
+        value: u32,
+        _name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.serialize_unsigned(value as u64)
+    }
+
+    fn start_struct(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        self.start_document(TYPE_DOCUMENT, false)
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        self.key.clear();
+        match self.key_repr {
+            KeyRepr::Id => self.key.push_str(&field_id.to_string()),
+            KeyRepr::Name => self.key.push_str(&field_name),
+            KeyRepr::IdAndName => self.key.push_str(&format!("{}:{}", field_id, field_name)),
+        }
+        value.serialize(self)
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        self.end_document()
+    }
+
+    fn start_seq(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        // Arrays are documents, there is none to hold the root.
+        if self.documents.is_empty() {
+            return Err(ser::SerializeError);
+        }
+        self.start_document(TYPE_ARRAY, true)
+    }
+
+    // Array elements are keyed by their index.
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        let index = match self.documents.last_mut() {
+            Some((_, Some(index))) => index,
+            _ => return Err(ser::SerializeError),
+        };
+        self.key = index.to_string();
+        *index += 1;
+        value.serialize(self)
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        self.end_document()
+    }
+
+    // BSON has no extension values, they are written as `{ 1:type, 2:data }`
+    // with the data as binary.
+    fn serialize_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), ser::SerializeError> {
+        struct Data<'a>(&'a [u8]);
+
+        impl<'a> ser::Serialize for Data<'a> {
+            fn serialize(
+                &self,
+                serializer: &mut dyn ser::Serializer,
+            ) -> Result<(), ser::SerializeError> {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+
+        self.start_struct(2)?;
+        self.serialize_struct_field(1, "type".into(), &type_id)?;
+        self.serialize_struct_field(2, "data".into(), &Data(data))?;
+        self.end_struct()
+    }
+
+    // Date/times hold milliseconds, timestamps with a finer precision are
+    // written as RFC 3339 strings.
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), ser::SerializeError> {
+        let milliseconds = value
+            .seconds
+            .checked_mul(1000)
+            .and_then(|ms| ms.checked_add((value.nanoseconds / 1_000_000) as i64));
+        match (milliseconds, value.nanoseconds % 1_000_000) {
+            (Some(milliseconds), 0) => {
+                self.write_element(TYPE_DATETIME)?;
+                self.buffer.extend_from_slice(&milliseconds.to_le_bytes());
+                Ok(())
+            }
+            _ => self.serialize_str(&value.to_rfc3339().ok_or(ser::SerializeError)?),
+        }
+    }
+}
+
+pub struct Deserializer<R: io::Read> {
+    read: io::BufReader<R>,
+    scratch: Vec<u8>,
+    // Number of bytes read, to check the length of documents.
+    position: usize,
+    limits: de::LimitTracker,
+}
+
+impl<R: io::Read> Deserializer<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read: io::BufReader::new(read),
+            scratch: Vec::new(),
+            position: 0,
+            limits: de::LimitTracker::new(de::DeserializeLimits::default()),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: de::DeserializeLimits) -> Self {
+        self.limits = de::LimitTracker::new(limits);
+        self
+    }
+
+    #[inline]
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], de::DeserializeError> {
+        let mut bytes = [0; N];
+        self.read.read_exact(&mut bytes)?;
+        self.position += N;
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, de::DeserializeError> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    #[inline]
+    fn read_i32(&mut self) -> Result<i32, de::DeserializeError> {
+        Ok(i32::from_le_bytes(self.read_array()?))
+    }
+
+    #[inline]
+    fn read_i64(&mut self) -> Result<i64, de::DeserializeError> {
+        Ok(i64::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_len(&mut self) -> Result<usize, de::DeserializeError> {
+        usize::try_from(self.read_i32()?).map_err(|_| de::DeserializeError::ParsingError)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&[u8], de::DeserializeError> {
+        self.limits
+            .check_str_len(len)
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        self.scratch.clear();
+        de::read_bounded(&mut self.read, len, &mut self.scratch)?;
+        self.position += len;
+        Ok(&self.scratch)
+    }
+
+    // Strings hold their length, including the trailing null byte.
+    fn read_string(&mut self) -> Result<&str, de::DeserializeError> {
+        let len = self.read_len()?;
+        match self.read_bytes(len)?.split_last() {
+            Some((0, s)) => std::str::from_utf8(s).map_err(|_| de::DeserializeError::ParsingError),
+            _ => Err(de::DeserializeError::ParsingError),
+        }
+    }
+
+    // Keys end with a null byte.
+    fn read_key(&mut self) -> Result<&str, de::DeserializeError> {
+        self.scratch.clear();
+        let len = (&mut self.read)
+            .take(de::MAX_TRUSTED_LEN as u64)
+            .read_until(0, &mut self.scratch)?;
+        self.position += len;
+        if self.scratch.pop() != Some(0) {
+            return Err(de::DeserializeError::ParsingError);
+        }
+        self.limits
+            .check_str_len(self.scratch.len())
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        std::str::from_utf8(&self.scratch).map_err(|_| de::DeserializeError::ParsingError)
+    }
+
+    // Reads the length of a document and calls `parse_element` for each of
+    // its elements with their type and key.
+    fn parse_elements<F>(&mut self, mut parse_element: F) -> Result<(), de::DeserializeError>
+    where
+        F: FnMut(&mut Self, u8) -> Result<(), de::DeserializeError>,
+    {
+        let start = self.position;
+        let len = self.read_len()?;
+        self.limits
+            .enter()
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        let mut count = 0;
+        loop {
+            match self.read_u8()? {
+                0 => break,
+                element_type => parse_element(self, element_type)?,
+            }
+            count += 1;
+        }
+        if self.position - start != len {
+            return Err(de::DeserializeError::ParsingError);
+        }
+        self.limits
+            .check_collection_len(count)
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        self.limits.leave();
+        Ok(())
+    }
+
+    fn parse_document(
+        &mut self,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_struct()?;
+        self.parse_elements(|de, element_type| {
+            let (id, name) = de::split_key(de.read_key()?);
+            match builder.member(id, name) {
+                Ok(visitor) => de.parse_value(element_type, visitor),
+                Err(de::DeserializeError::UnknownField) => {
+                    de.parse_value(element_type, &mut de::NullVisitor)
+                }
+                Err(e) => Err(e),
+            }
+        })?;
+        builder.finish()
+    }
+
+    // The keys of arrays are ignored, elements are read in order.
+    fn parse_array(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_seq(None)?;
+        self.parse_elements(|de, element_type| {
+            de.read_key()?;
+            de.parse_value(element_type, builder.element()?)
+        })?;
+        builder.finish()
+    }
+
+    fn parse_bytes(
+        &mut self,
+        len: usize,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let bytes = self.read_bytes(len)?;
+        match visitor.visit_bytes(bytes) {
+            Err(de::DeserializeError::UnimplementedVisit) => {}
+            result => return result,
+        }
+        let mut builder = visitor.visit_seq(Some(bytes.len()))?;
+        for byte in bytes {
+            builder.element()?.visit_unsigned(*byte as u64)?;
+        }
+        builder.finish()
+    }
+
+    fn parse_value(
+        &mut self,
+        element_type: u8,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match element_type {
+            TYPE_UNDEFINED | TYPE_NULL => visitor.visit_null(),
+            // Optional values only visit documents and arrays through the
+            // visitor of their content.
+            _ if matches!(visitor.hint(), de::Hint::Option) => {
+                self.parse_value(element_type, visitor.visit_some()?)
+            }
+            TYPE_DOUBLE => {
+                let value = f64::from_le_bytes(self.read_array()?);
+                visitor.visit_float(value)
+            }
+            TYPE_STRING => {
+                let s = self.read_string()?;
+                visitor.visit_str(s)
+            }
+            TYPE_DOCUMENT => self.parse_document(visitor),
+            TYPE_ARRAY => self.parse_array(visitor),
+            TYPE_BINARY => {
+                let len = self.read_len()?;
+                let _subtype = self.read_u8()?;
+                self.parse_bytes(len, visitor)
+            }
+            TYPE_OBJECT_ID => self.parse_bytes(12, visitor),
+            TYPE_BOOL => match self.read_u8()? {
+                0 => visitor.visit_bool(false),
+                1 => visitor.visit_bool(true),
+                _ => Err(de::DeserializeError::ParsingError),
+            },
+            TYPE_DATETIME => {
+                let milliseconds = self.read_i64()?;
+                let timestamp = Timestamp {
+                    seconds: milliseconds.div_euclid(1000),
+                    nanoseconds: milliseconds.rem_euclid(1000) as u32 * 1_000_000,
+                };
+                match visitor.visit_timestamp(timestamp) {
+                    Err(de::DeserializeError::UnimplementedVisit) => {
+                        visitor.visit_signed(milliseconds)
+                    }
+                    result => result,
+                }
+            }
+            TYPE_INT32 => {
+                let value = self.read_i32()?;
+                visitor.visit_signed(value as i64)
+            }
+            // Internal timestamps of the store, read as the raw integer.
+            TYPE_TIMESTAMP => {
+                let value = u64::from_le_bytes(self.read_array()?);
+                visitor.visit_unsigned(value)
+            }
+            TYPE_INT64 => {
+                let value = self.read_i64()?;
+                visitor.visit_signed(value)
+            }
+            // Decimal 128, regular expressions, JavaScript code...
+            _ => Err(de::DeserializeError::ParsingError),
+        }
+    }
+}
+
+impl<R: io::Read> de::Deserializer for Deserializer<R> {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        self.parse_document(visitor)
+    }
+}
@@ -0,0 +1,210 @@
+// Handwritten documents: date/times, binary and integer widths.
+
+use fixtures::{contacts, Contact, Date, Month};
+use macroserde::bytes::Bytes;
+use macroserde::de::*;
+use macroserde::ext::Timestamp;
+use macroserde::macroserde;
+use macroserde::ser::*;
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut ser = macroserde_bson::Serializer::new(Vec::new());
+    value.serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+fn decode<T: Deserialize>(bytes: &[u8]) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_bson::Deserializer::new(bytes))
+}
+
+// A document holding these elements, each its type, key and value.
+fn document(elements: &[(u8, &str, &[u8])]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (element_type, key, value) in elements {
+        body.push(*element_type);
+        body.extend_from_slice(key.as_bytes());
+        body.push(0);
+        body.extend_from_slice(value);
+    }
+    let mut document = ((body.len() + 5) as i32).to_le_bytes().to_vec();
+    document.extend_from_slice(&body);
+    document.push(0);
+    document
+}
+
+fn string(s: &str) -> Vec<u8> {
+    let mut bytes = ((s.len() + 1) as i32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(s.as_bytes());
+    bytes.push(0);
+    bytes
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Event {
+        at: Timestamp = 1,
+        data: Bytes = 2,
+        count: i64 = 3,
+        small: i32 = 4,
+    }
+}
+
+#[test]
+fn dates() {
+    let date = document(&[
+        (0x10, "day", &19i32.to_le_bytes()),
+        (0x10, "2", &10i32.to_le_bytes()),
+        (0x12, "3:year", &1993i64.to_le_bytes()),
+    ]);
+    assert_eq!(
+        decode::<Date>(&date).unwrap(),
+        Date {
+            day: 19,
+            month: Month::October,
+            year: 1993,
+        }
+    );
+
+    let steven = string("Steven");
+    let empty = string("");
+    let contact = document(&[
+        (0x02, "name", &steven),
+        (0x03, "birth_date", &date),
+        (0x02, "carBrand", &empty),
+    ]);
+    assert_eq!(decode::<Contact>(&contact).unwrap(), contacts()[0]);
+    let contact = document(&[
+        (0x02, "name", &empty),
+        (0x0a, "birth_date", &[]),
+        (0x06, "carBrand", &[]),
+    ]);
+    assert_eq!(decode::<Contact>(&contact).unwrap(), contacts()[1]);
+}
+
+#[test]
+fn datetime() {
+    let ms = 1_600_000_000_123i64.to_le_bytes();
+    let event = decode::<Event>(&document(&[(0x09, "at", &ms)])).unwrap();
+    assert_eq!(
+        event.at,
+        Timestamp {
+            seconds: 1_600_000_000,
+            nanoseconds: 123_000_000,
+        }
+    );
+    assert_eq!(
+        encode(&event),
+        document(&[
+            (0x09, "at", &ms),
+            (0x05, "data", &[0, 0, 0, 0, 0]),
+            (0x10, "count", &[0; 4]),
+            (0x10, "small", &[0; 4])
+        ])
+    );
+
+    // Before 1970, milliseconds are floored.
+    let before = decode::<Event>(&document(&[(0x09, "at", &(-1i64).to_le_bytes())])).unwrap();
+    assert_eq!(
+        before.at,
+        Timestamp {
+            seconds: -1,
+            nanoseconds: 999_000_000,
+        }
+    );
+
+    // Integer fields take the milliseconds.
+    let count = decode::<Event>(&document(&[(0x09, "count", &ms)])).unwrap();
+    assert_eq!(count.count, 1_600_000_000_123);
+
+    // Finer timestamps are RFC 3339 strings.
+    let exact = Event {
+        at: Timestamp {
+            seconds: 1_600_000_000,
+            nanoseconds: 5,
+        },
+        ..Event::default()
+    };
+    let encoded = encode(&exact);
+    assert_eq!(encoded[4], 0x02);
+    assert_eq!(decode::<Event>(&encoded).unwrap(), exact);
+}
+
+#[test]
+fn binary() {
+    let data = [3, 0, 0, 0, 0x00, 0, 255, 10];
+    let event = decode::<Event>(&document(&[(0x05, "data", &data)])).unwrap();
+    assert_eq!(event.data, Bytes(vec![0, 255, 10]));
+    assert_eq!(
+        &encode(&event)[4..17],
+        &[0x09, b'a', b't', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x05]
+    );
+
+    // Other subtypes and object IDs are bytes as well.
+    let user_defined = [1, 0, 0, 0, 0x80, 42];
+    let event = decode::<Event>(&document(&[(0x05, "data", &user_defined)])).unwrap();
+    assert_eq!(event.data, Bytes(vec![42]));
+    let object_id = decode::<Event>(&document(&[(0x07, "data", &[7; 12])])).unwrap();
+    assert_eq!(object_id.data, Bytes(vec![7; 12]));
+
+    // The length must fit in the document.
+    let truncated = document(&[(0x05, "data", &[9, 0, 0, 0, 0x00, 1, 2])]);
+    assert!(decode::<Event>(&truncated).is_err());
+}
+
+#[test]
+fn integer_widths() {
+    let event = Event {
+        count: 1 << 40,
+        small: -1,
+        ..Event::default()
+    };
+    let encoded = encode(&event);
+    let expected = document(&[
+        (0x09, "at", &[0; 8]),
+        (0x05, "data", &[0, 0, 0, 0, 0]),
+        (0x12, "count", &(1i64 << 40).to_le_bytes()),
+        (0x10, "small", &(-1i32).to_le_bytes()),
+    ]);
+    assert_eq!(encoded, expected);
+    assert_eq!(decode::<Event>(&encoded).unwrap(), event);
+
+    // Either width is read into any integer it fits in.
+    let widths = document(&[
+        (0x10, "count", &7i32.to_le_bytes()),
+        (0x12, "small", &(-7i64).to_le_bytes()),
+    ]);
+    let event = decode::<Event>(&widths).unwrap();
+    assert_eq!((event.count, event.small), (7, -7));
+    let too_big = document(&[(0x12, "small", &(1i64 << 40).to_le_bytes())]);
+    assert!(matches!(
+        decode::<Event>(&too_big),
+        Err(DeserializeError::IncompatibleNumericType)
+    ));
+
+    // BSON integers are signed.
+    let mut ser = macroserde_bson::Serializer::new(Vec::new());
+    assert!(Some(u64::MAX).serialize(&mut ser).is_err());
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    let encoded = document(&[(0x02, "name", &string("Steven"))]);
+    let mut de = macroserde_bson::Deserializer::new(&encoded[..]).with_limits(limits);
+    assert!(matches!(
+        Contact::deserialize(&mut de),
+        Err(DeserializeError::LimitExceeded(Limit::StrLen))
+    ));
+
+    // The declared length of a document must match its elements.
+    let mut encoded = document(&[(0x10, "day", &19i32.to_le_bytes())]);
+    encoded[0] += 1;
+    encoded.push(0);
+    assert!(matches!(
+        decode::<Date>(&encoded),
+        Err(DeserializeError::ParsingError)
+    ));
+}
@@ -0,0 +1,43 @@
+// Round trips of the README types.
+
+use fixtures::{address_book, person, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use macroserde_bson::KeyRepr;
+use std::fmt::Debug;
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    for &key_repr in &[KeyRepr::Name, KeyRepr::IdAndName, KeyRepr::Id] {
+        let mut ser = macroserde_bson::Serializer::new(Vec::new()).with_key_repr(key_repr);
+        value.serialize(&mut ser).unwrap();
+        let encoded = ser.finish().unwrap();
+        let decoded = T::deserialize(&mut macroserde_bson::Deserializer::new(&encoded[..]));
+        assert_eq!(decoded.unwrap(), value, "decoding {:?}", encoded);
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    check(address_book());
+}
@@ -0,0 +1,651 @@
+use macroserde::ext::Timestamp;
+use macroserde::{de, ser};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io;
+use std::io::Write;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+
+const TAG_DATE_TIME: u64 = 0;
+const TAG_EPOCH: u64 = 1;
+
+// Additional information of strings and containers without a length, they end
+// with the break byte.
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xff;
+
+pub struct Serializer<W: io::Write> {
+    write: io::BufWriter<W>,
+    indefinite_lengths: bool,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(w: W) -> Self {
+        Self {
+            write: io::BufWriter::new(w),
+            indefinite_lengths: false,
+        }
+    }
+
+    // Writes structs and sequences with a break byte instead of their length.
+    pub fn with_indefinite_lengths(mut self, indefinite_lengths: bool) -> Self {
+        self.indefinite_lengths = indefinite_lengths;
+        self
+    }
+
+    pub fn flush(&mut self) -> Result<(), ser::SerializeError> {
+        self.write.flush()?;
+        Ok(())
+    }
+
+    // Dropping the serializer also flushes it but any write error is lost.
+    pub fn finish(self) -> io::Result<W> {
+        self.write
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+    }
+
+    fn write_head(&mut self, major: u8, value: u64) -> Result<(), ser::SerializeError> {
+        let major = major << 5;
+        if value < 24 {
+            self.write.write_all(&[major | value as u8])?;
+        } else if value <= u8::MAX as u64 {
+            self.write.write_all(&[major | 24, value as u8])?;
+        } else if value <= u16::MAX as u64 {
+            self.write.write_all(&[major | 25])?;
+            self.write.write_all(&(value as u16).to_be_bytes())?;
+        } else if value <= u32::MAX as u64 {
+            self.write.write_all(&[major | 26])?;
+            self.write.write_all(&(value as u32).to_be_bytes())?;
+        } else {
+            self.write.write_all(&[major | 27])?;
+            self.write.write_all(&value.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write> ser::Serializer for Serializer<W> {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.write.write_all(&[0xf6])?;
+        Ok(())
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.write.write_all(&[0xf4 + value as u8])?;
+        Ok(())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        if value >= 0 {
+            self.write_head(MAJOR_UNSIGNED, value as u64)
+        } else {
+            // Negative integers are encoded as -1 - n.
+            self.write_head(MAJOR_NEGATIVE, !value as u64)
+        }
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.write_head(MAJOR_UNSIGNED, value)
+    }
+
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        if let Some(half) = f64_to_f16(value) {
+            self.write.write_all(&[0xf9])?;
+            self.write.write_all(&half.to_be_bytes())?;
+        } else if value as f32 as f64 == value {
+            self.write.write_all(&[0xfa])?;
+            self.write.write_all(&(value as f32).to_be_bytes())?;
+        } else {
+            self.write.write_all(&[0xfb])?;
+            self.write.write_all(&value.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.write_head(MAJOR_TEXT, value.len() as u64)?;
+        self.write.write_all(value.as_bytes())?;
+        Ok(())
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), ser::SerializeError> {
+        self.write_head(MAJOR_BYTES, value.len() as u64)?;
+        self.write.write_all(value)?;
+        Ok(())
+    }
+
+    fn serialize_enum(
+        &mut self,
+        value: u32,
+        _name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.serialize_unsigned(value as u64)
+    }
+
+    fn start_struct(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        if self.indefinite_lengths {
+            self.write.write_all(&[(MAJOR_MAP << 5) | INDEFINITE])?;
+            Ok(())
+        } else {
+            self.write_head(MAJOR_MAP, len as u64)
+        }
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        _field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        self.serialize_unsigned(field_id as u64)?;
+        value.serialize(self)
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        if self.indefinite_lengths {
+            self.write.write_all(&[BREAK])?;
+        }
+        Ok(())
+    }
+
+    fn start_seq(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        if self.indefinite_lengths {
+            self.write.write_all(&[(MAJOR_ARRAY << 5) | INDEFINITE])?;
+            Ok(())
+        } else {
+            self.write_head(MAJOR_ARRAY, len as u64)
+        }
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        if self.indefinite_lengths {
+            self.write.write_all(&[BREAK])?;
+        }
+        Ok(())
+    }
+
+    // CBOR has no extension values, they are written as `{ 1:type, 2:data }`
+    // with the data as a byte string.
+    fn serialize_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), ser::SerializeError> {
+        self.start_struct(2)?;
+        self.serialize_struct_field(1, "type".into(), &type_id)?;
+        self.serialize_unsigned(2)?;
+        self.serialize_bytes(data)?;
+        self.end_struct()
+    }
+
+    // Whole seconds use the epoch tag, others the date/time string tag which
+    // keeps the nanoseconds exact.
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), ser::SerializeError> {
+        if value.nanoseconds == 0 {
+            self.write_head(MAJOR_TAG, TAG_EPOCH)?;
+            self.serialize_signed(value.seconds)
+        } else {
+            self.write_head(MAJOR_TAG, TAG_DATE_TIME)?;
+            self.serialize_str(&value.to_rfc3339().ok_or(ser::SerializeError)?)
+        }
+    }
+}
+
+// Half precision is only used when it holds the exact value.
+fn f64_to_f16(value: f64) -> Option<u16> {
+    if value.is_nan() {
+        return Some(0x7e00);
+    }
+    let single = value as f32;
+    if single as f64 != value {
+        return None;
+    }
+    let bits = single.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        return Some(sign | 0x7c00);
+    } else if exponent == 0 {
+        // Zero, single precision subnormals are too small for half precision.
+        return if mantissa == 0 { Some(sign) } else { None };
+    }
+
+    let exponent = exponent - 127;
+    if (-14..=15).contains(&exponent) {
+        if mantissa & 0x1fff != 0 {
+            return None;
+        }
+        Some(sign | (((exponent + 15) as u16) << 10) | (mantissa >> 13) as u16)
+    } else if (-24..-14).contains(&exponent) {
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (-1 - exponent) as u32;
+        if mantissa & ((1 << shift) - 1) != 0 {
+            return None;
+        }
+        Some(sign | (mantissa >> shift) as u16)
+    } else {
+        None
+    }
+}
+
+fn f16_to_f64(half: u16) -> f64 {
+    let exponent = (half >> 10) & 0x1f;
+    let mantissa = (half & 0x3ff) as f64;
+    let value = match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (mantissa + 1024.0) * 2f64.powi(exponent as i32 - 25),
+    };
+    if half & 0x8000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+pub struct Deserializer<R: io::Read> {
+    read: io::BufReader<R>,
+    scratch: Vec<u8>,
+    limits: de::LimitTracker,
+}
+
+impl<R: io::Read> Deserializer<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read: io::BufReader::new(read),
+            scratch: Vec::new(),
+            limits: de::LimitTracker::new(de::DeserializeLimits::default()),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: de::DeserializeLimits) -> Self {
+        self.limits = de::LimitTracker::new(limits);
+        self
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, de::DeserializeError> {
+        let mut byte = [0];
+        io::Read::read_exact(&mut self.read, &mut byte)?;
+        Ok(byte[0])
+    }
+
+    #[inline]
+    fn read_u16(&mut self) -> Result<u16, de::DeserializeError> {
+        let mut bytes = [0; 2];
+        io::Read::read_exact(&mut self.read, &mut bytes)?;
+        Ok(u16::from_be_bytes(bytes))
+    }
+
+    #[inline]
+    fn read_u32(&mut self) -> Result<u32, de::DeserializeError> {
+        let mut bytes = [0; 4];
+        io::Read::read_exact(&mut self.read, &mut bytes)?;
+        Ok(u32::from_be_bytes(bytes))
+    }
+
+    #[inline]
+    fn read_u64(&mut self) -> Result<u64, de::DeserializeError> {
+        let mut bytes = [0; 8];
+        io::Read::read_exact(&mut self.read, &mut bytes)?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn read_argument(&mut self, info: u8) -> Result<u64, de::DeserializeError> {
+        match info {
+            0..=23 => Ok(info as u64),
+            24 => Ok(self.read_u8()? as u64),
+            25 => Ok(self.read_u16()? as u64),
+            26 => Ok(self.read_u32()? as u64),
+            27 => self.read_u64(),
+            _ => Err(de::DeserializeError::ParsingError),
+        }
+    }
+
+    fn read_len(&mut self, info: u8) -> Result<usize, de::DeserializeError> {
+        usize::try_from(self.read_argument(info)?).map_err(|_| de::DeserializeError::ParsingError)
+    }
+
+    fn read_negative(&mut self, info: u8) -> Result<i64, de::DeserializeError> {
+        let value = self.read_argument(info)?;
+        i64::try_from(value)
+            .map(|value| -1 - value)
+            .map_err(|_| de::DeserializeError::IncompatibleNumericType)
+    }
+
+    fn read_float(&mut self, info: u8) -> Result<f64, de::DeserializeError> {
+        match info {
+            25 => Ok(f16_to_f64(self.read_u16()?)),
+            26 => Ok(f32::from_bits(self.read_u32()?) as f64),
+            27 => Ok(f64::from_bits(self.read_u64()?)),
+            _ => Err(de::DeserializeError::ParsingError),
+        }
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&[u8], de::DeserializeError> {
+        self.limits
+            .check_str_len(len)
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        let result = de::read_bounded(&mut self.read, len, &mut scratch);
+        self.scratch = scratch;
+        result.map(move |_| &self.scratch[..])
+    }
+
+    // Indefinite length strings are a sequence of definite length chunks of
+    // the same major type, the limits apply to their total length.
+    fn read_string(&mut self, major: u8, info: u8) -> Result<&[u8], de::DeserializeError> {
+        if info != INDEFINITE {
+            let len = self.read_len(info)?;
+            return self.read_bytes(len);
+        }
+
+        let mut string = std::mem::take(&mut self.scratch);
+        string.clear();
+        let result = self.read_chunks(major, &mut string);
+        self.scratch = string;
+        result.map(move |_| &self.scratch[..])
+    }
+
+    fn read_chunks(&mut self, major: u8, string: &mut Vec<u8>) -> Result<(), de::DeserializeError> {
+        loop {
+            let initial = self.read_u8()?;
+            if initial == BREAK {
+                return Ok(());
+            } else if initial >> 5 != major || initial & 0x1f == INDEFINITE {
+                return Err(de::DeserializeError::ParsingError);
+            }
+            let len = self.read_len(initial & 0x1f)?;
+            self.limits
+                .check_str_growth(string.len().saturating_add(len), len)
+                .map_err(de::DeserializeError::LimitExceeded)?;
+            de::read_bounded(&mut self.read, len, string)?;
+        }
+    }
+
+    fn read_text(&mut self, info: u8) -> Result<&str, de::DeserializeError> {
+        let bytes = self.read_string(MAJOR_TEXT, info)?;
+        std::str::from_utf8(bytes).map_err(|_| de::DeserializeError::ParsingError)
+    }
+
+    fn read_container_len(&mut self, info: u8) -> Result<Option<usize>, de::DeserializeError> {
+        if info == INDEFINITE {
+            Ok(None)
+        } else {
+            Ok(Some(self.read_len(info)?))
+        }
+    }
+
+    // Returns the initial byte of the next element, or `None` at the end of the
+    // container.
+    fn next_element(
+        &mut self,
+        len: Option<usize>,
+        index: usize,
+    ) -> Result<Option<u8>, de::DeserializeError> {
+        match len {
+            Some(len) if index == len => Ok(None),
+            Some(_) => Ok(Some(self.read_u8()?)),
+            None => match self.read_u8()? {
+                BREAK => Ok(None),
+                initial => Ok(Some(initial)),
+            },
+        }
+    }
+
+    fn enter_container(&mut self, len: Option<usize>) -> Result<(), de::DeserializeError> {
+        self.limits
+            .enter()
+            .and_then(|_| self.limits.check_collection_len(len.unwrap_or(0)))
+            .map_err(de::DeserializeError::LimitExceeded)
+    }
+
+    // Indefinite length containers are checked as their elements arrive.
+    fn check_element(
+        &mut self,
+        len: Option<usize>,
+        count: usize,
+    ) -> Result<(), de::DeserializeError> {
+        if len.is_none() {
+            self.limits
+                .check_collection_growth(count + 1)
+                .map_err(de::DeserializeError::LimitExceeded)?;
+        }
+        Ok(())
+    }
+
+    fn parse(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        let initial = self.read_u8()?;
+        self.parse_value(initial, visitor)
+    }
+
+    fn parse_value(
+        &mut self,
+        initial: u8,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let info = initial & 0x1f;
+        match initial >> 5 {
+            // Optional values only visit containers through the visitor of
+            // their content.
+            MAJOR_ARRAY | MAJOR_MAP if matches!(visitor.hint(), de::Hint::Option) => {
+                self.parse_value(initial, visitor.visit_some()?)
+            }
+            MAJOR_UNSIGNED => {
+                let value = self.read_argument(info)?;
+                visitor.visit_unsigned(value)
+            }
+            MAJOR_NEGATIVE => {
+                let value = self.read_negative(info)?;
+                visitor.visit_signed(value)
+            }
+            MAJOR_BYTES => self.parse_bytes(info, visitor),
+            MAJOR_TEXT => {
+                let s = self.read_text(info)?;
+                visitor.visit_str(s)
+            }
+            MAJOR_ARRAY => self.parse_array(info, visitor),
+            MAJOR_MAP => self.parse_map(info, visitor),
+            MAJOR_TAG => {
+                let tag = self.read_argument(info)?;
+                self.parse_tagged(tag, visitor)
+            }
+            _ => match info {
+                20 => visitor.visit_bool(false),
+                21 => visitor.visit_bool(true),
+                // Null and undefined.
+                22 | 23 => visitor.visit_null(),
+                25..=27 => {
+                    let value = self.read_float(info)?;
+                    visitor.visit_float(value)
+                }
+                // Unassigned simple values, or a break outside of a container.
+                _ => Err(de::DeserializeError::ParsingError),
+            },
+        }
+    }
+
+    fn parse_bytes(
+        &mut self,
+        info: u8,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let bytes = self.read_string(MAJOR_BYTES, info)?;
+        match visitor.visit_bytes(bytes) {
+            Err(de::DeserializeError::UnimplementedVisit) => {}
+            result => return result,
+        }
+        let mut builder = visitor.visit_seq(Some(bytes.len()))?;
+        for byte in bytes {
+            builder.element()?.visit_unsigned(*byte as u64)?;
+        }
+        builder.finish()
+    }
+
+    fn parse_array(
+        &mut self,
+        info: u8,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let len = self.read_container_len(info)?;
+        self.enter_container(len)?;
+        let mut builder = visitor.visit_seq(len)?;
+        let mut count = 0;
+        while let Some(initial) = self.next_element(len, count)? {
+            self.check_element(len, count)?;
+            self.parse_value(initial, builder.element()?)?;
+            count += 1;
+        }
+        self.limits.leave();
+        builder.finish()
+    }
+
+    fn parse_map(
+        &mut self,
+        info: u8,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let len = self.read_container_len(info)?;
+        self.enter_container(len)?;
+        let mut builder = visitor.visit_struct()?;
+        let mut null_visitor = de::NullVisitor;
+        let mut count = 0;
+        while let Some(initial) = self.next_element(len, count)? {
+            self.check_element(len, count)?;
+            let member = match initial >> 5 {
+                MAJOR_UNSIGNED => match u32::try_from(self.read_argument(initial & 0x1f)?) {
+                    Ok(id) => builder.member(Some(id), None),
+                    Err(_) => Err(de::DeserializeError::UnknownField),
+                },
+                MAJOR_TEXT => {
+                    let key = self.read_text(initial & 0x1f)?;
+                    let (id, name) = de::split_key(key);
+                    builder.member(id, name)
+                }
+                // Any other key is valid CBOR but can't name a field.
+                _ => {
+                    self.parse_value(initial, &mut null_visitor)?;
+                    Err(de::DeserializeError::UnknownField)
+                }
+            };
+            match member {
+                Ok(visitor) => self.parse(visitor)?,
+                Err(de::DeserializeError::UnknownField) => self.parse(&mut null_visitor)?,
+                Err(e) => Err(e)?,
+            }
+            count += 1;
+        }
+        self.limits.leave();
+        builder.finish()
+    }
+
+    fn visit_timestamp_or_else<F>(
+        visitor: &mut dyn de::Visitor,
+        timestamp: Timestamp,
+        fallback: F,
+    ) -> Result<(), de::DeserializeError>
+    where
+        F: FnOnce(&mut dyn de::Visitor) -> Result<(), de::DeserializeError>,
+    {
+        match visitor.visit_timestamp(timestamp) {
+            Err(de::DeserializeError::UnimplementedVisit) => fallback(visitor),
+            result => result,
+        }
+    }
+
+    fn parse_tagged(
+        &mut self,
+        tag: u64,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match tag {
+            TAG_DATE_TIME => {
+                let initial = self.read_u8()?;
+                if initial >> 5 != MAJOR_TEXT {
+                    return Err(de::DeserializeError::ParsingError);
+                }
+                let s = self.read_text(initial & 0x1f)?;
+                let timestamp =
+                    Timestamp::parse_rfc3339(s).ok_or(de::DeserializeError::ParsingError)?;
+                Self::visit_timestamp_or_else(visitor, timestamp, |visitor| visitor.visit_str(s))
+            }
+            TAG_EPOCH => {
+                let initial = self.read_u8()?;
+                let info = initial & 0x1f;
+                match initial >> 5 {
+                    MAJOR_UNSIGNED => {
+                        let value = self.read_argument(info)?;
+                        let timestamp = Timestamp {
+                            seconds: i64::try_from(value)
+                                .map_err(|_| de::DeserializeError::IncompatibleNumericType)?,
+                            nanoseconds: 0,
+                        };
+                        Self::visit_timestamp_or_else(visitor, timestamp, |visitor| {
+                            visitor.visit_unsigned(value)
+                        })
+                    }
+                    MAJOR_NEGATIVE => {
+                        let value = self.read_negative(info)?;
+                        let timestamp = Timestamp {
+                            seconds: value,
+                            nanoseconds: 0,
+                        };
+                        Self::visit_timestamp_or_else(visitor, timestamp, |visitor| {
+                            visitor.visit_signed(value)
+                        })
+                    }
+                    _ if (0xf9..=0xfb).contains(&initial) => {
+                        let value = self.read_float(info)?;
+                        if !value.is_finite() {
+                            return Err(de::DeserializeError::ParsingError);
+                        }
+                        let mut seconds = value.floor();
+                        let mut nanoseconds = ((value - seconds) * 1e9).round();
+                        if nanoseconds >= 1e9 {
+                            seconds += 1.0;
+                            nanoseconds = 0.0;
+                        }
+                        let timestamp = Timestamp {
+                            seconds: seconds as i64,
+                            nanoseconds: nanoseconds as u32,
+                        };
+                        Self::visit_timestamp_or_else(visitor, timestamp, |visitor| {
+                            visitor.visit_float(value)
+                        })
+                    }
+                    _ => Err(de::DeserializeError::ParsingError),
+                }
+            }
+            // Other tags (bignums, URIs...) are ignored and their content read
+            // as is. Nested tags count towards the depth limit.
+            _ => {
+                self.limits
+                    .enter()
+                    .map_err(de::DeserializeError::LimitExceeded)?;
+                let result = self.parse(visitor);
+                self.limits.leave();
+                result
+            }
+        }
+    }
+}
+
+impl<R: io::Read> de::Deserializer for Deserializer<R> {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        self.parse(visitor)
+    }
+}
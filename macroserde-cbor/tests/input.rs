@@ -0,0 +1,227 @@
+// Handwritten CBOR: tags, indefinite lengths and the limits on both.
+
+use fixtures::{Contact, Date, Month};
+use macroserde::de::*;
+use macroserde::ext::Timestamp;
+use macroserde::ser::Serialize;
+
+fn decode<T: Deserialize>(bytes: &[u8]) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_cbor::Deserializer::new(bytes))
+}
+
+fn decode_with<T: Deserialize>(
+    bytes: &[u8],
+    limits: DeserializeLimits,
+) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_cbor::Deserializer::new(bytes).with_limits(limits))
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut ser = macroserde_cbor::Serializer::new(Vec::new());
+    value.serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+fn birthday() -> Date {
+    Date {
+        day: 19,
+        month: Month::October,
+        year: 1993,
+    }
+}
+
+const DATE: &[u8] = &[0xa3, 0x01, 0x13, 0x02, 0x0a, 0x03, 0x19, 0x07, 0xc9];
+
+#[test]
+fn keys() {
+    assert_eq!(decode::<Date>(DATE).unwrap(), birthday());
+
+    // Field names, and keys that give both.
+    let mut named = vec![0xa3, 0x63];
+    named.extend_from_slice(b"day");
+    named.extend_from_slice(&[0x13, 0x67]);
+    named.extend_from_slice(b"2:month");
+    named.extend_from_slice(&[0x63]);
+    named.extend_from_slice(b"OCT");
+    named.extend_from_slice(&[0x64]);
+    named.extend_from_slice(b"year");
+    named.extend_from_slice(&[0x19, 0x07, 0xc9]);
+    assert_eq!(decode::<Date>(&named).unwrap(), birthday());
+
+    // Keys that can't name a field are skipped with their value.
+    let mut skipped = vec![0xa4, 0xf5, 0x82, 0x01, 0x02];
+    skipped.extend_from_slice(&DATE[1..]);
+    assert_eq!(decode::<Date>(&skipped).unwrap(), birthday());
+}
+
+#[test]
+fn optional_struct() {
+    let mut some = vec![0xa3, 0x01, 0x66];
+    some.extend_from_slice(b"Steven");
+    some.push(0x03);
+    some.extend_from_slice(DATE);
+    some.extend_from_slice(&[0x18, 0x58, 0x60]);
+    let contact = decode::<Contact>(&some).unwrap();
+    assert_eq!(contact.birth_date, Some(birthday()));
+    assert_eq!(contact.car_brand, Some(String::new()));
+
+    let none = [0xa3, 0x01, 0x60, 0x03, 0xf6, 0x18, 0x58, 0xf6];
+    assert_eq!(decode::<Contact>(&none).unwrap(), Contact::default());
+}
+
+#[test]
+fn indefinite_lengths() {
+    assert_eq!(
+        decode::<Vec<u8>>(&[0x9f, 0x01, 0x02, 0xff]).unwrap(),
+        vec![1, 2]
+    );
+
+    let mut map = vec![0xbf];
+    map.extend_from_slice(&DATE[1..]);
+    map.push(0xff);
+    assert_eq!(decode::<Date>(&map).unwrap(), birthday());
+
+    let mut ser = macroserde_cbor::Serializer::new(Vec::new()).with_indefinite_lengths(true);
+    birthday().serialize(&mut ser).unwrap();
+    assert_eq!(ser.finish().unwrap(), map);
+
+    let text = [0x7f, 0x62, b'a', b'b', 0x60, 0x61, b'c', 0xff];
+    assert_eq!(decode::<String>(&text).unwrap(), "abc");
+
+    // Chunks must be definite strings of the same type.
+    let mixed = [0x7f, 0x41, b'a', 0xff];
+    assert!(matches!(
+        decode::<String>(&mixed),
+        Err(DeserializeError::ParsingError)
+    ));
+    let nested = [0x7f, 0x7f, 0xff, 0xff];
+    assert!(matches!(
+        decode::<String>(&nested),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn timestamp_tags() {
+    let seconds = Timestamp {
+        seconds: 1_600_000_000,
+        nanoseconds: 0,
+    };
+    let epoch = [0xc1, 0x1a, 0x5f, 0x5e, 0x10, 0x00];
+    assert_eq!(decode::<Timestamp>(&epoch).unwrap(), seconds);
+    assert_eq!(encode(&seconds), epoch);
+
+    let mut date_time = vec![0xc0, 0x74];
+    date_time.extend_from_slice(b"2020-09-13T12:26:40Z");
+    assert_eq!(decode::<Timestamp>(&date_time).unwrap(), seconds);
+
+    let float = [0xc1, 0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0];
+    assert_eq!(
+        decode::<Timestamp>(&float).unwrap(),
+        Timestamp {
+            seconds: 1,
+            nanoseconds: 500_000_000,
+        }
+    );
+
+    // Nanoseconds are kept exact by the date/time string.
+    let exact = Timestamp {
+        nanoseconds: 5,
+        ..seconds
+    };
+    let mut expected = vec![0xc0, 0x78, 0x1e];
+    expected.extend_from_slice(b"2020-09-13T12:26:40.000000005Z");
+    assert_eq!(encode(&exact), expected);
+    assert_eq!(decode::<Timestamp>(&expected).unwrap(), exact);
+
+    let far = Timestamp {
+        seconds: 400_000_000_000,
+        nanoseconds: 1,
+    };
+    let mut ser = macroserde_cbor::Serializer::new(Vec::new());
+    assert!(far.serialize(&mut ser).is_err());
+
+    // Visitors that don't take timestamps get the tagged value.
+    assert_eq!(decode::<u64>(&epoch).unwrap(), 1_600_000_000);
+    assert_eq!(
+        decode::<String>(&date_time).unwrap(),
+        "2020-09-13T12:26:40Z"
+    );
+
+    assert!(matches!(
+        decode::<Timestamp>(&[0xc0, 0x01]),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn other_tags() {
+    // A URI and a bignum tag are read as their content.
+    let mut uri = vec![0xd8, 0x20, 0x63];
+    uri.extend_from_slice(b"a:b");
+    assert_eq!(decode::<String>(&uri).unwrap(), "a:b");
+    assert_eq!(
+        decode::<Vec<u8>>(&[0xc2, 0x42, 0x01, 0x00]).unwrap(),
+        vec![1, 0]
+    );
+
+    let mut tagged = vec![0xd9, 0xd9, 0xf7];
+    tagged.extend_from_slice(DATE);
+    assert_eq!(decode::<Date>(&tagged).unwrap(), birthday());
+}
+
+#[test]
+fn limits() {
+    // Nested unknown tags count towards the depth.
+    let mut tags = vec![0xc6; 1_000_000];
+    tags.push(0x01);
+    assert!(matches!(
+        decode::<u8>(&tags),
+        Err(DeserializeError::LimitExceeded(Limit::Depth))
+    ));
+
+    // Indefinite length containers are limited as they grow.
+    let limits = DeserializeLimits {
+        max_collection_len: 2,
+        ..DeserializeLimits::default()
+    };
+    assert_eq!(
+        decode_with::<Vec<u8>>(&[0x9f, 0x01, 0x02, 0xff], limits).unwrap(),
+        vec![1, 2]
+    );
+    assert!(matches!(
+        decode_with::<Vec<u8>>(&[0x9f, 0x01, 0x02, 0x03, 0xff], limits),
+        Err(DeserializeError::LimitExceeded(Limit::CollectionLen))
+    ));
+    let mut map = vec![0xbf];
+    map.extend_from_slice(&DATE[1..]);
+    map.push(0xff);
+    assert!(matches!(
+        decode_with::<Date>(&map, limits),
+        Err(DeserializeError::LimitExceeded(Limit::CollectionLen))
+    ));
+
+    // Elements of an endless array count towards the total.
+    let limits = DeserializeLimits {
+        max_total_alloc: 1000,
+        ..DeserializeLimits::default()
+    };
+    let mut endless = vec![0x9f];
+    endless.resize(2001, 0xf6);
+    assert!(matches!(
+        decode_with::<Vec<Option<u8>>>(&endless, limits),
+        Err(DeserializeError::LimitExceeded(Limit::TotalAlloc))
+    ));
+
+    // The total length of an indefinite length string is limited, not only
+    // the length of its chunks.
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    let chunks = [0x7f, 0x62, b'a', b'b', 0x62, b'c', b'd', 0x61, b'e', 0xff];
+    assert!(matches!(
+        decode_with::<String>(&chunks, limits),
+        Err(DeserializeError::LimitExceeded(Limit::StrLen))
+    ));
+}
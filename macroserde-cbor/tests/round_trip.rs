@@ -0,0 +1,47 @@
+// Round trips of the README types.
+
+use fixtures::{contacts, person, Contact, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use std::fmt::Debug;
+
+fn encode<T: Serialize>(value: &T, indefinite_lengths: bool) -> Vec<u8> {
+    let mut ser =
+        macroserde_cbor::Serializer::new(Vec::new()).with_indefinite_lengths(indefinite_lengths);
+    value.serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    for &indefinite_lengths in &[false, true] {
+        let encoded = encode(&value, indefinite_lengths);
+        let decoded = T::deserialize(&mut macroserde_cbor::Deserializer::new(&encoded[..]));
+        assert_eq!(decoded.unwrap(), value, "decoding {:?}", encoded);
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    check::<Vec<Contact>>(contacts());
+}
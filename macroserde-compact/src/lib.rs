@@ -0,0 +1,355 @@
+use macroserde::de::Hint;
+use macroserde::ext::Timestamp;
+use macroserde::{de, ser};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io;
+use std::io::Write;
+
+// Values are written without any type information or field keys, reading them
+// back relies on the hints of the visitors:
+//
+// - integers are varints, zigzag encoded when signed, and floats are 8 bytes
+//   little endian;
+// - strings, bytes and sequences are prefixed by their length;
+// - struct fields are written one after the other in ID order;
+// - enumerations are their ID and unions the ID of the variant followed by its
+//   value;
+// - null is `0`, a `Some` is `1` followed by its value.
+pub struct Serializer<W: io::Write> {
+    write: io::BufWriter<W>,
+    // Struct fields are encoded in these buffers, then written sorted by ID
+    // when the struct ends.
+    buffers: Vec<Vec<u8>>,
+    // Encoded fields of each open struct along with their ID.
+    fields: Vec<Vec<(u32, Vec<u8>)>>,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(w: W) -> Self {
+        Self {
+            write: io::BufWriter::new(w),
+            buffers: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), ser::SerializeError> {
+        self.write.flush()?;
+        Ok(())
+    }
+
+    // Dropping the serializer also flushes it but any write error is lost.
+    pub fn finish(self) -> io::Result<W> {
+        self.write
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+    }
+
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), ser::SerializeError> {
+        match self.buffers.last_mut() {
+            Some(buffer) => buffer.extend_from_slice(bytes),
+            None => self.write.write_all(bytes)?,
+        }
+        Ok(())
+    }
+
+    fn write_varint(&mut self, mut value: u64) -> Result<(), ser::SerializeError> {
+        let mut bytes = [0; 10];
+        let mut len = 0;
+        while value >= 0x80 {
+            bytes[len] = value as u8 | 0x80;
+            value >>= 7;
+            len += 1;
+        }
+        bytes[len] = value as u8;
+        self.write_all(&bytes[..=len])
+    }
+}
+
+impl<W: io::Write> ser::Serializer for Serializer<W> {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.write_all(&[0])
+    }
+
+    fn serialize_some(&mut self) -> Result<(), ser::SerializeError> {
+        self.write_all(&[1])
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.write_all(&[value as u8])
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        self.write_varint(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.write_varint(value)
+    }
+
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.serialize_bytes(value.as_bytes())
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), ser::SerializeError> {
+        self.write_varint(value.len() as u64)?;
+        self.write_all(value)
+    }
+
+    fn serialize_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), ser::SerializeError> {
+        self.write_all(&[type_id as u8])?;
+        self.serialize_bytes(data)
+    }
+
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), ser::SerializeError> {
+        self.serialize_signed(value.seconds)?;
+        self.write_varint(value.nanoseconds as u64)
+    }
+
+    fn serialize_enum(
+        &mut self,
+        value: u32,
+        _name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.write_varint(value as u64)
+    }
+
+    fn serialize_union(
+        &mut self,
+        variant_id: u32,
+        _variant_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        self.write_varint(variant_id as u64)?;
+        value.serialize(self)
+    }
+
+    fn start_struct(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.fields.push(Vec::with_capacity(len));
+        Ok(())
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        _field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        self.buffers.push(Vec::new());
+        value.serialize(self)?;
+        let value = self.buffers.pop().unwrap();
+        self.fields.last_mut().unwrap().push((field_id, value));
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        let mut fields = self.fields.pop().unwrap();
+        fields.sort_by_key(|(id, _)| *id);
+        for (_, value) in fields {
+            self.write_all(&value)?;
+        }
+        Ok(())
+    }
+
+    fn start_seq(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.write_varint(len as u64)
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        Ok(())
+    }
+}
+
+pub struct Deserializer<R: io::Read> {
+    read: io::BufReader<R>,
+    scratch: Vec<u8>,
+    limits: de::LimitTracker,
+}
+
+impl<R: io::Read> Deserializer<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read: io::BufReader::new(read),
+            scratch: Vec::new(),
+            limits: de::LimitTracker::new(de::DeserializeLimits::default()),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: de::DeserializeLimits) -> Self {
+        self.limits = de::LimitTracker::new(limits);
+        self
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, de::DeserializeError> {
+        let mut byte = [0];
+        io::Read::read_exact(&mut self.read, &mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn read_varint(&mut self) -> Result<u64, de::DeserializeError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(de::DeserializeError::ParsingError)
+    }
+
+    fn read_signed(&mut self) -> Result<i64, de::DeserializeError> {
+        let value = self.read_varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn read_len(&mut self) -> Result<usize, de::DeserializeError> {
+        usize::try_from(self.read_varint()?).map_err(|_| de::DeserializeError::ParsingError)
+    }
+
+    fn read_bytes(&mut self) -> Result<&[u8], de::DeserializeError> {
+        let len = self.read_len()?;
+        self.limits
+            .check_str_len(len)
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        self.scratch.clear();
+        de::read_bounded(&mut self.read, len, &mut self.scratch)?;
+        Ok(&self.scratch)
+    }
+
+    fn enter(&mut self) -> Result<(), de::DeserializeError> {
+        self.limits
+            .enter()
+            .map_err(de::DeserializeError::LimitExceeded)
+    }
+
+    fn parse(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        match visitor.hint() {
+            Hint::Any => Err(de::DeserializeError::MissingHint),
+            Hint::Null => match self.read_u8()? {
+                0 => visitor.visit_null(),
+                _ => Err(de::DeserializeError::ParsingError),
+            },
+            Hint::Option => match self.read_u8()? {
+                0 => visitor.visit_null(),
+                1 => self.parse(visitor.visit_some()?),
+                _ => Err(de::DeserializeError::ParsingError),
+            },
+            Hint::Bool => match self.read_u8()? {
+                0 => visitor.visit_bool(false),
+                1 => visitor.visit_bool(true),
+                _ => Err(de::DeserializeError::ParsingError),
+            },
+            Hint::Signed => visitor.visit_signed(self.read_signed()?),
+            Hint::Unsigned => visitor.visit_unsigned(self.read_varint()?),
+            Hint::Float => {
+                let mut bytes = [0; 8];
+                io::Read::read_exact(&mut self.read, &mut bytes)?;
+                visitor.visit_float(f64::from_le_bytes(bytes))
+            }
+            Hint::Str => {
+                let bytes = self.read_bytes()?;
+                let s =
+                    std::str::from_utf8(bytes).map_err(|_| de::DeserializeError::ParsingError)?;
+                visitor.visit_str(s)
+            }
+            Hint::Bytes => visitor.visit_bytes(self.read_bytes()?),
+            Hint::Ext => {
+                let type_id = self.read_u8()? as i8;
+                visitor.visit_ext(type_id, self.read_bytes()?)
+            }
+            Hint::Timestamp => {
+                let seconds = self.read_signed()?;
+                let nanoseconds = u32::try_from(self.read_varint()?)
+                    .map_err(|_| de::DeserializeError::ParsingError)?;
+                visitor.visit_timestamp(Timestamp {
+                    seconds,
+                    nanoseconds,
+                })
+            }
+            Hint::Enum => {
+                let id = u32::try_from(self.read_varint()?)
+                    .map_err(|_| de::DeserializeError::UnknownEnumVariant)?;
+                visitor.visit_enum(Some(id), None)
+            }
+            Hint::Seq(expected_len) => self.parse_seq(expected_len, visitor),
+            Hint::Struct(ids) => self.parse_struct(ids, visitor),
+            Hint::Union(_) => {
+                let id = u32::try_from(self.read_varint()?)
+                    .map_err(|_| de::DeserializeError::UnknownUnionVariant)?;
+                self.enter()?;
+                let mut builder = visitor.visit_struct()?;
+                self.parse(builder.member(Some(id), None)?)?;
+                builder.finish()?;
+                self.limits.leave();
+                Ok(())
+            }
+        }
+    }
+
+    fn parse_seq(
+        &mut self,
+        expected_len: Option<usize>,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let len = self.read_len()?;
+        if matches!(expected_len, Some(expected_len) if expected_len != len) {
+            return Err(de::DeserializeError::ParsingError);
+        }
+        self.enter()?;
+        self.limits
+            .check_collection_len(len)
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        let mut builder = visitor.visit_seq(Some(len))?;
+        for _ in 0..len {
+            self.parse(builder.element()?)?;
+        }
+        builder.finish()?;
+        self.limits.leave();
+        Ok(())
+    }
+
+    fn parse_struct(
+        &mut self,
+        ids: &[u32],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let sorted_ids;
+        let ids = if ids.windows(2).all(|pair| pair[0] < pair[1]) {
+            ids
+        } else {
+            let mut ids = ids.to_vec();
+            ids.sort_unstable();
+            sorted_ids = ids;
+            &sorted_ids
+        };
+
+        self.enter()?;
+        let mut builder = visitor.visit_struct()?;
+        for id in ids {
+            self.parse(builder.member(Some(*id), None)?)?;
+        }
+        builder.finish()?;
+        self.limits.leave();
+        Ok(())
+    }
+}
+
+impl<R: io::Read> de::Deserializer for Deserializer<R> {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        self.parse(visitor)
+    }
+}
@@ -0,0 +1,133 @@
+// Handwritten input, whose layout only the hints of the visitors give.
+
+use fixtures::{contacts, person, Contact, Date, Month, Person};
+use macroserde::de::*;
+use macroserde::ext::Timestamp;
+use macroserde::ser::*;
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut ser = macroserde_compact::Serializer::new(Vec::new());
+    value.serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+fn decode<T: Deserialize>(bytes: &[u8]) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_compact::Deserializer::new(bytes))
+}
+
+// Day, month and year, without keys.
+const DATE: &[u8] = &[0x13, 0x0a, 0xc9, 0x0f];
+
+#[test]
+fn readme_person() {
+    // Fields in ID order: name, zigzag age, birth date, pets, height,
+    // is_cool, occupation and car_brand (88).
+    let mut bytes = vec![0x06];
+    bytes.extend_from_slice(b"Steven");
+    bytes.push(0x36);
+    bytes.extend_from_slice(DATE);
+    bytes.extend_from_slice(&[0x02, 0x07]);
+    bytes.extend_from_slice(b"Bouboul");
+    bytes.push(0x0e);
+    bytes.extend_from_slice(b"Monsieur Puppy");
+    bytes.push(0x01);
+    bytes.extend_from_slice(&(1.735f32 as f64).to_le_bytes());
+    bytes.extend_from_slice(&[0x01, 0x02, 0x08]);
+    bytes.extend_from_slice(b"Engineer");
+    bytes.push(0x00);
+    assert_eq!(decode::<Person>(&bytes).unwrap(), person());
+    assert_eq!(encode(&person()), bytes);
+}
+
+#[test]
+fn options() {
+    let mut some = vec![0x06];
+    some.extend_from_slice(b"Steven");
+    some.push(0x01);
+    some.extend_from_slice(DATE);
+    some.extend_from_slice(&[0x01, 0x00]);
+    assert_eq!(decode::<Contact>(&some).unwrap(), contacts()[0]);
+
+    assert_eq!(
+        decode::<Contact>(&[0x00, 0x00, 0x00]).unwrap(),
+        contacts()[1]
+    );
+    assert!(matches!(
+        decode::<Option<u8>>(&[0x02, 0x00]),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn arrays() {
+    assert_eq!(
+        decode::<[u8; 3]>(&[0x03, 0x01, 0x02, 0x03]).unwrap(),
+        [1, 2, 3]
+    );
+    assert!(matches!(
+        decode::<[u8; 3]>(&[0x02, 0x01, 0x02]),
+        Err(DeserializeError::ParsingError)
+    ));
+    let dates = [
+        Date {
+            day: 19,
+            month: Month::October,
+            year: 1993,
+        },
+        Date::default(),
+    ];
+    let mut bytes = vec![0x02];
+    bytes.extend_from_slice(DATE);
+    bytes.extend_from_slice(&[0x00, 0x01, 0x00]);
+    assert_eq!(decode::<[Date; 2]>(&bytes).unwrap(), dates);
+    assert_eq!(encode(&dates), bytes);
+}
+
+#[test]
+fn scalars() {
+    assert_eq!(decode::<i64>(&[0x03]).unwrap(), -2);
+    assert_eq!(decode::<u32>(&[0x96, 0x01]).unwrap(), 150);
+    assert!(matches!(
+        decode::<bool>(&[0x02]),
+        Err(DeserializeError::ParsingError)
+    ));
+    assert!(matches!(
+        decode::<Month>(&[0x0d]),
+        Err(DeserializeError::UnknownEnumVariant)
+    ));
+    assert_eq!(
+        decode::<Timestamp>(&[0x01, 0x05]).unwrap(),
+        Timestamp {
+            seconds: -1,
+            nanoseconds: 5,
+        }
+    );
+    // Varints are at most 10 bytes long.
+    assert!(matches!(
+        decode::<u64>(&[0xff; 11]),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    let encoded = [0x05, b'a', b'b', b'c', b'd', b'e'];
+    let mut de = macroserde_compact::Deserializer::new(&encoded[..]).with_limits(limits);
+    assert!(matches!(
+        String::deserialize(&mut de),
+        Err(DeserializeError::LimitExceeded(Limit::StrLen))
+    ));
+
+    // A large length isn't allocated upfront.
+    let huge = [0x80, 0x80, 0x80, 0x80, 0x7f, b'x'];
+    assert!(decode::<String>(&huge).is_err());
+    let huge = [0xff, 0xff, 0xff, 0xff, 0x0f];
+    assert!(matches!(
+        decode::<Vec<u8>>(&huge),
+        Err(DeserializeError::LimitExceeded(Limit::CollectionLen))
+    ));
+}
@@ -0,0 +1,46 @@
+// Round trips of the README types.
+
+use fixtures::{contacts, person, Contact, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use std::fmt::Debug;
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    let mut ser = macroserde_compact::Serializer::new(Vec::new());
+    value.serialize(&mut ser).unwrap();
+    let encoded = ser.finish().unwrap();
+    let decoded = T::deserialize(&mut macroserde_compact::Deserializer::new(&encoded[..]));
+    assert_eq!(decoded.unwrap(), value, "decoding {:?}", encoded);
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    check::<Vec<Contact>>(contacts());
+}
+
+#[test]
+fn arrays() {
+    check([person(), Person::default()]);
+    check::<[u8; 0]>([]);
+}
@@ -0,0 +1,261 @@
+mod parser;
+
+use macroserde::de::{DeserializeLimits, Hint};
+use macroserde::ext::Timestamp;
+use macroserde::{de, ser};
+use parser::{Cell, Parser, Record};
+use std::borrow::Cow;
+use std::io;
+use std::io::Write;
+
+pub use parser::{ParseError, ParseErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderRepr {
+    /// `carBrand`.
+    Name,
+    /// `10:carBrand`.
+    IdAndName,
+}
+
+// Writes a sequence of flat structs, one record per struct after a header
+// naming the fields of the first one. Fields must be scalars, null is an empty
+// cell and enumerations are written by name.
+pub struct Serializer<W: io::Write> {
+    write: io::BufWriter<W>,
+    header_repr: HeaderRepr,
+    // 0 outside the sequence, 1 in the sequence, 2 in a record.
+    depth: usize,
+    header: Vec<(u32, Cow<'static, str>)>,
+    records_written: usize,
+    record: Vec<String>,
+    cell: Option<String>,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(w: W) -> Self {
+        Self {
+            write: io::BufWriter::new(w),
+            header_repr: HeaderRepr::Name,
+            depth: 0,
+            header: Vec::new(),
+            records_written: 0,
+            record: Vec::new(),
+            cell: None,
+        }
+    }
+
+    pub fn with_header_repr(mut self, header_repr: HeaderRepr) -> Self {
+        self.header_repr = header_repr;
+        self
+    }
+
+    pub fn flush(&mut self) -> Result<(), ser::SerializeError> {
+        self.write.flush()?;
+        Ok(())
+    }
+
+    // Dropping the serializer also flushes it but any write error is lost.
+    pub fn finish(self) -> io::Result<W> {
+        self.write
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+    }
+
+    fn set_cell(&mut self, cell: String) -> Result<(), ser::SerializeError> {
+        if self.depth != 2 {
+            return Err(ser::SerializeError);
+        }
+        self.cell = Some(cell);
+        Ok(())
+    }
+
+    // Empty strings are quoted to tell them apart from null.
+    fn quote(s: &str) -> String {
+        if !s.is_empty() && !s.contains(&[',', '"', '\r', '\n'][..]) {
+            return s.to_owned();
+        }
+        format!("\"{}\"", s.replace('"', "\"\""))
+    }
+
+    fn write_record(&mut self, cells: &[String]) -> Result<(), ser::SerializeError> {
+        self.write.write_all(cells.join(",").as_bytes())?;
+        self.write.write_all(b"\r\n")?;
+        Ok(())
+    }
+}
+
+impl<W: io::Write> ser::Serializer for Serializer<W> {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.set_cell(String::new())
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.set_cell(value.to_string())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        self.set_cell(value.to_string())
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.set_cell(value.to_string())
+    }
+
+    // `1e300` rather than the 301 digits `Display` writes.
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.set_cell(format!("{:?}", value))
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.set_cell(Self::quote(value))
+    }
+
+    fn serialize_enum(
+        &mut self,
+        _value: u32,
+        name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.set_cell(Self::quote(&name))
+    }
+
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), ser::SerializeError> {
+        self.set_cell(value.to_rfc3339().ok_or(ser::SerializeError)?)
+    }
+
+    fn start_struct(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        if self.depth != 1 {
+            return Err(ser::SerializeError);
+        }
+        self.depth = 2;
+        Ok(())
+    }
+
+    // Every record must have the fields of the first one, in the same order.
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        let index = self.record.len();
+        if self.records_written == 0 {
+            self.header.push((field_id, field_name));
+        } else if self.header.get(index).map(|field| field.0) != Some(field_id) {
+            return Err(ser::SerializeError);
+        }
+        value.serialize(self)?;
+        let cell = self.cell.take().ok_or(ser::SerializeError)?;
+        self.record.push(cell);
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        if self.record.len() != self.header.len() {
+            return Err(ser::SerializeError);
+        }
+        if self.records_written == 0 {
+            let header: Vec<String> = self
+                .header
+                .iter()
+                .map(|(id, name)| match self.header_repr {
+                    HeaderRepr::Name => Self::quote(name),
+                    HeaderRepr::IdAndName => Self::quote(&format!("{}:{}", id, name)),
+                })
+                .collect();
+            self.write_record(&header)?;
+        }
+        let record = std::mem::take(&mut self.record);
+        self.write_record(&record)?;
+        self.records_written += 1;
+        self.depth = 1;
+        Ok(())
+    }
+
+    fn start_seq(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        if self.depth != 0 {
+            return Err(ser::SerializeError);
+        }
+        self.depth = 1;
+        Ok(())
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        self.depth = 0;
+        Ok(())
+    }
+}
+
+pub struct Deserializer {
+    records: Vec<Record>,
+}
+
+impl Deserializer {
+    pub fn new(s: &str) -> Result<Self, ParseError> {
+        Self::with_limits(s, DeserializeLimits::default())
+    }
+
+    pub fn with_limits(s: &str, limits: DeserializeLimits) -> Result<Self, ParseError> {
+        Ok(Self {
+            records: Parser::new(s, limits).parse()?,
+        })
+    }
+
+    fn visit_record(
+        header: &[(Option<u32>, Option<&str>)],
+        record: &Record,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_struct()?;
+        for ((id, name), cell) in header.iter().zip(&record.cells) {
+            match builder.member(*id, *name) {
+                Ok(visitor) => Self::visit_cell(cell, visitor).map_err(|e| e.at(cell.position))?,
+                Err(de::DeserializeError::UnknownField) => {}
+                Err(e) => Err(e.at(cell.position))?,
+            }
+        }
+        builder.finish()
+    }
+
+    // Cells are text, the visitor's hint tells what to convert them to.
+    fn visit_cell(cell: &Cell, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        if cell.text.is_empty() {
+            if cell.quoted && visitor.hint() == Hint::Option {
+                return Self::visit_cell(cell, visitor.visit_some()?);
+            }
+            if !cell.quoted {
+                match visitor.visit_null() {
+                    Err(de::DeserializeError::UnimplementedVisit) => {}
+                    result => return result,
+                }
+            }
+        }
+        de::visit_hinted_str(visitor, &cell.text)
+    }
+}
+
+impl de::Deserializer for Deserializer {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        let (header, records) = match self.records.split_first() {
+            Some((header, records)) => (header.cells.as_slice(), records),
+            None => (&[][..], &[][..]),
+        };
+        let header: Vec<_> = header
+            .iter()
+            .map(|cell| de::split_key(&cell.text))
+            .collect();
+        let mut builder = visitor.visit_seq(Some(records.len()))?;
+        for record in records {
+            Self::visit_record(&header, record, builder.element()?)
+                .map_err(|e| e.at(record.position))?;
+        }
+        builder.finish()
+    }
+}
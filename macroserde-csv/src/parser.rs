@@ -0,0 +1,268 @@
+use macroserde::de::{DeserializeLimits, Limit, LimitTracker, Position};
+use std::fmt;
+
+pub struct Cell {
+    pub text: String,
+    // Quoted cells are always strings, an unquoted empty cell is null.
+    pub quoted: bool,
+    pub position: Position,
+}
+
+pub struct Record {
+    pub cells: Vec<Cell>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    // A record doesn't have as many cells as the header.
+    CellCount { expected: usize, found: usize },
+    LimitExceeded(Limit),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+    // The source line containing the error.
+    pub snippet: String,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::CellCount { expected, found } => {
+                write!(f, "expected {} cells, found {}", expected, found)
+            }
+            ParseErrorKind::LimitExceeded(Limit::Depth) => write!(f, "too deeply nested"),
+            ParseErrorKind::LimitExceeded(Limit::StrLen) => write!(f, "cell too long"),
+            ParseErrorKind::LimitExceeded(Limit::CollectionLen) => {
+                write!(f, "too many cells or records")
+            }
+            ParseErrorKind::LimitExceeded(Limit::TotalAlloc) => write!(f, "input too large"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.position.line, self.position.column
+        )?;
+        writeln!(f, "{}", self.snippet)?;
+        let caret_offset: String = self
+            .snippet
+            .chars()
+            .take(self.position.column - 1)
+            .map(|c| if c == '\t' { c } else { ' ' })
+            .collect();
+        write!(f, "{}^", caret_offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    index: usize,
+    line: usize,
+    column: usize,
+    line_start: usize,
+    limits: LimitTracker,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(src: &'a str, limits: DeserializeLimits) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            index: 0,
+            line: 1,
+            column: 1,
+            line_start: 0,
+            limits: LimitTracker::new(limits),
+        }
+    }
+
+    // Blank lines are skipped, the first record is the header.
+    pub fn parse(mut self) -> Result<Vec<Record>, ParseError> {
+        let mut records: Vec<Record> = Vec::new();
+        loop {
+            match records.first() {
+                // With a single column, a blank line is a record holding an
+                // empty cell, only the end of the previous record is skipped.
+                Some(header) if header.cells.len() == 1 => {
+                    if self.peek() == Some(b'\r') {
+                        self.advance();
+                    }
+                    if self.peek() == Some(b'\n') {
+                        self.advance();
+                    }
+                }
+                _ => {
+                    while let Some(b'\r') | Some(b'\n') = self.peek() {
+                        self.advance();
+                    }
+                }
+            }
+            if self.peek().is_none() {
+                break;
+            }
+            let record = self.parse_record()?;
+            if let Some(header) = records.first() {
+                if record.cells.len() != header.cells.len() {
+                    let kind = ParseErrorKind::CellCount {
+                        expected: header.cells.len(),
+                        found: record.cells.len(),
+                    };
+                    return Err(self.error_at(kind, record.position));
+                }
+            }
+            records.push(record);
+        }
+        let limit = self.limits.check_collection_len(records.len());
+        self.check_limit(limit)?;
+        Ok(records)
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        let line_end = self.src[self.line_start..]
+            .find('\n')
+            .map(|i| self.line_start + i)
+            .unwrap_or(self.src.len());
+        ParseError {
+            kind,
+            position: self.position(),
+            snippet: self.src[self.line_start..line_end]
+                .trim_end_matches('\r')
+                .to_owned(),
+        }
+    }
+
+    // The snippet is the line of the position, which can be before the
+    // current one when a quoted cell spans several lines.
+    fn error_at(&self, kind: ParseErrorKind, position: Position) -> ParseError {
+        let snippet = self
+            .src
+            .lines()
+            .nth(position.line - 1)
+            .unwrap_or("")
+            .trim_end_matches('\r')
+            .to_owned();
+        ParseError {
+            kind,
+            position,
+            snippet,
+        }
+    }
+
+    fn check_limit(&self, result: Result<(), Limit>) -> Result<(), ParseError> {
+        result.map_err(|limit| self.error(ParseErrorKind::LimitExceeded(limit)))
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.src[self.index..].chars().next() {
+            Some(c) => self.error(ParseErrorKind::UnexpectedChar(c)),
+            None => self.error(ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.index).copied()
+    }
+
+    #[inline]
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.index + offset).copied()
+    }
+
+    fn advance(&mut self) {
+        let byte = self.bytes[self.index];
+        self.index += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+            self.line_start = self.index;
+        } else if byte & 0xc0 != 0x80 {
+            // Columns count characters, not UTF-8 continuation bytes.
+            self.column += 1;
+        }
+    }
+
+    fn parse_record(&mut self) -> Result<Record, ParseError> {
+        let position = self.position();
+        let mut cells = Vec::new();
+        loop {
+            cells.push(self.parse_cell()?);
+            match (self.peek(), self.peek_at(1)) {
+                (Some(b','), _) => self.advance(),
+                (Some(b'\r'), Some(b'\n')) | (Some(b'\n'), _) | (None, _) => break,
+                _ => return Err(self.unexpected()),
+            }
+        }
+        let limit = self.limits.check_collection_len(cells.len());
+        self.check_limit(limit)?;
+        Ok(Record { cells, position })
+    }
+
+    fn parse_cell(&mut self) -> Result<Cell, ParseError> {
+        let position = self.position();
+        let quoted = self.peek() == Some(b'"');
+        let text = if quoted {
+            self.advance();
+            let mut text = String::new();
+            let mut start = self.index;
+            loop {
+                match self.peek() {
+                    None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                    // A doubled quote is a literal quote.
+                    Some(b'"') if self.peek_at(1) == Some(b'"') => {
+                        self.advance();
+                        text.push_str(&self.src[start..self.index]);
+                        self.advance();
+                        start = self.index;
+                    }
+                    Some(b'"') => {
+                        text.push_str(&self.src[start..self.index]);
+                        self.advance();
+                        break;
+                    }
+                    Some(_) => self.advance(),
+                }
+            }
+            text
+        } else {
+            let start = self.index;
+            loop {
+                match self.peek() {
+                    None | Some(b',') | Some(b'\r') | Some(b'\n') => break,
+                    Some(b'"') => return Err(self.unexpected()),
+                    Some(_) => self.advance(),
+                }
+            }
+            self.src[start..self.index].to_owned()
+        };
+        let limit = self.limits.check_str_len(text.len());
+        self.check_limit(limit)?;
+        Ok(Cell {
+            text,
+            quoted,
+            position,
+        })
+    }
+}
@@ -0,0 +1,104 @@
+// Handwritten CSV: quoting, line breaks in cells and headers.
+
+use fixtures::{record, Month, Record};
+use macroserde::de::*;
+use macroserde_csv::ParseErrorKind;
+
+fn decode<T: Deserialize>(s: &str) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_csv::Deserializer::new(s).unwrap())
+}
+
+fn parse_error(s: &str) -> ParseErrorKind {
+    match macroserde_csv::Deserializer::new(s) {
+        Ok(_) => panic!("{:?} parsed", s),
+        Err(e) => e.kind,
+    }
+}
+
+#[test]
+fn headers() {
+    let by_name = "name,age,month,height,carBrand,IsCool\r\nSteven,27,OCT,1.735,,true\r\n";
+    assert_eq!(decode::<Vec<Record>>(by_name).unwrap(), vec![record()]);
+
+    // Columns are matched by ID, name or both, in any order, and unknown
+    // columns are skipped.
+    let by_id = "6,3,1,2,notes,5\ntrue,10,Steven,27,\"x\",1.735\n";
+    assert_eq!(decode::<Vec<Record>>(by_id).unwrap(), vec![record()]);
+    let both = "88:carBrand,1:name,2:age,3:month,6:IsCool,5:height\n,Steven,27,OCT,true,1.735\n";
+    assert_eq!(decode::<Vec<Record>>(both).unwrap(), vec![record()]);
+
+    // Missing columns are left to their default.
+    assert_eq!(
+        decode::<Vec<Record>>("name,age\nSteven,27\n").unwrap(),
+        vec![Record {
+            name: "Steven".to_owned(),
+            age: 27,
+            ..Record::default()
+        }]
+    );
+    assert!(decode::<Vec<Record>>("").unwrap().is_empty());
+}
+
+#[test]
+fn quoting() {
+    let csv = concat!(
+        "name,age,month,height,carBrand,IsCool\n",
+        "\"Steven \"\"Steve\"\", Jr\",27,\"OCT\",,\"\",false\n",
+        "\"\",-1,1,,,false\n",
+    );
+    let records = decode::<Vec<Record>>(csv).unwrap();
+    assert_eq!(records[0].name, "Steven \"Steve\", Jr");
+    assert_eq!(records[0].month, Month::October);
+    // A quoted empty cell is an empty string, an unquoted one is null.
+    assert_eq!(records[0].car_brand, Some(String::new()));
+    assert_eq!(records[0].height, None);
+    assert_eq!(records[1].name, "");
+    assert_eq!(records[1].car_brand, None);
+
+    assert!(matches!(
+        parse_error("name\n\"Ste\"ven\n"),
+        ParseErrorKind::UnexpectedChar('v')
+    ));
+    assert!(matches!(
+        parse_error("name\n\"Steven\n"),
+        ParseErrorKind::UnexpectedEof
+    ));
+}
+
+#[test]
+fn line_breaks() {
+    let csv =
+        "name,age,month,height,carBrand,IsCool\r\n\"Steven\r\nLe Rouzic\",27,OCT,,\"a\nb\",true\n";
+    let records = decode::<Vec<Record>>(csv).unwrap();
+    assert_eq!(records[0].name, "Steven\r\nLe Rouzic");
+    assert_eq!(records[0].car_brand.as_deref(), Some("a\nb"));
+
+    // Records end with either line break, the last one may have none.
+    let csv = "name,age,month,height,carBrand,IsCool\nSteven,27,OCT,1.735,,true";
+    assert_eq!(decode::<Vec<Record>>(csv).unwrap(), vec![record()]);
+}
+
+#[test]
+fn cell_count() {
+    assert_eq!(
+        parse_error("name,age\nSteven\n"),
+        ParseErrorKind::CellCount {
+            expected: 2,
+            found: 1,
+        }
+    );
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    let error = macroserde_csv::Deserializer::with_limits("name\r\nSteven\r\n", limits).err();
+    assert!(
+        matches!(error, Some(ref e) if e.kind == ParseErrorKind::LimitExceeded(Limit::StrLen)),
+        "{:?}",
+        error
+    );
+}
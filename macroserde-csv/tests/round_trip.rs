@@ -0,0 +1,68 @@
+// Round trips of the README types.
+
+use fixtures::{person, record, Occupation, Record};
+use macroserde::de::*;
+use macroserde::macroserde;
+use macroserde::ser::*;
+use macroserde_csv::HeaderRepr;
+use std::fmt::Debug;
+
+fn encode<T: Serialize>(value: &T, header_repr: HeaderRepr) -> Result<String, SerializeError> {
+    let mut ser = macroserde_csv::Serializer::new(Vec::new()).with_header_repr(header_repr);
+    value.serialize(&mut ser)?;
+    Ok(String::from_utf8(ser.finish()?).unwrap())
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct CarBrand {
+        car_brand: Option<String> = 88 @ "carBrand",
+    }
+}
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    for &header_repr in &[HeaderRepr::Name, HeaderRepr::IdAndName] {
+        let encoded = encode(&value, header_repr).unwrap();
+        let decoded = T::deserialize(&mut macroserde_csv::Deserializer::new(&encoded).unwrap());
+        assert_eq!(decoded.unwrap(), value, "decoding {:?}", encoded);
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(vec![record(), Record::default()]);
+    // Nested structs, sequences and unions aren't flat.
+    assert!(encode(&vec![person()], HeaderRepr::Name).is_err());
+}
+
+#[test]
+fn option() {
+    check(vec![
+        CarBrand { car_brand: None },
+        CarBrand {
+            car_brand: Some(String::new()),
+        },
+        CarBrand {
+            car_brand: Some("Zoé, \"Renault\"\r\nTwingo".to_owned()),
+        },
+    ]);
+}
+
+#[test]
+fn unit_variant() {
+    check(vec![Occupation::Unemployed, Occupation::Unemployed]);
+    // Records must have the same fields.
+    assert!(encode(
+        &vec![
+            Occupation::Unemployed,
+            Occupation::Employed("Engineer".to_owned())
+        ],
+        HeaderRepr::Name
+    )
+    .is_err());
+}
+
+#[test]
+fn empty_seq() {
+    check(Vec::<Record>::new());
+}
@@ -1,10 +1,43 @@
+mod parser;
+
 use json;
 use macroserde::{de, ser};
+use parser::{Node, NodeKind, Number, Parser};
+use std::borrow::Cow;
 use std::io;
 
+pub use parser::{ParseError, ParseErrorKind, ParseOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumRepr {
+    /// `"10:OCT"`
+    IdAndName,
+    /// `10`
+    Id,
+    /// `"OCT"`
+    Name,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFiniteFloats {
+    /// `null`, the value can't be read back.
+    Null,
+    /// `"NaN"`, `"Infinity"` and `"-Infinity"`.
+    Str,
+    /// Serialization fails.
+    Error,
+}
+
+// Integers beyond this can't be represented exactly by an IEEE double, which is
+// what JavaScript uses for all numbers.
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
 pub struct Serializer {
     current_value: json::JsonValue,
     stack: Vec<json::JsonValue>,
+    enum_repr: EnumRepr,
+    large_integers_as_str: bool,
+    non_finite_floats: NonFiniteFloats,
 }
 
 impl Serializer {
@@ -12,9 +45,28 @@ impl Serializer {
         Self {
             current_value: json::JsonValue::Null,
             stack: Vec::new(),
+            enum_repr: EnumRepr::IdAndName,
+            large_integers_as_str: false,
+            non_finite_floats: NonFiniteFloats::Null,
         }
     }
 
+    pub fn with_enum_repr(mut self, enum_repr: EnumRepr) -> Self {
+        self.enum_repr = enum_repr;
+        self
+    }
+
+    // Writes integers outside of [-(2^53 - 1), 2^53 - 1] as strings.
+    pub fn with_large_integers_as_str(mut self, large_integers_as_str: bool) -> Self {
+        self.large_integers_as_str = large_integers_as_str;
+        self
+    }
+
+    pub fn with_non_finite_floats(mut self, non_finite_floats: NonFiniteFloats) -> Self {
+        self.non_finite_floats = non_finite_floats;
+        self
+    }
+
     pub fn write_pretty<W: io::Write>(&self, writer: &mut W, spaces: u16) -> io::Result<()> {
         self.current_value.write_pretty(writer, spaces)
     }
@@ -36,17 +88,42 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
-        self.current_value = json::JsonValue::from(value);
+        self.current_value =
+            if self.large_integers_as_str && value.unsigned_abs() > MAX_SAFE_INTEGER {
+                json::JsonValue::from(value.to_string())
+            } else {
+                // `JsonValue::from(i64)` negates the value, which overflows for
+                // `i64::MIN`.
+                json::JsonValue::Number(json::number::Number::from_parts(
+                    value >= 0,
+                    value.unsigned_abs(),
+                    0,
+                ))
+            };
         Ok(())
     }
 
     fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
-        self.current_value = json::JsonValue::from(value);
+        self.current_value = if self.large_integers_as_str && value > MAX_SAFE_INTEGER {
+            json::JsonValue::from(value.to_string())
+        } else {
+            json::JsonValue::from(value)
+        };
         Ok(())
     }
 
     fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
-        self.current_value = json::JsonValue::from(value);
+        self.current_value = if value.is_finite() {
+            json::JsonValue::from(value)
+        } else {
+            match self.non_finite_floats {
+                NonFiniteFloats::Null => json::JsonValue::Null,
+                NonFiniteFloats::Str if value.is_nan() => json::JsonValue::from("NaN"),
+                NonFiniteFloats::Str if value > 0.0 => json::JsonValue::from("Infinity"),
+                NonFiniteFloats::Str => json::JsonValue::from("-Infinity"),
+                NonFiniteFloats::Error => return Err(ser::SerializeError),
+            }
+        };
         Ok(())
     }
 
@@ -58,9 +135,13 @@ impl ser::Serializer for Serializer {
     fn serialize_enum(
         &mut self,
         value: u32,
-        name: &'static str,
+        name: Cow<'static, str>,
     ) -> Result<(), ser::SerializeError> {
-        self.current_value = json::JsonValue::from(format!("{}:{}", value, name));
+        self.current_value = match self.enum_repr {
+            EnumRepr::IdAndName => json::JsonValue::from(format!("{}:{}", value, name)),
+            EnumRepr::Id => json::JsonValue::from(value),
+            EnumRepr::Name => json::JsonValue::from(&*name),
+        };
         Ok(())
     }
 
@@ -72,7 +153,7 @@ impl ser::Serializer for Serializer {
     fn serialize_struct_field(
         &mut self,
         field_id: u32,
-        field_name: &'static str,
+        field_name: Cow<'static, str>,
         value: &dyn ser::Serialize,
     ) -> Result<(), ser::SerializeError> {
         value.serialize(self)?;
@@ -116,89 +197,185 @@ impl ser::Serializer for Serializer {
 }
 
 pub struct Deserializer {
-    value: json::JsonValue,
+    value: Node,
+    enum_repr: Option<EnumRepr>,
 }
 
 impl Deserializer {
-    pub fn new(s: &str) -> Option<Self> {
-        Some(Self {
-            value: json::parse(s).ok()?,
+    pub fn new(s: &str) -> Result<Self, ParseError> {
+        Self::with_options(s, ParseOptions::default())
+    }
+
+    pub fn new_relaxed(s: &str) -> Result<Self, ParseError> {
+        Self::with_options(
+            s,
+            ParseOptions {
+                relaxed: true,
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    pub fn with_options(s: &str, options: ParseOptions) -> Result<Self, ParseError> {
+        Ok(Self {
+            value: Parser::new(s, options).parse()?,
+            enum_repr: None,
         })
     }
 
-    fn split_key(s: &str) -> (Option<u32>, &str) {
-        if let Some(sep) = s.find(':') {
-            if let Ok(id) = s[..sep].parse::<u32>() {
-                return (Some(id), &s[(sep + 1)..]);
-            }
-        }
-        return (None, s);
+    // By default enumerations are accepted in any representation, this only
+    // accepts the given one.
+    pub fn with_strict_enum_repr(mut self, enum_repr: EnumRepr) -> Self {
+        self.enum_repr = Some(enum_repr);
+        self
     }
 
     fn visit_value(
-        value: &json::JsonValue,
+        &self,
+        value: &Node,
         visitor: &mut dyn de::Visitor,
     ) -> Result<(), de::DeserializeError> {
-        match value {
-            json::JsonValue::Null => visitor.visit_null(),
-            json::JsonValue::Short(s) => visitor.visit_str(s.as_str()),
-            json::JsonValue::String(s) => visitor.visit_str(s.as_str()),
-            json::JsonValue::Number(_) => Self::visit_number(value, visitor),
-            json::JsonValue::Boolean(val) => visitor.visit_bool(*val),
-            json::JsonValue::Object(_) => Self::visit_object(value, visitor),
-            json::JsonValue::Array(_) => Self::visit_array(value, visitor),
+        match &value.kind {
+            NodeKind::Null => visitor.visit_null(),
+            NodeKind::Bool(val) => visitor.visit_bool(*val),
+            NodeKind::Number(number) => self.visit_number(number, visitor),
+            NodeKind::Str(s) => self.visit_str(s, visitor),
+            NodeKind::Array(elements) => self.visit_array(elements, visitor),
+            NodeKind::Object(entries) => self.visit_object(entries, visitor),
+        }
+        .map_err(|e| e.at(value.position))
+    }
+
+    fn visit_enum_or_else<F>(
+        visitor: &mut dyn de::Visitor,
+        id: Option<u32>,
+        name: Option<&str>,
+        fallback: F,
+    ) -> Result<(), de::DeserializeError>
+    where
+        F: FnOnce(&mut dyn de::Visitor) -> Result<(), de::DeserializeError>,
+    {
+        match visitor.visit_enum(id, name) {
+            Err(de::DeserializeError::UnimplementedVisit) => fallback(visitor),
+            result => result,
+        }
+    }
+
+    fn visit_str(
+        &self,
+        s: &str,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let (id, name) = match self.enum_repr {
+            None => return Self::visit_str_or_number(s, visitor),
+            Some(EnumRepr::IdAndName) => match de::split_key(s) {
+                (Some(id), Some(name)) => (Some(id), Some(name)),
+                _ => (None, None),
+            },
+            Some(EnumRepr::Id) => (None, None),
+            Some(EnumRepr::Name) => (None, Some(s)),
+        };
+        Self::visit_enum_or_else(visitor, id, name, |visitor| {
+            Self::visit_str_or_number(s, visitor)
+        })
+    }
+
+    // Numbers the serializer couldn't write losslessly are written as strings,
+    // read them back if the visitor doesn't expect a string.
+    fn visit_str_or_number(
+        s: &str,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match visitor.visit_str(s) {
+            Err(de::DeserializeError::UnimplementedVisit) => {}
+            result => return result,
+        }
+
+        match s {
+            "NaN" => visitor.visit_float(f64::NAN),
+            "Infinity" => visitor.visit_float(f64::INFINITY),
+            "-Infinity" => visitor.visit_float(f64::NEG_INFINITY),
+            _ => {
+                if let Ok(u) = s.parse::<u64>() {
+                    visitor.visit_unsigned(u)
+                } else if let Ok(i) = s.parse::<i64>() {
+                    visitor.visit_signed(i)
+                } else {
+                    Err(de::DeserializeError::UnimplementedVisit)
+                }
+            }
         }
     }
 
     fn visit_array(
-        value: &json::JsonValue,
+        &self,
+        elements: &[Node],
         visitor: &mut dyn de::Visitor,
     ) -> Result<(), de::DeserializeError> {
-        let mut builder = visitor.visit_seq(Some(value.len()))?;
-        for entry in value.members() {
+        let mut builder = visitor.visit_seq(Some(elements.len()))?;
+        for element in elements {
             let visitor = builder.element()?;
-            Self::visit_value(entry, visitor)?;
+            self.visit_value(element, visitor)?;
         }
         builder.finish()
     }
 
     fn visit_object(
-        value: &json::JsonValue,
+        &self,
+        entries: &[(String, Node)],
         visitor: &mut dyn de::Visitor,
     ) -> Result<(), de::DeserializeError> {
         let mut null_visitor = de::NullVisitor;
         let mut builder = visitor.visit_struct()?;
-        for entry in value.entries() {
-            let (id, name) = Self::split_key(entry.0);
-            match builder.member(id, Some(name)) {
-                Ok(visitor) => Self::visit_value(entry.1, visitor)?,
+        for entry in entries {
+            let (id, name) = de::split_key(&entry.0);
+            match builder.member(id, name) {
+                Ok(visitor) => self.visit_value(&entry.1, visitor)?,
                 Err(de::DeserializeError::UnknownField) => {
-                    Self::visit_value(entry.1, &mut null_visitor)?
+                    self.visit_value(&entry.1, &mut null_visitor)?
                 }
-                Err(e) => Err(e)?,
+                Err(e) => Err(e.at(entry.1.position))?,
             }
         }
         builder.finish()
     }
 
     fn visit_number(
-        number: &json::JsonValue,
+        &self,
+        number: &Number,
         visitor: &mut dyn de::Visitor,
     ) -> Result<(), de::DeserializeError> {
-        if let Some(u) = number.as_u64() {
-            visitor.visit_unsigned(u)
-        } else if let Some(i) = number.as_i64() {
-            visitor.visit_signed(i)
-        } else if let Some(f) = number.as_f64() {
-            visitor.visit_float(f)
-        } else {
-            Err(de::DeserializeError::IncompatibleNumericType)
+        match self.enum_repr {
+            None => Self::visit_plain_number(number, visitor),
+            Some(EnumRepr::Id) => {
+                let id = match *number {
+                    Number::Unsigned(u) if u <= u32::MAX as u64 => Some(u as u32),
+                    _ => None,
+                };
+                Self::visit_enum_or_else(visitor, id, None, |visitor| {
+                    Self::visit_plain_number(number, visitor)
+                })
+            }
+            Some(_) => Self::visit_enum_or_else(visitor, None, None, |visitor| {
+                Self::visit_plain_number(number, visitor)
+            }),
+        }
+    }
+
+    fn visit_plain_number(
+        number: &Number,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match *number {
+            Number::Unsigned(u) => visitor.visit_unsigned(u),
+            Number::Signed(i) => visitor.visit_signed(i),
+            Number::Float(f) => visitor.visit_float(f),
         }
     }
 }
 
 impl de::Deserializer for Deserializer {
     fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
-        Self::visit_value(&self.value, visitor)
+        self.visit_value(&self.value, visitor)
     }
 }
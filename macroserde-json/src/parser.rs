@@ -0,0 +1,564 @@
+use macroserde::de::{DeserializeLimits, Limit, LimitTracker, Position};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    // Also accepts comments, trailing commas, unquoted keys, single-quoted
+    // strings and hexadecimal integers, for human-edited files.
+    pub relaxed: bool,
+    pub limits: DeserializeLimits,
+}
+
+pub enum Number {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+}
+
+pub enum NodeKind {
+    Null,
+    Bool(bool),
+    Number(Number),
+    Str(String),
+    Array(Vec<Node>),
+    Object(Vec<(String, Node)>),
+}
+
+pub struct Node {
+    pub kind: NodeKind,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    InvalidNumber,
+    InvalidEscape,
+    InvalidUnicodeEscape,
+    ControlCharInString,
+    TrailingChars,
+    LimitExceeded(Limit),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+    // The source line containing the error.
+    pub snippet: String,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number"),
+            ParseErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            ParseErrorKind::InvalidUnicodeEscape => write!(f, "invalid unicode escape"),
+            ParseErrorKind::ControlCharInString => write!(f, "control character in string"),
+            ParseErrorKind::TrailingChars => write!(f, "trailing characters"),
+            ParseErrorKind::LimitExceeded(Limit::Depth) => write!(f, "too deeply nested"),
+            ParseErrorKind::LimitExceeded(Limit::StrLen) => write!(f, "string too long"),
+            ParseErrorKind::LimitExceeded(Limit::CollectionLen) => {
+                write!(f, "too many elements")
+            }
+            ParseErrorKind::LimitExceeded(Limit::TotalAlloc) => write!(f, "input too large"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.position.line, self.position.column
+        )?;
+        writeln!(f, "{}", self.snippet)?;
+        let caret_offset: String = self
+            .snippet
+            .chars()
+            .take(self.position.column - 1)
+            .map(|c| if c == '\t' { c } else { ' ' })
+            .collect();
+        write!(f, "{}^", caret_offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    index: usize,
+    line: usize,
+    column: usize,
+    line_start: usize,
+    relaxed: bool,
+    limits: LimitTracker,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(src: &'a str, options: ParseOptions) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            index: 0,
+            line: 1,
+            column: 1,
+            line_start: 0,
+            relaxed: options.relaxed,
+            limits: LimitTracker::new(options.limits),
+        }
+    }
+
+    pub fn parse(mut self) -> Result<Node, ParseError> {
+        self.skip_whitespace()?;
+        let node = self.parse_value()?;
+        self.skip_whitespace()?;
+        if self.index < self.bytes.len() {
+            return Err(self.error(ParseErrorKind::TrailingChars));
+        }
+        Ok(node)
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        let line_end = self.src[self.line_start..]
+            .find('\n')
+            .map(|i| self.line_start + i)
+            .unwrap_or(self.src.len());
+        ParseError {
+            kind,
+            position: self.position(),
+            snippet: self.src[self.line_start..line_end]
+                .trim_end_matches('\r')
+                .to_owned(),
+        }
+    }
+
+    fn check_limit(&self, result: Result<(), Limit>) -> Result<(), ParseError> {
+        result.map_err(|limit| self.error(ParseErrorKind::LimitExceeded(limit)))
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.src[self.index..].chars().next() {
+            Some(c) => self.error(ParseErrorKind::UnexpectedChar(c)),
+            None => self.error(ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.index).copied()
+    }
+
+    #[inline]
+    fn bump(&mut self) {
+        let byte = self.bytes[self.index];
+        self.index += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+            self.line_start = self.index;
+        } else if byte & 0xc0 != 0x80 {
+            self.column += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        if self.peek() == Some(byte) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        for &byte in keyword.as_bytes() {
+            self.expect(byte)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.index + offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), ParseError> {
+        loop {
+            match self.peek() {
+                Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => self.bump(),
+                Some(b'/') if self.relaxed => self.skip_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), ParseError> {
+        match self.peek_at(1) {
+            Some(b'/') => {
+                while let Some(byte) = self.peek() {
+                    if byte == b'\n' {
+                        break;
+                    }
+                    self.bump();
+                }
+                Ok(())
+            }
+            Some(b'*') => {
+                self.bump();
+                self.bump();
+                loop {
+                    match self.peek() {
+                        None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                        Some(b'*') if self.peek_at(1) == Some(b'/') => {
+                            self.bump();
+                            self.bump();
+                            return Ok(());
+                        }
+                        Some(_) => self.bump(),
+                    }
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Node, ParseError> {
+        let position = self.position();
+        let kind = match self.peek() {
+            Some(b'n') => {
+                self.expect_keyword("null")?;
+                NodeKind::Null
+            }
+            Some(b't') => {
+                self.expect_keyword("true")?;
+                NodeKind::Bool(true)
+            }
+            Some(b'f') => {
+                self.expect_keyword("false")?;
+                NodeKind::Bool(false)
+            }
+            Some(b'"') => NodeKind::Str(self.parse_str()?),
+            Some(b'\'') if self.relaxed => NodeKind::Str(self.parse_str()?),
+            Some(b'[') => {
+                let limit = self.limits.enter();
+                self.check_limit(limit)?;
+                let elements = self.parse_array()?;
+                self.limits.leave();
+                NodeKind::Array(elements)
+            }
+            Some(b'{') => {
+                let limit = self.limits.enter();
+                self.check_limit(limit)?;
+                let entries = self.parse_object()?;
+                self.limits.leave();
+                NodeKind::Object(entries)
+            }
+            Some(b'-') | Some(b'0'..=b'9') => NodeKind::Number(self.parse_number()?),
+            _ => return Err(self.unexpected()),
+        };
+        Ok(Node { kind, position })
+    }
+
+    fn parse_array(&mut self) -> Result<Vec<Node>, ParseError> {
+        self.expect(b'[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace()?;
+        if self.peek() == Some(b']') {
+            self.bump();
+            return Ok(elements);
+        }
+        loop {
+            self.skip_whitespace()?;
+            if self.relaxed && !elements.is_empty() && self.peek() == Some(b']') {
+                self.bump();
+                return Ok(elements);
+            }
+            let limit = self.limits.check_collection_growth(elements.len() + 1);
+            self.check_limit(limit)?;
+            elements.push(self.parse_value()?);
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => self.bump(),
+                Some(b']') => {
+                    self.bump();
+                    return Ok(elements);
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Vec<(String, Node)>, ParseError> {
+        self.expect(b'{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace()?;
+        if self.peek() == Some(b'}') {
+            self.bump();
+            return Ok(entries);
+        }
+        loop {
+            self.skip_whitespace()?;
+            if self.relaxed && !entries.is_empty() && self.peek() == Some(b'}') {
+                self.bump();
+                return Ok(entries);
+            }
+            let limit = self.limits.check_collection_growth(entries.len() + 1);
+            self.check_limit(limit)?;
+            let key = self.parse_key()?;
+            self.skip_whitespace()?;
+            self.expect(b':')?;
+            self.skip_whitespace()?;
+            entries.push((key, self.parse_value()?));
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => self.bump(),
+                Some(b'}') => {
+                    self.bump();
+                    return Ok(entries);
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_key(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(b'"') => self.parse_str(),
+            Some(b'\'') if self.relaxed => self.parse_str(),
+            Some(c) if self.relaxed && Self::is_identifier_byte(c) => Ok(self.parse_unquoted_key()),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn is_identifier_byte(byte: u8) -> bool {
+        byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$'
+    }
+
+    // Unquoted keys are identifiers, optionally prefixed by an ID as in `1:name`.
+    fn parse_unquoted_key(&mut self) -> String {
+        let start = self.index;
+        while self.peek().is_some_and(Self::is_identifier_byte) {
+            self.bump();
+        }
+        let is_id = self.src[start..self.index]
+            .bytes()
+            .all(|byte| byte.is_ascii_digit());
+        let is_name_next = self
+            .peek_at(1)
+            .is_some_and(|byte| Self::is_identifier_byte(byte) && !byte.is_ascii_digit());
+        if is_id && self.peek() == Some(b':') && is_name_next {
+            self.bump();
+            while self.peek().is_some_and(Self::is_identifier_byte) {
+                self.bump();
+            }
+        }
+        self.src[start..self.index].to_owned()
+    }
+
+    fn parse_str(&mut self) -> Result<String, ParseError> {
+        let quote = self.peek().unwrap_or(b'"');
+        self.bump();
+        let mut result = String::new();
+        let mut run_start = self.index;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                Some(c) if c == quote => {
+                    self.push_run(&mut result, run_start)?;
+                    self.bump();
+                    return Ok(result);
+                }
+                Some(b'\\') => {
+                    self.push_run(&mut result, run_start)?;
+                    self.bump();
+                    let c = self.parse_escape()?;
+                    let limit = self
+                        .limits
+                        .check_str_growth(result.len() + c.len_utf8(), c.len_utf8());
+                    self.check_limit(limit)?;
+                    result.push(c);
+                    run_start = self.index;
+                }
+                Some(0x00..=0x1f) => return Err(self.error(ParseErrorKind::ControlCharInString)),
+                Some(_) => self.bump(),
+            }
+        }
+    }
+
+    // Appends the unescaped run of a string ending at the current index, once
+    // its length is checked.
+    fn push_run(&mut self, result: &mut String, run_start: usize) -> Result<(), ParseError> {
+        let run = &self.src[run_start..self.index];
+        let limit = self
+            .limits
+            .check_str_growth(result.len() + run.len(), run.len());
+        self.check_limit(limit)?;
+        result.push_str(run);
+        Ok(())
+    }
+
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\'') if self.relaxed => '\'',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.bump();
+                return self.parse_unicode_escape();
+            }
+            None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+            Some(_) => return Err(self.error(ParseErrorKind::InvalidEscape)),
+        };
+        self.bump();
+        Ok(c)
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, ParseError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = match self.peek() {
+                Some(c @ b'0'..=b'9') => c - b'0',
+                Some(c @ b'a'..=b'f') => c - b'a' + 10,
+                Some(c @ b'A'..=b'F') => c - b'A' + 10,
+                _ => return Err(self.error(ParseErrorKind::InvalidUnicodeEscape)),
+            };
+            value = value * 16 + digit as u32;
+            self.bump();
+        }
+        Ok(value)
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.parse_hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if self.peek() != Some(b'\\') {
+                return Err(self.error(ParseErrorKind::InvalidUnicodeEscape));
+            }
+            self.bump();
+            if self.peek() != Some(b'u') {
+                return Err(self.error(ParseErrorKind::InvalidUnicodeEscape));
+            }
+            self.bump();
+            let low = self.parse_hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error(ParseErrorKind::InvalidUnicodeEscape));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        std::char::from_u32(code).ok_or_else(|| self.error(ParseErrorKind::InvalidUnicodeEscape))
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let mut count = 0;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.bump();
+            count += 1;
+        }
+        count
+    }
+
+    fn parse_number(&mut self) -> Result<Number, ParseError> {
+        let start = self.index;
+        let start_position = self.position();
+        let mut is_float = false;
+
+        let negative = self.peek() == Some(b'-');
+        if negative {
+            self.bump();
+        }
+        if self.relaxed && self.peek() == Some(b'0') {
+            if let Some(b'x') | Some(b'X') = self.peek_at(1) {
+                return self.parse_hex_number(negative, start_position);
+            }
+        }
+        match self.peek() {
+            Some(b'0') => self.bump(),
+            Some(b'1'..=b'9') => {
+                self.skip_digits();
+            }
+            _ => return Err(self.unexpected()),
+        }
+        if self.peek() == Some(b'.') {
+            is_float = true;
+            self.bump();
+            if self.skip_digits() == 0 {
+                return Err(self.unexpected());
+            }
+        }
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            is_float = true;
+            self.bump();
+            if let Some(b'+') | Some(b'-') = self.peek() {
+                self.bump();
+            }
+            if self.skip_digits() == 0 {
+                return Err(self.unexpected());
+            }
+        }
+
+        let text = &self.src[start..self.index];
+        if !is_float {
+            if let Ok(u) = text.parse::<u64>() {
+                return Ok(Number::Unsigned(u));
+            } else if let Ok(i) = text.parse::<i64>() {
+                return Ok(Number::Signed(i));
+            }
+        }
+        match text.parse::<f64>() {
+            Ok(f) => Ok(Number::Float(f)),
+            Err(_) => {
+                let mut error = self.error(ParseErrorKind::InvalidNumber);
+                error.position = start_position;
+                Err(error)
+            }
+        }
+    }
+
+    fn parse_hex_number(
+        &mut self,
+        negative: bool,
+        start_position: Position,
+    ) -> Result<Number, ParseError> {
+        self.bump();
+        self.bump();
+        let start = self.index;
+        while self.peek().is_some_and(|byte| byte.is_ascii_hexdigit()) {
+            self.bump();
+        }
+        let invalid_number = || {
+            let mut error = self.error(ParseErrorKind::InvalidNumber);
+            error.position = start_position;
+            error
+        };
+        let value =
+            u64::from_str_radix(&self.src[start..self.index], 16).map_err(|_| invalid_number())?;
+        if !negative {
+            Ok(Number::Unsigned(value))
+        } else if value <= i64::MAX as u64 + 1 {
+            Ok(Number::Signed((value as i64).wrapping_neg()))
+        } else {
+            Err(invalid_number())
+        }
+    }
+}
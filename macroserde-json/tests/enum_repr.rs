@@ -0,0 +1,76 @@
+use macroserde::de::{Deserialize, DeserializeError};
+use macroserde::macroserde;
+use macroserde::ser::Serialize;
+use macroserde_json::EnumRepr;
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    enum Month {
+        #[default]
+        January = 1 @ "JAN",
+        October = 10 @ "OCT",
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Date {
+        day: u8 = 1,
+        month: Month = 2,
+    }
+}
+
+const DATE: Date = Date {
+    day: 19,
+    month: Month::October,
+};
+
+fn encode(value: &Date, enum_repr: EnumRepr) -> String {
+    let mut ser = macroserde_json::Serializer::new().with_enum_repr(enum_repr);
+    value.serialize(&mut ser).unwrap();
+    let mut bytes = Vec::new();
+    ser.write(&mut bytes).unwrap();
+    String::from_utf8(bytes).unwrap()
+}
+
+fn decode(s: &str, enum_repr: Option<EnumRepr>) -> Result<Date, DeserializeError> {
+    let mut de = macroserde_json::Deserializer::new(s).unwrap();
+    if let Some(enum_repr) = enum_repr {
+        de = de.with_strict_enum_repr(enum_repr);
+    }
+    Date::deserialize(&mut de)
+}
+
+#[test]
+fn writes_each_repr() {
+    assert_eq!(
+        encode(&DATE, EnumRepr::IdAndName),
+        r#"{"1:day":19,"2:month":"10:OCT"}"#
+    );
+    assert_eq!(encode(&DATE, EnumRepr::Id), r#"{"1:day":19,"2:month":10}"#);
+    assert_eq!(
+        encode(&DATE, EnumRepr::Name),
+        r#"{"1:day":19,"2:month":"OCT"}"#
+    );
+}
+
+#[test]
+fn lenient_reads_any_repr() {
+    for enum_repr in [EnumRepr::IdAndName, EnumRepr::Id, EnumRepr::Name] {
+        assert_eq!(decode(&encode(&DATE, enum_repr), None).unwrap(), DATE);
+    }
+}
+
+#[test]
+fn strict_reads_only_its_repr() {
+    for strict in [EnumRepr::IdAndName, EnumRepr::Id, EnumRepr::Name] {
+        for written in [EnumRepr::IdAndName, EnumRepr::Id, EnumRepr::Name] {
+            let result = decode(&encode(&DATE, written), Some(strict));
+            if written == strict {
+                assert_eq!(result.unwrap(), DATE);
+            } else {
+                assert!(result.is_err(), "{:?} read as {:?}", written, strict);
+            }
+        }
+    }
+}
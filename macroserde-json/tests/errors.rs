@@ -0,0 +1,58 @@
+use macroserde::de::{Deserialize, DeserializeError, Position};
+use macroserde::macroserde;
+use macroserde_json::{Deserializer, ParseErrorKind};
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Date {
+        day: u8 = 1,
+        year: u32 = 3,
+    }
+}
+
+#[test]
+fn parse_error_position_and_snippet() {
+    let src = "{\n  \"1:day\": 19,\n  \"3:year\" 1993\n}";
+    let error = Deserializer::new(src).err().unwrap();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedChar('1'));
+    assert_eq!(
+        error.position,
+        Position {
+            line: 3,
+            column: 12
+        }
+    );
+    assert_eq!(error.snippet, "  \"3:year\" 1993");
+    assert_eq!(
+        error.to_string(),
+        "unexpected character '1' at line 3, column 12\n  \"3:year\" 1993\n           ^"
+    );
+}
+
+#[test]
+fn parse_error_at_end_of_input() {
+    let error = Deserializer::new("[1,\r\n2").err().unwrap();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedEof);
+    assert_eq!(error.position, Position { line: 2, column: 2 });
+    assert_eq!(error.snippet, "2");
+}
+
+#[test]
+fn deserialize_error_position() {
+    let src = "{\n  \"1:day\": 19,\n  \"3:year\": -1\n}";
+    let mut de = Deserializer::new(src).unwrap();
+    let error = Date::deserialize(&mut de).unwrap_err();
+    match error {
+        DeserializeError::AtPosition(error, position) => {
+            assert!(matches!(*error, DeserializeError::IncompatibleNumericType));
+            assert_eq!(
+                position,
+                Position {
+                    line: 3,
+                    column: 13
+                }
+            );
+        }
+        error => panic!("{:?}", error),
+    }
+}
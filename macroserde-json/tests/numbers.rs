@@ -0,0 +1,77 @@
+use macroserde::de::Deserialize;
+use macroserde::ser::{Serialize, SerializeError};
+use macroserde_json::NonFiniteFloats;
+
+fn encode<T: Serialize>(value: &T, large_integers_as_str: bool) -> String {
+    let mut ser =
+        macroserde_json::Serializer::new().with_large_integers_as_str(large_integers_as_str);
+    value.serialize(&mut ser).unwrap();
+    let mut bytes = Vec::new();
+    ser.write(&mut bytes).unwrap();
+    String::from_utf8(bytes).unwrap()
+}
+
+fn encode_float(value: f64, non_finite_floats: NonFiniteFloats) -> Result<String, SerializeError> {
+    let mut ser = macroserde_json::Serializer::new().with_non_finite_floats(non_finite_floats);
+    value.serialize(&mut ser)?;
+    let mut bytes = Vec::new();
+    ser.write(&mut bytes).unwrap();
+    Ok(String::from_utf8(bytes).unwrap())
+}
+
+fn decode<T: Deserialize>(s: &str) -> T {
+    let mut de = macroserde_json::Deserializer::new(s).unwrap();
+    T::deserialize(&mut de).unwrap()
+}
+
+#[test]
+fn extreme_integers() {
+    assert_eq!(encode(&i64::MIN, false), "-9223372036854775808");
+    assert_eq!(encode(&i64::MIN, true), r#""-9223372036854775808""#);
+    assert_eq!(encode(&u64::MAX, false), "18446744073709551615");
+    assert_eq!(encode(&u64::MAX, true), r#""18446744073709551615""#);
+    for large_integers_as_str in [false, true] {
+        let s = encode(&i64::MIN, large_integers_as_str);
+        assert_eq!(decode::<i64>(&s), i64::MIN);
+        let s = encode(&u64::MAX, large_integers_as_str);
+        assert_eq!(decode::<u64>(&s), u64::MAX);
+    }
+}
+
+#[test]
+fn large_integers_as_str() {
+    let safe = (1i64 << 53) - 1;
+    assert_eq!(encode(&safe, true), "9007199254740991");
+    assert_eq!(encode(&-safe, true), "-9007199254740991");
+    assert_eq!(encode(&(safe + 1), true), r#""9007199254740992""#);
+    assert_eq!(encode(&(-safe - 1), true), r#""-9007199254740992""#);
+
+    let values = vec![0, safe + 1, -safe - 1, i64::MAX];
+    assert_eq!(decode::<Vec<i64>>(&encode(&values, true)), values);
+}
+
+#[test]
+fn non_finite_floats() {
+    assert_eq!(
+        encode_float(f64::NAN, NonFiniteFloats::Null).unwrap(),
+        "null"
+    );
+    assert_eq!(
+        encode_float(f64::NAN, NonFiniteFloats::Str).unwrap(),
+        r#""NaN""#
+    );
+    assert_eq!(
+        encode_float(f64::INFINITY, NonFiniteFloats::Str).unwrap(),
+        r#""Infinity""#
+    );
+    assert_eq!(
+        encode_float(f64::NEG_INFINITY, NonFiniteFloats::Str).unwrap(),
+        r#""-Infinity""#
+    );
+    assert!(encode_float(f64::INFINITY, NonFiniteFloats::Error).is_err());
+    assert_eq!(encode_float(1.5, NonFiniteFloats::Error).unwrap(), "1.5");
+
+    assert!(decode::<f64>(r#""NaN""#).is_nan());
+    assert_eq!(decode::<f64>(r#""Infinity""#), f64::INFINITY);
+    assert_eq!(decode::<f64>(r#""-Infinity""#), f64::NEG_INFINITY);
+}
@@ -0,0 +1,59 @@
+use macroserde::de::Deserialize;
+use macroserde::macroserde;
+use macroserde_json::{Deserializer, ParseErrorKind};
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Person {
+        name: String = 1,
+        pets: Vec<String> = 4,
+        car_brand: Option<String> = 88 @ "carBrand",
+        flags: u32 = 9,
+    }
+}
+
+const SRC: &str = r#"
+// A hand-written person.
+{
+    1:name: 'Steven', /* by ID and name */
+    pets: [
+        "Bouboul",
+        'Monsieur Puppy',
+    ],
+    carBrand: null,
+    flags: 0xff,
+}
+"#;
+
+#[test]
+fn reads_human_edited_input() {
+    let mut de = Deserializer::new_relaxed(SRC).unwrap();
+    assert_eq!(
+        Person::deserialize(&mut de).unwrap(),
+        Person {
+            name: "Steven".to_owned(),
+            pets: vec!["Bouboul".to_owned(), "Monsieur Puppy".to_owned()],
+            car_brand: None,
+            flags: 0xff,
+        }
+    );
+}
+
+#[test]
+fn strict_rejects_relaxed_syntax() {
+    let error = Deserializer::new(SRC).err().unwrap();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedChar('/'));
+    assert_eq!(error.position.line, 2);
+
+    for src in ["[1, 2,]", "{a: 1}", "['a']", "0x10", "[1 /* two */]"] {
+        assert!(Deserializer::new(src).is_err(), "{}", src);
+        assert!(Deserializer::new_relaxed(src).is_ok(), "{}", src);
+    }
+}
+
+#[test]
+fn relaxed_still_rejects_malformed_input() {
+    for src in ["[1,,2]", "{,}", "/* unterminated", "[1] // trailing\n2"] {
+        assert!(Deserializer::new_relaxed(src).is_err(), "{}", src);
+    }
+}
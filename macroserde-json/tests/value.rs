@@ -0,0 +1,47 @@
+use macroserde::de::Deserialize;
+use macroserde::ser::Serialize;
+use macroserde::value::{Key, Value};
+
+fn read(s: &str) -> Value {
+    <Value as Deserialize>::deserialize(&mut macroserde_json::Deserializer::new(s).unwrap())
+        .unwrap()
+}
+
+fn write(value: &Value) -> Option<String> {
+    let mut ser = macroserde_json::Serializer::new();
+    value.serialize(&mut ser).ok()?;
+    let mut bytes = Vec::new();
+    ser.write(&mut bytes).unwrap();
+    Some(String::from_utf8(bytes).unwrap())
+}
+
+#[test]
+fn keys() {
+    let json = r#"{"1:name":"Steven","3:birth_date":{"1:day":19}}"#;
+    let value = read(json);
+    assert_eq!(
+        value,
+        Value::Struct(vec![
+            (
+                Key::new(Some(1), Some("name")),
+                Value::Str("Steven".to_owned())
+            ),
+            (
+                Key::new(Some(3), Some("birth_date")),
+                Value::Struct(vec![(Key::new(Some(1), Some("day")), Value::Unsigned(19))])
+            ),
+        ])
+    );
+    assert_eq!(write(&value).unwrap(), json);
+}
+
+#[test]
+fn missing_id() {
+    // Without an ID the field can't be written, rather than being given 0.
+    assert_eq!(write(&read(r#"{"name":"Steven"}"#)), None);
+    let nested = Value::Seq(vec![Value::Struct(vec![(
+        Key::new(None, Some("day")),
+        Value::Unsigned(19),
+    )])]);
+    assert_eq!(write(&nested), None);
+}
@@ -0,0 +1,95 @@
+use macroserde::de::*;
+use macroserde::macroserde;
+use macroserde::ser::*;
+use std::time::Instant;
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    enum Month {
+        #[default]
+        January = 1 @ "JAN",
+        October = 10 @ "OCT",
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Date {
+        day: u8 = 1,
+        month: Month = 2,
+        year: u32 = 3,
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    union Occupation {
+        #[default]
+        Unemployed = 1,
+        Employed(String) = 2 @ "hasJob",
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Person {
+        name: String = 1,
+        age: i16 = 2,
+        birth_date: Date = 3,
+        pets: Vec<String> = 4,
+        height: Option<f32> = 5,
+        car_brand: Option<String> = 88 @ "carBrand",
+        is_cool: bool = 6 @ "IsCool",
+        occupation: Occupation = 7,
+    }
+}
+
+fn bench<F: FnMut()>(name: &str, bytes: usize, mut f: F) {
+    let iterations = 200_000;
+    for _ in 0..iterations / 10 {
+        f();
+    }
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed();
+    let per_iteration = elapsed.as_nanos() / iterations as u128;
+    let throughput = (bytes * iterations) as f64 / elapsed.as_secs_f64() / 1e6;
+    println!(
+        "{:<24} {:>6} ns/iter {:>8.1} MB/s",
+        name, per_iteration, throughput
+    );
+}
+
+fn main() {
+    let person = Person {
+        name: "Steven".to_owned(),
+        age: 27,
+        height: Some(1.735),
+        car_brand: None,
+        is_cool: true,
+        birth_date: Date {
+            day: 19,
+            month: Month::October,
+            year: 1993,
+        },
+        pets: vec!["Bouboul".to_owned(), "Monsieur Puppy".to_owned()],
+        occupation: Occupation::Employed("Engineer".to_owned()),
+    };
+
+    let mut buffer = Vec::<u8>::new();
+    let mut ser = macroserde_msgpack::Serializer::new(&mut buffer);
+    person.serialize(&mut ser).unwrap();
+    ser.finish().unwrap();
+
+    bench("Deserializer::new", buffer.len(), || {
+        let mut de = macroserde_msgpack::Deserializer::new(buffer.as_slice());
+        assert_eq!(Person::deserialize(&mut de).unwrap().age, 27);
+    });
+
+    bench("Deserializer::from_slice", buffer.len(), || {
+        let mut de = macroserde_msgpack::Deserializer::from_slice(&buffer);
+        assert_eq!(Person::deserialize(&mut de).unwrap().age, 27);
+    });
+}
@@ -1,15 +1,86 @@
+mod read;
+
+use macroserde::ext::Timestamp;
 use macroserde::{de, ser};
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::io;
-use std::io::{Read, Write};
+use std::io::Write;
+
+pub use read::{IoRead, Read, SliceRead};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRepr {
+    /// `10`
+    Id,
+    /// `"carBrand"`
+    Name,
+    /// `"10:carBrand"`
+    IdAndName,
+}
 
 pub struct Serializer<W: io::Write> {
     write: io::BufWriter<W>,
+    key_repr: KeyRepr,
+    canonical: bool,
+    // In canonical mode struct fields are encoded in these buffers, then
+    // written sorted by key when the struct ends.
+    buffers: Vec<Vec<u8>>,
+    // Encoded fields of each open struct along with the length of their key.
+    fields: Vec<Vec<(usize, Vec<u8>)>>,
 }
 
 impl<W: io::Write> Serializer<W> {
     pub fn new(w: W) -> Self {
         Self {
             write: io::BufWriter::new(w),
+            key_repr: KeyRepr::Id,
+            canonical: false,
+            buffers: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn with_key_repr(mut self, key_repr: KeyRepr) -> Self {
+        self.key_repr = key_repr;
+        self
+    }
+
+    // Encodes the same value to the same bytes: struct fields are sorted by
+    // key and floats are always written as float 64 with a single NaN.
+    pub fn with_canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    pub fn flush(&mut self) -> Result<(), ser::SerializeError> {
+        self.write.flush()?;
+        Ok(())
+    }
+
+    // Dropping the serializer also flushes it but any write error is lost.
+    pub fn finish(self) -> io::Result<W> {
+        self.write
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+    }
+
+    #[inline]
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), ser::SerializeError> {
+        match self.buffers.last_mut() {
+            Some(buffer) => buffer.extend_from_slice(bytes),
+            None => self.write.write_all(bytes)?,
+        }
+        Ok(())
+    }
+
+    fn write_key(&mut self, field_id: u32, field_name: &str) -> Result<(), ser::SerializeError> {
+        match self.key_repr {
+            KeyRepr::Id => ser::Serializer::serialize_unsigned(self, field_id as u64),
+            KeyRepr::Name => ser::Serializer::serialize_str(self, field_name),
+            KeyRepr::IdAndName => {
+                ser::Serializer::serialize_str(self, &format!("{}:{}", field_id, field_name))
+            }
         }
     }
 
@@ -19,33 +90,47 @@ impl<W: io::Write> Serializer<W> {
         value: u64,
     ) -> Result<(), ser::SerializeError> {
         if value <= std::u8::MAX as u64 {
-            self.write.write_all(&[discriminant])?;
-            self.write.write_all(&(value as u8).to_be_bytes())?;
+            self.write_all(&[discriminant])?;
+            self.write_all(&(value as u8).to_be_bytes())?;
         } else if value <= std::u16::MAX as u64 {
-            self.write.write_all(&[discriminant + 1])?;
-            self.write.write_all(&(value as u16).to_be_bytes())?;
+            self.write_all(&[discriminant + 1])?;
+            self.write_all(&(value as u16).to_be_bytes())?;
         } else if value <= std::u32::MAX as u64 {
-            self.write.write_all(&[discriminant + 2])?;
-            self.write.write_all(&(value as u32).to_be_bytes())?;
+            self.write_all(&[discriminant + 2])?;
+            self.write_all(&(value as u32).to_be_bytes())?;
         } else {
-            self.write.write_all(&[discriminant + 3])?;
-            self.write.write_all(&value.to_be_bytes())?;
+            self.write_all(&[discriminant + 3])?;
+            self.write_all(&value.to_be_bytes())?;
         }
         Ok(())
     }
 
+    fn write_unsigned_8_to_32(
+        &mut self,
+        discriminant: u8,
+        value: u64,
+    ) -> Result<(), ser::SerializeError> {
+        if value <= std::u8::MAX as u64 {
+            self.write_all(&[discriminant])?;
+            self.write_all(&(value as u8).to_be_bytes())?;
+            Ok(())
+        } else {
+            self.write_unsigned_16_to_32(discriminant + 1, value)
+        }
+    }
+
     fn write_unsigned_16_to_32(
         &mut self,
         discriminant: u8,
         value: u64,
     ) -> Result<(), ser::SerializeError> {
         if value <= std::u16::MAX as u64 {
-            self.write.write_all(&[discriminant])?;
-            self.write.write_all(&(value as u16).to_be_bytes())?;
+            self.write_all(&[discriminant])?;
+            self.write_all(&(value as u16).to_be_bytes())?;
             Ok(())
         } else if value <= std::u32::MAX as u64 {
-            self.write.write_all(&[discriminant + 1])?;
-            self.write.write_all(&(value as u32).to_be_bytes())?;
+            self.write_all(&[discriminant + 1])?;
+            self.write_all(&(value as u32).to_be_bytes())?;
             Ok(())
         } else {
             Err(ser::SerializeError)
@@ -53,45 +138,43 @@ impl<W: io::Write> Serializer<W> {
     }
 }
 
-impl<W: io::Write> Drop for Serializer<W> {
-    fn drop(&mut self) {
-        self.write.flush().unwrap();
-    }
-}
-
 impl<W: io::Write> ser::Serializer for Serializer<W> {
     fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
-        self.write.write_all(&[0xc0])?;
+        self.write_all(&[0xc0])?;
         Ok(())
     }
 
     fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
-        self.write.write_all(&[0xc2 + value as u8])?;
+        self.write_all(&[0xc2 + value as u8])?;
         Ok(())
     }
 
     fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
-        if value >= -32 && value <= 127 {
-            self.write.write_all(&(value as i8).to_be_bytes())?;
+        // Non-negative values use the unsigned formats, which are never
+        // longer than the signed ones.
+        if value >= 0 {
+            self.serialize_unsigned(value as u64)?;
+        } else if value >= -32 && value <= 127 {
+            self.write_all(&(value as i8).to_be_bytes())?;
         } else if value >= std::i8::MIN as i64 && value <= std::i8::MAX as i64 {
-            self.write.write_all(&[0xd0])?;
-            self.write.write_all(&(value as i8).to_be_bytes())?;
+            self.write_all(&[0xd0])?;
+            self.write_all(&(value as i8).to_be_bytes())?;
         } else if value >= std::i16::MIN as i64 && value <= std::i16::MAX as i64 {
-            self.write.write_all(&[0xd1])?;
-            self.write.write_all(&(value as i16).to_be_bytes())?;
+            self.write_all(&[0xd1])?;
+            self.write_all(&(value as i16).to_be_bytes())?;
         } else if value >= std::i32::MIN as i64 && value <= std::i32::MAX as i64 {
-            self.write.write_all(&[0xd2])?;
-            self.write.write_all(&(value as i32).to_be_bytes())?;
+            self.write_all(&[0xd2])?;
+            self.write_all(&(value as i32).to_be_bytes())?;
         } else {
-            self.write.write_all(&[0xd3])?;
-            self.write.write_all(&value.to_be_bytes())?;
+            self.write_all(&[0xd3])?;
+            self.write_all(&value.to_be_bytes())?;
         }
         Ok(())
     }
 
     fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
         if value <= 127 {
-            self.write.write_all(&(value as u8).to_be_bytes())?;
+            self.write_all(&(value as u8).to_be_bytes())?;
         } else {
             self.write_unsigned_8_to_64(0xcc, value)?;
         }
@@ -99,62 +182,86 @@ impl<W: io::Write> ser::Serializer for Serializer<W> {
     }
 
     fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
-        if value as f32 as f64 == value {
-            self.write.write_all(&[0xca])?;
-            self.write.write_all(&(value as f32).to_be_bytes())?;
+        if self.canonical {
+            let value = if value.is_nan() { f64::NAN } else { value };
+            self.write_all(&[0xcb])?;
+            self.write_all(&value.to_be_bytes())?;
+        } else if value as f32 as f64 == value {
+            self.write_all(&[0xca])?;
+            self.write_all(&(value as f32).to_be_bytes())?;
         } else {
-            self.write.write_all(&[0xcb])?;
-            self.write.write_all(&value.to_be_bytes())?;
+            self.write_all(&[0xcb])?;
+            self.write_all(&value.to_be_bytes())?;
         }
         Ok(())
     }
 
     fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
-        if value.len() > std::u32::MAX as usize {
-            return Err(ser::SerializeError);
-        } else if value.len() < 32 {
-            self.write.write_all(&[0xa0 + value.len() as u8])?;
+        if value.len() < 32 {
+            self.write_all(&[0xa0 + value.len() as u8])?;
         } else {
-            self.write_unsigned_8_to_64(0xd9, value.len() as u64)?;
+            self.write_unsigned_8_to_32(0xd9, value.len() as u64)?;
         }
-        self.write.write_all(value.as_bytes())?;
+        self.write_all(value.as_bytes())?;
         Ok(())
     }
 
     fn serialize_enum(
         &mut self,
         value: u32,
-        _name: &'static str,
+        _name: Cow<'static, str>,
     ) -> Result<(), ser::SerializeError> {
         self.serialize_unsigned(value as u64)
     }
 
     fn start_struct(&mut self, len: usize) -> Result<(), ser::SerializeError> {
         if len < 16 {
-            self.write.write_all(&[0x80 + len as u8])?;
+            self.write_all(&[0x80 + len as u8])?;
         } else {
             self.write_unsigned_16_to_32(0xde, len as u64)?;
         }
+        if self.canonical {
+            self.fields.push(Vec::with_capacity(len));
+        }
         Ok(())
     }
 
     fn serialize_struct_field(
         &mut self,
         field_id: u32,
-        _field_name: &'static str,
+        field_name: Cow<'static, str>,
         value: &dyn ser::Serialize,
     ) -> Result<(), ser::SerializeError> {
-        self.serialize_unsigned(field_id as u64)?;
-        value.serialize(self)
+        if !self.canonical {
+            self.write_key(field_id, &field_name)?;
+            return value.serialize(self);
+        }
+
+        self.buffers.push(Vec::new());
+        self.write_key(field_id, &field_name)?;
+        let key_len = self.buffers.last().map_or(0, Vec::len);
+        value.serialize(self)?;
+        let field = self.buffers.pop().unwrap();
+        self.fields.last_mut().unwrap().push((key_len, field));
+        Ok(())
     }
 
     fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        if self.canonical {
+            let mut fields = self.fields.pop().unwrap();
+            // Shortest form unsigned integers sort by value, so ID keys end up
+            // in ID order.
+            fields.sort_by(|(a_len, a), (b_len, b)| a[..*a_len].cmp(&b[..*b_len]));
+            for (_, field) in fields {
+                self.write_all(&field)?;
+            }
+        }
         Ok(())
     }
 
     fn start_seq(&mut self, len: usize) -> Result<(), ser::SerializeError> {
         if len < 16 {
-            self.write.write_all(&[0x90 + len as u8])?;
+            self.write_all(&[0x90 + len as u8])?;
         } else {
             self.write_unsigned_16_to_32(0xdc, len as u64)?;
         }
@@ -171,18 +278,82 @@ impl<W: io::Write> ser::Serializer for Serializer<W> {
     fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
         Ok(())
     }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), ser::SerializeError> {
+        self.write_unsigned_8_to_32(0xc4, value.len() as u64)?;
+        self.write_all(value)?;
+        Ok(())
+    }
+
+    fn serialize_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), ser::SerializeError> {
+        match data.len() {
+            1 => self.write_all(&[0xd4])?,
+            2 => self.write_all(&[0xd5])?,
+            4 => self.write_all(&[0xd6])?,
+            8 => self.write_all(&[0xd7])?,
+            16 => self.write_all(&[0xd8])?,
+            len if len <= u8::MAX as usize => {
+                self.write_all(&[0xc7, len as u8])?;
+            }
+            len => self.write_unsigned_16_to_32(0xc8, len as u64)?,
+        }
+        self.write_all(&type_id.to_be_bytes())?;
+        self.write_all(data)?;
+        Ok(())
+    }
+
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), ser::SerializeError> {
+        self.serialize_ext(Timestamp::EXT_TYPE_ID, &value.to_ext_data())
+    }
 }
 
-pub struct Deserializer<R: io::Read> {
-    read: io::BufReader<R>,
+pub struct Deserializer<R: Read> {
+    read: R,
+    scratch: Vec<u8>,
+    limits: de::LimitTracker,
 }
 
-impl<R: io::Read> Deserializer<R> {
+impl<R: io::Read> Deserializer<IoRead<R>> {
     pub fn new(read: R) -> Self {
         Self {
-            read: io::BufReader::new(read),
+            read: IoRead::new(read),
+            scratch: Vec::new(),
+            limits: de::LimitTracker::new(de::DeserializeLimits::default()),
         }
     }
+}
+
+impl<'a> Deserializer<SliceRead<'a>> {
+    // Strings and extension data are borrowed from the slice without copies.
+    pub fn from_slice(slice: &'a [u8]) -> Self {
+        Self {
+            read: SliceRead::new(slice),
+            scratch: Vec::new(),
+            limits: de::LimitTracker::new(de::DeserializeLimits::default()),
+        }
+    }
+}
+
+impl<R: Read> Deserializer<R> {
+    pub fn with_limits(mut self, limits: de::DeserializeLimits) -> Self {
+        self.limits = de::LimitTracker::new(limits);
+        self
+    }
+
+    #[inline]
+    fn read_slice(&mut self, len: usize) -> Result<&[u8], de::DeserializeError> {
+        self.limits
+            .check_str_len(len)
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        self.read.read_slice(len, &mut self.scratch)
+    }
+
+    fn enter_collection(&mut self, len: usize) -> Result<(), de::DeserializeError> {
+        self.limits
+            .enter()
+            .and_then(|_| self.limits.check_collection_len(len))
+            .map_err(de::DeserializeError::LimitExceeded)
+    }
 
     #[inline]
     fn read_u8(&mut self) -> Result<u8, de::DeserializeError> {
@@ -255,7 +426,16 @@ impl<R: io::Read> Deserializer<R> {
     }
 
     fn parse(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
-        match self.read_u8()? {
+        let discriminant = self.read_u8()?;
+        self.parse_value(discriminant, visitor)
+    }
+
+    fn parse_value(
+        &mut self,
+        discriminant: u8,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match discriminant {
             val @ 0x80..=0x8f => {
                 self.parse_map((val - 0x80) as usize, &mut *visitor.visit_struct()?)
             }
@@ -311,6 +491,36 @@ impl<R: io::Read> Deserializer<R> {
                 let len = self.read_u32()? as usize;
                 self.parse_str(len, visitor)
             }
+            0xc4 => {
+                let len = self.read_u8()? as usize;
+                self.parse_bin(len, visitor)
+            }
+            0xc5 => {
+                let len = self.read_u16()? as usize;
+                self.parse_bin(len, visitor)
+            }
+            0xc6 => {
+                let len = self.read_u32()? as usize;
+                self.parse_bin(len, visitor)
+            }
+            0xd4 => self.parse_ext(1, visitor),
+            0xd5 => self.parse_ext(2, visitor),
+            0xd6 => self.parse_ext(4, visitor),
+            0xd7 => self.parse_ext(8, visitor),
+            0xd8 => self.parse_ext(16, visitor),
+            0xc7 => {
+                let len = self.read_u8()? as usize;
+                self.parse_ext(len, visitor)
+            }
+            0xc8 => {
+                let len = self.read_u16()? as usize;
+                self.parse_ext(len, visitor)
+            }
+            0xc9 => {
+                let len = self.read_u32()? as usize;
+                self.parse_ext(len, visitor)
+            }
+            // 0xc1 is never used.
             _ => Err(de::DeserializeError::ParsingError),
         }
     }
@@ -342,27 +552,99 @@ impl<R: io::Read> Deserializer<R> {
         len: usize,
         builder: &mut dyn de::StructBuilder,
     ) -> Result<(), de::DeserializeError> {
+        self.enter_collection(len)?;
         let mut null_visitor = de::NullVisitor;
         for _ in 0..len {
-            let id_d = self.read_u8()?;
-            let id = self.parse_unsigned(id_d)? as u32;
-            match builder.member(Some(id), None) {
+            let key_d = self.read_u8()?;
+            let member = match key_d {
+                0xa0..=0xbf | 0xd9..=0xdb => {
+                    let key = self.parse_key_str(key_d)?;
+                    let (id, name) = de::split_key(key);
+                    builder.member(id, name)
+                }
+                0x00..=0x7f | 0xcc..=0xcf => match u32::try_from(self.parse_unsigned(key_d)?) {
+                    Ok(id) => builder.member(Some(id), None),
+                    Err(_) => Err(de::DeserializeError::UnknownField),
+                },
+                0xe0..=0xff | 0xd0..=0xd3 => match u32::try_from(self.parse_signed(key_d)?) {
+                    Ok(id) => builder.member(Some(id), None),
+                    Err(_) => Err(de::DeserializeError::UnknownField),
+                },
+                // Any other key is valid msgpack but can't name a field.
+                _ => {
+                    self.parse_value(key_d, &mut null_visitor)?;
+                    Err(de::DeserializeError::UnknownField)
+                }
+            };
+            match member {
                 Ok(visitor) => self.parse(visitor)?,
                 Err(de::DeserializeError::UnknownField) => self.parse(&mut null_visitor)?,
                 Err(e) => Err(e)?,
             }
         }
+        self.limits.leave();
         builder.finish()
     }
 
+    fn parse_ext(
+        &mut self,
+        len: usize,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let type_id = self.read_i8()?;
+        let data = self.read_slice(len)?;
+        // Malformed timestamps are left to visitors of raw extension values,
+        // a `Timestamp` visitor rejects them.
+        if type_id == Timestamp::EXT_TYPE_ID {
+            if let Ok(timestamp) = Timestamp::from_ext_data(data) {
+                match visitor.visit_timestamp(timestamp) {
+                    Err(de::DeserializeError::UnimplementedVisit) => {}
+                    result => return result,
+                }
+            }
+        }
+        visitor.visit_ext(type_id, data)
+    }
+
+    fn parse_bin(
+        &mut self,
+        len: usize,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let bytes = self.read_slice(len)?;
+        match visitor.visit_bytes(bytes) {
+            Err(de::DeserializeError::UnimplementedVisit) => {}
+            result => return result,
+        }
+        let mut builder = visitor.visit_seq(Some(len))?;
+        for byte in bytes {
+            builder.element()?.visit_unsigned(*byte as u64)?;
+        }
+        builder.finish()
+    }
+
+    fn parse_key_str(&mut self, discriminant: u8) -> Result<&str, de::DeserializeError> {
+        let len = match discriminant {
+            val @ 0xa0..=0xbf => (val - 0xa0) as usize,
+            0xd9 => self.read_u8()? as usize,
+            0xda => self.read_u16()? as usize,
+            0xdb => self.read_u32()? as usize,
+            _ => return Err(de::DeserializeError::ParsingError),
+        };
+        let bytes = self.read_slice(len)?;
+        std::str::from_utf8(bytes).map_err(|_| de::DeserializeError::ParsingError)
+    }
+
     fn parse_array(
         &mut self,
         len: usize,
         builder: &mut dyn de::SeqBuilder,
     ) -> Result<(), de::DeserializeError> {
+        self.enter_collection(len)?;
         for _ in 0..len {
             self.parse(builder.element()?)?;
         }
+        self.limits.leave();
         builder.finish()
     }
 
@@ -371,15 +653,13 @@ impl<R: io::Read> Deserializer<R> {
         len: usize,
         visitor: &mut dyn de::Visitor,
     ) -> Result<(), de::DeserializeError> {
-        let mut buffer = Vec::new();
-        buffer.resize(len, 0);
-        self.read.read_exact(&mut buffer)?;
-        let s = std::str::from_utf8(&buffer).map_err(|_| de::DeserializeError::ParsingError)?;
-        visitor.visit_str(&s)
+        let bytes = self.read_slice(len)?;
+        let s = std::str::from_utf8(bytes).map_err(|_| de::DeserializeError::ParsingError)?;
+        visitor.visit_str(s)
     }
 }
 
-impl<R: io::Read> de::Deserializer for Deserializer<R> {
+impl<R: Read> de::Deserializer for Deserializer<R> {
     fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
         self.parse(visitor)
     }
@@ -0,0 +1,81 @@
+use macroserde::de;
+use std::io;
+
+pub trait Read {
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), de::DeserializeError>;
+
+    // Returns the next `len` bytes, either borrowed from the input or copied
+    // into `scratch`.
+    fn read_slice<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<&'s [u8], de::DeserializeError>;
+}
+
+pub struct IoRead<R: io::Read> {
+    read: io::BufReader<R>,
+}
+
+impl<R: io::Read> IoRead<R> {
+    pub fn new(read: R) -> Self {
+        Self {
+            read: io::BufReader::new(read),
+        }
+    }
+}
+
+impl<R: io::Read> Read for IoRead<R> {
+    #[inline]
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), de::DeserializeError> {
+        io::Read::read_exact(&mut self.read, buffer)?;
+        Ok(())
+    }
+
+    fn read_slice<'s>(
+        &'s mut self,
+        len: usize,
+        scratch: &'s mut Vec<u8>,
+    ) -> Result<&'s [u8], de::DeserializeError> {
+        scratch.clear();
+        de::read_bounded(&mut self.read, len, scratch)?;
+        Ok(scratch)
+    }
+}
+
+pub struct SliceRead<'a> {
+    slice: &'a [u8],
+}
+
+impl<'a> SliceRead<'a> {
+    pub fn new(slice: &'a [u8]) -> Self {
+        Self { slice }
+    }
+
+    #[inline]
+    fn take(&mut self, len: usize) -> Result<&'a [u8], de::DeserializeError> {
+        if len > self.slice.len() {
+            return Err(de::DeserializeError::UnexpectedEof);
+        }
+        let (head, tail) = self.slice.split_at(len);
+        self.slice = tail;
+        Ok(head)
+    }
+}
+
+impl<'a> Read for SliceRead<'a> {
+    #[inline]
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), de::DeserializeError> {
+        buffer.copy_from_slice(self.take(buffer.len())?);
+        Ok(())
+    }
+
+    #[inline]
+    fn read_slice<'s>(
+        &'s mut self,
+        len: usize,
+        _scratch: &'s mut Vec<u8>,
+    ) -> Result<&'s [u8], de::DeserializeError> {
+        self.take(len)
+    }
+}
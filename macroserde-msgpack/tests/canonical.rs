@@ -0,0 +1,68 @@
+use macroserde::macroserde;
+use macroserde::ser::Serialize;
+use macroserde_msgpack::KeyRepr;
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Inner {
+        b: u8 = 2,
+        a: u8 = 1,
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Outer {
+        car_brand: String = 88 @ "carBrand",
+        height: f32 = 5,
+        inner: Inner = 3,
+    }
+}
+
+fn outer(height: f32) -> Outer {
+    Outer {
+        car_brand: "x".to_owned(),
+        height,
+        inner: Inner { b: 7, a: 8 },
+    }
+}
+
+fn encode(value: &Outer, key_repr: KeyRepr) -> Vec<u8> {
+    let mut ser = macroserde_msgpack::Serializer::new(Vec::new())
+        .with_key_repr(key_repr)
+        .with_canonical(true);
+    value.serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+#[test]
+fn id_keys() {
+    assert_eq!(
+        encode(&outer(1.5), KeyRepr::Id),
+        [
+            0x83, // Outer
+            0x03, 0x82, 0x01, 0x08, 0x02, 0x07, // inner, sorted
+            0x05, 0xcb, 0x3f, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // height, as float 64
+            0x58, 0xa1, b'x', // carBrand
+        ]
+    );
+}
+
+#[test]
+fn name_keys() {
+    // Keys sort by their encoding: shorter strings first, then bytewise.
+    let mut expected = vec![0x83];
+    expected.extend_from_slice(b"\xa5inner\x82\xa1a\x08\xa1b\x07");
+    expected.extend_from_slice(b"\xa6height\xcb\x3f\xf8\x00\x00\x00\x00\x00\x00");
+    expected.extend_from_slice(b"\xa8carBrand\xa1x");
+    assert_eq!(encode(&outer(1.5), KeyRepr::Name), expected);
+}
+
+#[test]
+fn single_nan() {
+    let quiet = encode(&outer(f32::NAN), KeyRepr::Id);
+    let other = encode(&outer(f32::from_bits(0x7fc0_0001)), KeyRepr::Id);
+    let negative = encode(&outer(-f32::NAN), KeyRepr::Id);
+    assert_eq!(quiet, other);
+    assert_eq!(quiet, negative);
+}
@@ -0,0 +1,419 @@
+// Every format of the msgpack specification's type table, encoded and decoded:
+// https://github.com/msgpack/msgpack/blob/master/spec.md#formats
+
+use macroserde::bytes::Bytes;
+use macroserde::de::{Deserialize, DeserializeError};
+use macroserde::ext::{Ext, Timestamp};
+use macroserde::macroserde;
+use macroserde::ser::Serialize;
+use std::fmt::Debug;
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Small {
+        a: u8 = 1,
+        b: String = 2,
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Wide {
+        f1: u8 = 1,
+        f2: u8 = 2,
+        f3: u8 = 3,
+        f4: u8 = 4,
+        f5: u8 = 5,
+        f6: u8 = 6,
+        f7: u8 = 7,
+        f8: u8 = 8,
+        f9: u8 = 9,
+        f10: u8 = 10,
+        f11: u8 = 11,
+        f12: u8 = 12,
+        f13: u8 = 13,
+        f14: u8 = 14,
+        f15: u8 = 15,
+        f16: u8 = 16,
+    }
+}
+
+fn encode<T: Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    let mut ser = macroserde_msgpack::Serializer::new(Vec::new());
+    value.serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+fn decode<T: Deserialize + Debug + PartialEq>(bytes: &[u8]) -> Result<T, DeserializeError> {
+    let mut de = macroserde_msgpack::Deserializer::from_slice(bytes);
+    let from_slice = T::deserialize(&mut de);
+    let mut de = macroserde_msgpack::Deserializer::new(bytes);
+    let from_reader = T::deserialize(&mut de);
+    match (&from_slice, &from_reader) {
+        (Ok(a), Ok(b)) => assert_eq!(a, b),
+        (Err(_), Err(_)) => {}
+        _ => panic!("{:?} != {:?}", from_slice, from_reader),
+    }
+    from_slice
+}
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T, bytes: &[u8]) {
+    assert_eq!(encode(&value), bytes, "encoding {:?}", value);
+    assert_eq!(decode::<T>(bytes).unwrap(), value);
+}
+
+fn with_header(header: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut bytes = header.to_vec();
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn string(len: usize) -> String {
+    "x".repeat(len)
+}
+
+#[test]
+fn nil() {
+    check((), &[0xc0]);
+    check(None::<u8>, &[0xc0]);
+}
+
+#[test]
+fn never_used() {
+    assert!(matches!(
+        decode::<()>(&[0xc1]),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn bool() {
+    check(false, &[0xc2]);
+    check(true, &[0xc3]);
+}
+
+#[test]
+fn positive_fixint() {
+    check(0u8, &[0x00]);
+    check(127u8, &[0x7f]);
+    check(127i64, &[0x7f]);
+}
+
+#[test]
+fn uint() {
+    check(128u8, &[0xcc, 0x80]);
+    check(255u8, &[0xcc, 0xff]);
+    check(256u16, &[0xcd, 0x01, 0x00]);
+    check(65535u16, &[0xcd, 0xff, 0xff]);
+    check(65536u32, &[0xce, 0x00, 0x01, 0x00, 0x00]);
+    check(u32::MAX, &[0xce, 0xff, 0xff, 0xff, 0xff]);
+    check(
+        u32::MAX as u64 + 1,
+        &[0xcf, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00],
+    );
+    check(
+        u64::MAX,
+        &[0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+    );
+}
+
+#[test]
+fn positive_signed_use_uint() {
+    check(128i16, &[0xcc, 0x80]);
+    check(200i64, &[0xcc, 0xc8]);
+    check(65535i32, &[0xcd, 0xff, 0xff]);
+    check(i32::MAX, &[0xce, 0x7f, 0xff, 0xff, 0xff]);
+    check(
+        i64::MAX,
+        &[0xcf, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+    );
+}
+
+#[test]
+fn negative_fixint() {
+    check(-1i8, &[0xff]);
+    check(-32i64, &[0xe0]);
+}
+
+#[test]
+fn int() {
+    check(-33i8, &[0xd0, 0xdf]);
+    check(-128i8, &[0xd0, 0x80]);
+    check(-129i16, &[0xd1, 0xff, 0x7f]);
+    check(-32768i16, &[0xd1, 0x80, 0x00]);
+    check(-32769i32, &[0xd2, 0xff, 0xff, 0x7f, 0xff]);
+    check(i32::MIN, &[0xd2, 0x80, 0x00, 0x00, 0x00]);
+    check(
+        i32::MIN as i64 - 1,
+        &[0xd3, 0xff, 0xff, 0xff, 0xff, 0x7f, 0xff, 0xff, 0xff],
+    );
+    check(
+        i64::MIN,
+        &[0xd3, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+    );
+}
+
+#[test]
+fn non_shortest_integers() {
+    assert_eq!(decode::<u8>(&[0xcd, 0x00, 0x05]).unwrap(), 5);
+    assert_eq!(decode::<u8>(&[0xd0, 0x05]).unwrap(), 5);
+    assert_eq!(decode::<i64>(&[0xcc, 0x05]).unwrap(), 5);
+    assert_eq!(
+        decode::<u8>(&[0xd3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]).unwrap(),
+        1
+    );
+    assert!(matches!(
+        decode::<u8>(&[0xcd, 0x01, 0x00]),
+        Err(DeserializeError::IncompatibleNumericType)
+    ));
+    assert!(matches!(
+        decode::<u64>(&[0xff]),
+        Err(DeserializeError::IncompatibleNumericType)
+    ));
+}
+
+#[test]
+fn float() {
+    check(1.5f32, &[0xca, 0x3f, 0xc0, 0x00, 0x00]);
+    check(
+        0.1f64,
+        &[0xcb, 0x3f, 0xb9, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a],
+    );
+    assert_eq!(decode::<f64>(&[0xca, 0x3f, 0xc0, 0x00, 0x00]).unwrap(), 1.5);
+}
+
+#[test]
+fn fixstr() {
+    check(String::new(), &[0xa0]);
+    check(string(31), &with_header(&[0xbf], string(31).as_bytes()));
+}
+
+#[test]
+fn str() {
+    check(
+        string(32),
+        &with_header(&[0xd9, 0x20], string(32).as_bytes()),
+    );
+    check(
+        string(255),
+        &with_header(&[0xd9, 0xff], string(255).as_bytes()),
+    );
+    check(
+        string(256),
+        &with_header(&[0xda, 0x01, 0x00], string(256).as_bytes()),
+    );
+    check(
+        string(65535),
+        &with_header(&[0xda, 0xff, 0xff], string(65535).as_bytes()),
+    );
+    check(
+        string(65536),
+        &with_header(&[0xdb, 0x00, 0x01, 0x00, 0x00], string(65536).as_bytes()),
+    );
+}
+
+#[test]
+fn invalid_utf8_str() {
+    assert!(matches!(
+        decode::<String>(&[0xa2, 0xc3, 0x28]),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn bin() {
+    check(Bytes(Vec::new()), &[0xc4, 0x00]);
+    check(Bytes(vec![7; 255]), &with_header(&[0xc4, 0xff], &[7; 255]));
+    check(
+        Bytes(vec![7; 256]),
+        &with_header(&[0xc5, 0x01, 0x00], &[7; 256]),
+    );
+    check(
+        Bytes(vec![7; 65536]),
+        &with_header(&[0xc6, 0x00, 0x01, 0x00, 0x00], &[7; 65536]),
+    );
+}
+
+#[test]
+fn bin_as_seq() {
+    assert_eq!(
+        decode::<Vec<u8>>(&[0xc4, 0x02, 0x01, 0x02]).unwrap(),
+        vec![1, 2]
+    );
+    assert_eq!(
+        decode::<Bytes>(&[0x92, 0x01, 0x02]).unwrap(),
+        Bytes(vec![1, 2])
+    );
+}
+
+#[test]
+fn fixarray() {
+    check(Vec::<u8>::new(), &[0x90]);
+    check(vec![1u8; 15], &with_header(&[0x9f], &[1; 15]));
+}
+
+#[test]
+fn array() {
+    check(vec![1u8; 16], &with_header(&[0xdc, 0x00, 0x10], &[1; 16]));
+    check(
+        vec![1u8; 65535],
+        &with_header(&[0xdc, 0xff, 0xff], &[1; 65535]),
+    );
+    check(
+        vec![1u8; 65536],
+        &with_header(&[0xdd, 0x00, 0x01, 0x00, 0x00], &[1; 65536]),
+    );
+}
+
+#[test]
+fn fixmap() {
+    check(
+        Small {
+            a: 5,
+            b: "hi".to_owned(),
+        },
+        &[0x82, 0x01, 0x05, 0x02, 0xa2, b'h', b'i'],
+    );
+}
+
+#[test]
+fn map() {
+    let mut bytes = vec![0xde, 0x00, 0x10];
+    for id in 1..=16 {
+        bytes.extend_from_slice(&[id, 0x00]);
+    }
+    check(Wide::default(), &bytes);
+
+    // Structs never have enough fields to need a map 32.
+    assert_eq!(
+        decode::<Small>(&[0xdf, 0x00, 0x00, 0x00, 0x01, 0x01, 0x05]).unwrap(),
+        Small {
+            a: 5,
+            b: String::new(),
+        }
+    );
+}
+
+#[test]
+fn map_keys() {
+    // Field names, field names with their ID and IDs in any integer format.
+    assert_eq!(
+        decode::<Small>(&[0x82, 0xa1, b'a', 0x05, 0xa3, b'2', b':', b'b', 0xa1, b'x']).unwrap(),
+        Small {
+            a: 5,
+            b: "x".to_owned(),
+        }
+    );
+    assert_eq!(
+        decode::<Small>(&[0x81, 0xd0, 0x01, 0x05]).unwrap(),
+        Small {
+            a: 5,
+            b: String::new(),
+        }
+    );
+
+    // Keys that can't be field IDs are unknown fields rather than truncated.
+    let unknown_keys: &[&[u8]] = &[
+        &[0xcf, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01],
+        &[0xff],
+        &[0xc0],
+        &[0x92, 0x01, 0x02],
+        &[0x81, 0x01, 0x02],
+        &[0xc4, 0x01, 0x01],
+        &[0xd4, 0x01, 0x01],
+    ];
+    for key in unknown_keys {
+        let bytes = with_header(&[0x82], key);
+        let bytes = with_header(&bytes, &[0x05, 0x01, 0x06]);
+        assert_eq!(
+            decode::<Small>(&bytes).unwrap(),
+            Small {
+                a: 6,
+                b: String::new(),
+            },
+            "key {:x?}",
+            key
+        );
+    }
+}
+
+#[test]
+fn fixext() {
+    for (header, len) in [(0xd4, 1), (0xd5, 2), (0xd6, 4), (0xd7, 8), (0xd8, 16)].iter() {
+        let ext = Ext {
+            type_id: 5,
+            data: vec![9; *len],
+        };
+        check(ext, &with_header(&[*header, 0x05], &vec![9; *len]));
+    }
+}
+
+#[test]
+fn ext() {
+    for (header, len) in [
+        (&[0xc7, 0x00][..], 0),
+        (&[0xc7, 0x03][..], 3),
+        (&[0xc7, 0xff][..], 255),
+        (&[0xc8, 0x01, 0x00][..], 256),
+        (&[0xc8, 0xff, 0xff][..], 65535),
+        (&[0xc9, 0x00, 0x01, 0x00, 0x00][..], 65536),
+    ]
+    .iter()
+    {
+        let ext = Ext {
+            type_id: -5,
+            data: vec![9; *len],
+        };
+        let header = with_header(header, &[0xfb]);
+        check(ext, &with_header(&header, &vec![9; *len]));
+    }
+}
+
+#[test]
+fn timestamp() {
+    check(
+        Timestamp {
+            seconds: 1,
+            nanoseconds: 0,
+        },
+        &[0xd6, 0xff, 0x00, 0x00, 0x00, 0x01],
+    );
+    check(
+        Timestamp {
+            seconds: 1,
+            nanoseconds: 1,
+        },
+        &[0xd7, 0xff, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01],
+    );
+    check(
+        Timestamp {
+            seconds: -1,
+            nanoseconds: 1,
+        },
+        &[
+            0xc7, 0x0c, 0xff, 0x00, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff,
+        ],
+    );
+    assert!(matches!(
+        decode::<Timestamp>(&[0xd5, 0xff, 0x00, 0x00]),
+        Err(DeserializeError::InvalidExtData)
+    ));
+}
+
+#[test]
+fn truncated() {
+    let inputs: &[&[u8]] = &[
+        &[],
+        &[0xcd, 0x01],
+        &[0xa2, b'h'],
+        &[0xd9],
+        &[0xc5, 0x00, 0x02, 0x01],
+        &[0x92, 0x01],
+        &[0x81, 0x01],
+        &[0xd6, 0xff, 0x00],
+    ];
+    for input in inputs {
+        assert!(decode::<Option<Small>>(input).is_err(), "{:x?}", input);
+    }
+}
@@ -0,0 +1,157 @@
+use macroserde::de::{Deserialize, DeserializeError};
+use macroserde::ext::{Ext, ExtType, Timestamp};
+use macroserde::ser::Serialize;
+use macroserde::{macroserde, macroserde_ext};
+
+#[derive(Debug, PartialEq, Default)]
+struct Point {
+    x: u8,
+    y: u8,
+}
+
+impl ExtType for Point {
+    const TYPE_ID: i8 = 3;
+
+    fn to_ext_data(&self) -> Vec<u8> {
+        vec![self.x, self.y]
+    }
+
+    fn from_ext_data(data: &[u8]) -> Result<Self, DeserializeError> {
+        match data {
+            [x, y] => Ok(Point { x: *x, y: *y }),
+            _ => Err(DeserializeError::InvalidExtData),
+        }
+    }
+}
+
+macroserde_ext!(Point);
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Event {
+        at: Timestamp = 1,
+        place: Point = 2,
+        raw: Option<Ext> = 3,
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Skipped {
+        place: Point = 2,
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut ser = macroserde_msgpack::Serializer::new(Vec::new());
+    value.serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+fn decode<T: Deserialize>(bytes: &[u8]) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_msgpack::Deserializer::from_slice(bytes))
+}
+
+#[test]
+fn timestamp_widths() {
+    let timestamps = [
+        // 32 bits: seconds only, up to 2106.
+        (
+            Timestamp {
+                seconds: 750_988_800,
+                nanoseconds: 0,
+            },
+            4,
+        ),
+        // 64 bits: nanoseconds and 34 bits of seconds.
+        (
+            Timestamp {
+                seconds: 750_988_800,
+                nanoseconds: 250_000_000,
+            },
+            8,
+        ),
+        (
+            Timestamp {
+                seconds: (1 << 34) - 1,
+                nanoseconds: 0,
+            },
+            8,
+        ),
+        // 96 bits: anything else, including before 1970.
+        (
+            Timestamp {
+                seconds: -1,
+                nanoseconds: 0,
+            },
+            12,
+        ),
+        (
+            Timestamp {
+                seconds: i64::MAX,
+                nanoseconds: 999_999_999,
+            },
+            12,
+        ),
+    ];
+    for (timestamp, len) in timestamps.iter() {
+        assert_eq!(timestamp.to_ext_data().len(), *len, "{:?}", timestamp);
+        let event = Event {
+            at: *timestamp,
+            place: Point { x: 1, y: 2 },
+            raw: None,
+        };
+        assert_eq!(decode::<Event>(&encode(&event)).unwrap(), event);
+    }
+}
+
+#[test]
+fn ext_types() {
+    let event = Event {
+        at: Timestamp::default(),
+        place: Point { x: 1, y: 2 },
+        raw: Some(Ext {
+            type_id: 42,
+            data: vec![1, 2, 3],
+        }),
+    };
+    let bytes = encode(&event);
+    assert_eq!(&bytes[9..13], &[0xd5, 0x03, 0x01, 0x02]);
+    assert_eq!(decode::<Event>(&bytes).unwrap(), event);
+
+    // An extension of another type isn't a `Point`.
+    let mut other = bytes.clone();
+    other[10] = 4;
+    assert!(matches!(
+        decode::<Event>(&other),
+        Err(DeserializeError::UnknownExtType(4))
+    ));
+}
+
+#[test]
+fn malformed_timestamps() {
+    // A type -1 extension of 3 bytes isn't a timestamp.
+    let malformed = [0xc7, 0x03, 0xff, 0x01, 0x02, 0x03];
+    assert!(matches!(
+        decode::<Timestamp>(&malformed),
+        Err(DeserializeError::InvalidExtData)
+    ));
+    assert_eq!(
+        decode::<Ext>(&malformed).unwrap(),
+        Ext {
+            type_id: -1,
+            data: vec![1, 2, 3],
+        }
+    );
+
+    // Skipped as an unknown field.
+    let mut bytes = vec![0x82, 0x01];
+    bytes.extend_from_slice(&malformed);
+    bytes.extend_from_slice(&[0x02, 0xd5, 0x03, 0x01, 0x02]);
+    assert_eq!(
+        decode::<Skipped>(&bytes).unwrap(),
+        Skipped {
+            place: Point { x: 1, y: 2 },
+        }
+    );
+}
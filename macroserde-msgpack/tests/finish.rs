@@ -0,0 +1,61 @@
+use macroserde::ser::Serialize;
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+// Accepts `capacity` bytes, then fails like a full disk.
+#[derive(Clone)]
+struct Disk {
+    bytes: Rc<RefCell<Vec<u8>>>,
+    capacity: usize,
+}
+
+impl Disk {
+    fn new(capacity: usize) -> Self {
+        Self {
+            bytes: Rc::new(RefCell::new(Vec::new())),
+            capacity,
+        }
+    }
+}
+
+impl io::Write for Disk {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut bytes = self.bytes.borrow_mut();
+        let len = buf.len().min(self.capacity - bytes.len());
+        if len == 0 && !buf.is_empty() {
+            return Err(io::Error::other("disk full"));
+        }
+        bytes.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn flush_writes_buffered_bytes() {
+    let disk = Disk::new(usize::MAX);
+    let mut ser = macroserde_msgpack::Serializer::new(disk.clone());
+    "hello".serialize(&mut ser).unwrap();
+    assert!(disk.bytes.borrow().is_empty());
+    ser.flush().unwrap();
+    assert_eq!(*disk.bytes.borrow(), b"\xa5hello");
+    7u8.serialize(&mut ser).unwrap();
+    ser.finish().unwrap();
+    assert_eq!(*disk.bytes.borrow(), b"\xa5hello\x07");
+}
+
+#[test]
+fn write_errors_are_reported() {
+    let mut ser = macroserde_msgpack::Serializer::new(Disk::new(3));
+    "hello".serialize(&mut ser).unwrap();
+    assert!(ser.flush().is_err());
+
+    let mut ser = macroserde_msgpack::Serializer::new(Disk::new(3));
+    "hello".serialize(&mut ser).unwrap();
+    let error = ser.finish().err().unwrap();
+    assert_eq!(error.to_string(), "disk full");
+}
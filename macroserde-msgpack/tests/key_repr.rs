@@ -0,0 +1,61 @@
+use macroserde::de::Deserialize;
+use macroserde::macroserde;
+use macroserde::ser::Serialize;
+use macroserde_msgpack::KeyRepr;
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Car {
+        seats: u8 = 1,
+        car_brand: String = 88 @ "carBrand",
+    }
+}
+
+fn car() -> Car {
+    Car {
+        seats: 5,
+        car_brand: "x".to_owned(),
+    }
+}
+
+fn encode(key_repr: KeyRepr) -> Vec<u8> {
+    let mut ser = macroserde_msgpack::Serializer::new(Vec::new()).with_key_repr(key_repr);
+    car().serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+fn with_keys(first: &[u8], second: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x82];
+    bytes.extend_from_slice(first);
+    bytes.push(0x05);
+    bytes.extend_from_slice(second);
+    bytes.extend_from_slice(&[0xa1, b'x']);
+    bytes
+}
+
+#[test]
+fn writes_keys() {
+    assert_eq!(encode(KeyRepr::Id), with_keys(&[0x01], &[0x58]));
+    assert_eq!(
+        encode(KeyRepr::Name),
+        with_keys(b"\xa5seats", b"\xa8carBrand")
+    );
+    assert_eq!(
+        encode(KeyRepr::IdAndName),
+        with_keys(b"\xa71:seats", b"\xab88:carBrand")
+    );
+}
+
+#[test]
+fn reads_any_key() {
+    for key_repr in [KeyRepr::Id, KeyRepr::Name, KeyRepr::IdAndName] {
+        let bytes = encode(key_repr);
+        let mut de = macroserde_msgpack::Deserializer::from_slice(&bytes);
+        assert_eq!(Car::deserialize(&mut de).unwrap(), car(), "{:?}", key_repr);
+    }
+
+    // The ID wins over a mismatching name, and a bare number string is an ID.
+    let bytes = with_keys(b"\xa81:wheels", b"\xa288");
+    let mut de = macroserde_msgpack::Deserializer::from_slice(&bytes);
+    assert_eq!(Car::deserialize(&mut de).unwrap(), car());
+}
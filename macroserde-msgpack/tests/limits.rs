@@ -0,0 +1,73 @@
+use macroserde::bytes::Bytes;
+use macroserde::de::{Deserialize, DeserializeError, DeserializeLimits, Limit};
+use macroserde::value::Value;
+
+fn decode<T: Deserialize>(bytes: &[u8], limits: DeserializeLimits) -> Result<T, DeserializeError> {
+    let mut de = macroserde_msgpack::Deserializer::from_slice(bytes).with_limits(limits);
+    let from_slice = T::deserialize(&mut de);
+    let mut de = macroserde_msgpack::Deserializer::new(bytes).with_limits(limits);
+    let from_reader = T::deserialize(&mut de);
+    assert_eq!(from_slice.is_ok(), from_reader.is_ok());
+    from_slice
+}
+
+fn limit<T: Deserialize>(bytes: &[u8], limits: DeserializeLimits) -> Option<Limit> {
+    match decode::<T>(bytes, limits) {
+        Err(DeserializeError::LimitExceeded(limit)) => Some(limit),
+        _ => None,
+    }
+}
+
+#[test]
+fn hostile_length_prefixes() {
+    // Lengths of 4G elements or bytes followed by almost nothing.
+    let defaults = DeserializeLimits::default();
+    let array32 = [0xdd, 0xff, 0xff, 0xff, 0xff, 0x01];
+    assert_eq!(
+        limit::<Vec<u8>>(&array32, defaults),
+        Some(Limit::CollectionLen)
+    );
+    assert_eq!(
+        limit::<Value>(&array32, defaults),
+        Some(Limit::CollectionLen)
+    );
+    let map32 = [0xdf, 0xff, 0xff, 0xff, 0xff, 0x01, 0x01];
+    assert_eq!(limit::<Value>(&map32, defaults), Some(Limit::CollectionLen));
+    let str32 = [0xdb, 0xff, 0xff, 0xff, 0xff, b'x'];
+    assert_eq!(limit::<String>(&str32, defaults), Some(Limit::StrLen));
+    let bin32 = [0xc6, 0xff, 0xff, 0xff, 0xff, 0x00];
+    assert_eq!(limit::<Bytes>(&bin32, defaults), Some(Limit::StrLen));
+    let ext32 = [0xc9, 0xff, 0xff, 0xff, 0xff, 0x05, 0x00];
+    assert_eq!(limit::<Value>(&ext32, defaults), Some(Limit::StrLen));
+
+    // Within the limits, the truncated input is only an error.
+    let unlimited = DeserializeLimits::unlimited();
+    assert!(decode::<Vec<u8>>(&array32, unlimited).is_err());
+    assert!(decode::<String>(&str32, unlimited).is_err());
+}
+
+#[test]
+fn depth_and_total() {
+    let nested = [0x91; 200];
+    let limits = DeserializeLimits {
+        max_depth: 100,
+        ..DeserializeLimits::default()
+    };
+    assert_eq!(limit::<Value>(&nested, limits), Some(Limit::Depth));
+
+    // An array of 3 elements and 3 strings of 4 bytes allocate 15 in total.
+    let strings = b"\x93\xa4abcd\xa4abcd\xa4abcd";
+    let limits = DeserializeLimits {
+        max_total_alloc: 15,
+        ..DeserializeLimits::default()
+    };
+    assert!(decode::<Vec<String>>(strings, limits).is_ok());
+    let limits = DeserializeLimits {
+        max_total_alloc: 14,
+        ..DeserializeLimits::default()
+    };
+    assert_eq!(
+        limit::<Vec<String>>(strings, limits),
+        Some(Limit::TotalAlloc)
+    );
+}
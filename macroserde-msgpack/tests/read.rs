@@ -0,0 +1,73 @@
+use macroserde::de::{Deserialize, DeserializeError};
+use macroserde::macroserde;
+use macroserde::ser::Serialize;
+use macroserde_msgpack::{Deserializer, IoRead, Read, SliceRead};
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Person {
+        name: String = 1,
+        pets: Vec<String> = 4,
+        height: Option<f32> = 5,
+    }
+}
+
+fn person() -> Person {
+    Person {
+        name: "Steven".to_owned(),
+        pets: vec!["Bouboul".to_owned(), "Monsieur Puppy".to_owned()],
+        height: Some(1.75),
+    }
+}
+
+#[test]
+fn slice_read_borrows() {
+    let input = [0x01, 0x02, 0x03, 0x04];
+    let mut read = SliceRead::new(&input);
+    let mut scratch = Vec::new();
+    let mut byte = [0];
+    read.read_exact(&mut byte).unwrap();
+    assert_eq!(byte, [0x01]);
+    let slice = read.read_slice(2, &mut scratch).unwrap();
+    assert_eq!(slice, &[0x02, 0x03]);
+    assert!(std::ptr::eq(slice.as_ptr(), &input[1]));
+    assert!(scratch.is_empty());
+    assert!(matches!(
+        read.read_slice(2, &mut scratch),
+        Err(DeserializeError::UnexpectedEof)
+    ));
+}
+
+#[test]
+fn io_read_reuses_scratch() {
+    let input = [0x01, 0x02, 0x03, 0x04];
+    let mut read = IoRead::new(&input[..]);
+    let mut scratch = Vec::new();
+    assert_eq!(
+        read.read_slice(3, &mut scratch).unwrap(),
+        &[0x01, 0x02, 0x03]
+    );
+    let buffer = scratch.as_ptr();
+    assert_eq!(read.read_slice(1, &mut scratch).unwrap(), &[0x04]);
+    assert_eq!(scratch.as_ptr(), buffer);
+    assert!(read.read_slice(1, &mut scratch).is_err());
+}
+
+#[test]
+fn slice_and_reader_agree() {
+    let mut ser = macroserde_msgpack::Serializer::new(Vec::new());
+    person().serialize(&mut ser).unwrap();
+    let bytes = ser.finish().unwrap();
+
+    let mut de = Deserializer::from_slice(&bytes);
+    assert_eq!(Person::deserialize(&mut de).unwrap(), person());
+    let mut de = Deserializer::new(&bytes[..]);
+    assert_eq!(Person::deserialize(&mut de).unwrap(), person());
+
+    for len in 0..bytes.len() {
+        let mut de = Deserializer::from_slice(&bytes[..len]);
+        assert!(Person::deserialize(&mut de).is_err(), "{} bytes", len);
+        let mut de = Deserializer::new(&bytes[..len]);
+        assert!(Person::deserialize(&mut de).is_err(), "{} bytes", len);
+    }
+}
@@ -0,0 +1,649 @@
+use macroserde::{de, ser};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io;
+use std::io::Write;
+
+const WIRE_VARINT: u8 = 0;
+const WIRE_I64: u8 = 1;
+const WIRE_LEN: u8 = 2;
+const WIRE_START_GROUP: u8 = 3;
+const WIRE_END_GROUP: u8 = 4;
+const WIRE_I32: u8 = 5;
+
+const MAX_FIELD_NUMBER: u32 = (1 << 29) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignedEncoding {
+    /// `int64`, negative values take 10 bytes.
+    TwosComplement,
+    /// `sint64`. Unsigned integers are written the same way so that they can be
+    /// read back, values above `i64::MAX` can't be written.
+    ZigZag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatEncoding {
+    /// `double`, 8 bytes.
+    Double,
+    /// `float`, 4 bytes. Floats are always written as `double`, this is only
+    /// read.
+    Float,
+}
+
+// An encoded value, the field number it's written with is only known once it
+// has been serialized.
+enum Value {
+    Null,
+    Varint(u64),
+    I64(u64),
+    Len(Vec<u8>),
+    Repeated(Vec<Value>),
+}
+
+enum Frame {
+    // The fields of an open message, and whether it holds a union variant.
+    Message(Vec<u8>, bool),
+    Repeated(Vec<Value>),
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn write_tag(buf: &mut Vec<u8>, field_number: u32, wire_type: u8) {
+    write_varint(buf, (field_number as u64) << 3 | wire_type as u64);
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+// Null fields are left out, except for unit union variants which are written as
+// an empty message.
+fn write_field(
+    buf: &mut Vec<u8>,
+    field_number: u32,
+    value: Value,
+    is_variant: bool,
+) -> Result<(), ser::SerializeError> {
+    if field_number == 0 || field_number > MAX_FIELD_NUMBER {
+        return Err(ser::SerializeError);
+    }
+    match value {
+        Value::Null if is_variant => write_field(buf, field_number, Value::Len(Vec::new()), false)?,
+        Value::Null => {}
+        Value::Varint(value) => {
+            write_tag(buf, field_number, WIRE_VARINT);
+            write_varint(buf, value);
+        }
+        Value::I64(bits) => {
+            write_tag(buf, field_number, WIRE_I64);
+            buf.extend_from_slice(&bits.to_le_bytes());
+        }
+        Value::Len(bytes) => {
+            write_tag(buf, field_number, WIRE_LEN);
+            write_varint(buf, bytes.len() as u64);
+            buf.extend_from_slice(&bytes);
+        }
+        // Varints are packed, other values are written as one field per element.
+        Value::Repeated(elements) => {
+            let is_packed = !elements.is_empty()
+                && elements
+                    .iter()
+                    .all(|element| matches!(element, Value::Varint(_)));
+            if is_packed {
+                let mut packed = Vec::new();
+                for element in elements {
+                    if let Value::Varint(value) = element {
+                        write_varint(&mut packed, value);
+                    }
+                }
+                write_field(buf, field_number, Value::Len(packed), false)?;
+            } else {
+                for element in elements {
+                    match element {
+                        // Protobuf has no null elements nor nested repeated fields.
+                        Value::Null | Value::Repeated(_) => return Err(ser::SerializeError),
+                        element => write_field(buf, field_number, element, false)?,
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub struct Serializer<W: io::Write> {
+    write: io::BufWriter<W>,
+    signed_encoding: SignedEncoding,
+    current_value: Value,
+    stack: Vec<Frame>,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(w: W) -> Self {
+        Self {
+            write: io::BufWriter::new(w),
+            signed_encoding: SignedEncoding::TwosComplement,
+            current_value: Value::Null,
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn with_signed_encoding(mut self, signed_encoding: SignedEncoding) -> Self {
+        self.signed_encoding = signed_encoding;
+        self
+    }
+
+    pub fn flush(&mut self) -> Result<(), ser::SerializeError> {
+        self.write.flush()?;
+        Ok(())
+    }
+
+    // Dropping the serializer also flushes it but any write error is lost.
+    pub fn finish(self) -> io::Result<W> {
+        self.write
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)
+    }
+
+    // A protobuf message is a struct, other values can only be fields.
+    fn set(&mut self, value: Value) -> Result<(), ser::SerializeError> {
+        if self.stack.is_empty() {
+            return Err(ser::SerializeError);
+        }
+        self.current_value = value;
+        Ok(())
+    }
+}
+
+impl<W: io::Write> ser::Serializer for Serializer<W> {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.set(Value::Null)
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.set(Value::Varint(value as u64))
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        match self.signed_encoding {
+            SignedEncoding::TwosComplement => self.set(Value::Varint(value as u64)),
+            SignedEncoding::ZigZag => self.set(Value::Varint(zigzag(value))),
+        }
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        match self.signed_encoding {
+            SignedEncoding::TwosComplement => self.set(Value::Varint(value)),
+            SignedEncoding::ZigZag => {
+                let value = i64::try_from(value).map_err(|_| ser::SerializeError)?;
+                self.set(Value::Varint(zigzag(value)))
+            }
+        }
+    }
+
+    // Always a `double`, `float` fields are only read.
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.set(Value::I64(value.to_bits()))
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.set(Value::Len(value.as_bytes().to_vec()))
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), ser::SerializeError> {
+        self.set(Value::Len(value.to_vec()))
+    }
+
+    fn serialize_enum(
+        &mut self,
+        value: u32,
+        _name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.set(Value::Varint(value as u64))
+    }
+
+    fn start_struct(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Frame::Message(Vec::new(), false));
+        Ok(())
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        _field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        match self.stack.last_mut() {
+            Some(Frame::Message(buf, is_union)) => write_field(buf, field_id, value, *is_union),
+            _ => Err(ser::SerializeError),
+        }
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        let buf = match self.stack.pop() {
+            Some(Frame::Message(buf, _)) => buf,
+            _ => return Err(ser::SerializeError),
+        };
+        if self.stack.is_empty() {
+            // The top-level message isn't length delimited.
+            self.write.write_all(&buf)?;
+            self.current_value = Value::Null;
+        } else {
+            self.current_value = Value::Len(buf);
+        }
+        Ok(())
+    }
+
+    fn start_seq(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        if self.stack.is_empty() {
+            return Err(ser::SerializeError);
+        }
+        self.stack.push(Frame::Repeated(Vec::new()));
+        Ok(())
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        match self.stack.last_mut() {
+            Some(Frame::Repeated(elements)) => {
+                elements.push(value);
+                Ok(())
+            }
+            _ => Err(ser::SerializeError),
+        }
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        match self.stack.pop() {
+            Some(Frame::Repeated(elements)) => self.set(Value::Repeated(elements)),
+            _ => Err(ser::SerializeError),
+        }
+    }
+
+    fn serialize_union(
+        &mut self,
+        variant_id: u32,
+        variant_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        self.stack.push(Frame::Message(Vec::new(), true));
+        self.serialize_struct_field(variant_id, variant_name, value)?;
+        self.end_struct()
+    }
+}
+
+// A field value as found on the wire.
+enum WireValue<'a> {
+    Varint(u64),
+    I64(u64),
+    Len(&'a [u8]),
+    I32(u32),
+}
+
+fn read_varint(input: &mut &[u8]) -> Result<u64, de::DeserializeError> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = input
+            .split_first()
+            .ok_or(de::DeserializeError::UnexpectedEof)?;
+        *input = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(de::DeserializeError::ParsingError)
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], de::DeserializeError> {
+    if input.len() < len {
+        return Err(de::DeserializeError::UnexpectedEof);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+fn read_float(bytes: &[u8]) -> f64 {
+    if let Ok(bytes) = <[u8; 4]>::try_from(bytes) {
+        return f32::from_le_bytes(bytes) as f64;
+    }
+    let mut double = [0; 8];
+    double.copy_from_slice(bytes);
+    f64::from_le_bytes(double)
+}
+
+fn read_tag(input: &mut &[u8]) -> Result<(u32, u8), de::DeserializeError> {
+    let tag = read_varint(input)?;
+    let field_number = u32::try_from(tag >> 3).map_err(|_| de::DeserializeError::ParsingError)?;
+    if field_number == 0 || field_number > MAX_FIELD_NUMBER {
+        return Err(de::DeserializeError::ParsingError);
+    }
+    Ok((field_number, (tag & 0x7) as u8))
+}
+
+// Protobuf isn't self-describing: varints are read as enumerations or integers,
+// fixed width values as floats, and length delimited values as strings, bytes,
+// messages or packed varints, whichever the visitor accepts.
+pub struct Deserializer<'a> {
+    input: &'a [u8],
+    signed_encoding: SignedEncoding,
+    // The wire doesn't say the width of packed floats.
+    packed_float_encoding: FloatEncoding,
+    limits: de::LimitTracker,
+}
+
+impl<'a> Deserializer<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            signed_encoding: SignedEncoding::TwosComplement,
+            packed_float_encoding: FloatEncoding::Double,
+            limits: de::LimitTracker::new(de::DeserializeLimits::default()),
+        }
+    }
+
+    pub fn with_signed_encoding(mut self, signed_encoding: SignedEncoding) -> Self {
+        self.signed_encoding = signed_encoding;
+        self
+    }
+
+    pub fn with_packed_float_encoding(mut self, packed_float_encoding: FloatEncoding) -> Self {
+        self.packed_float_encoding = packed_float_encoding;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: de::DeserializeLimits) -> Self {
+        self.limits = de::LimitTracker::new(limits);
+        self
+    }
+
+    fn enter(&mut self) -> Result<(), de::DeserializeError> {
+        self.limits
+            .enter()
+            .map_err(de::DeserializeError::LimitExceeded)
+    }
+
+    fn check_collection_len(&mut self, len: usize) -> Result<(), de::DeserializeError> {
+        self.limits
+            .check_collection_len(len)
+            .map_err(de::DeserializeError::LimitExceeded)
+    }
+
+    fn check_collection_growth(&mut self, len: usize) -> Result<(), de::DeserializeError> {
+        self.limits
+            .check_collection_growth(len)
+            .map_err(de::DeserializeError::LimitExceeded)
+    }
+
+    // Returns `None` for groups, which are deprecated and skipped.
+    fn read_value(
+        &mut self,
+        input: &mut &'a [u8],
+        field_number: u32,
+        wire_type: u8,
+    ) -> Result<Option<WireValue<'a>>, de::DeserializeError> {
+        let value = match wire_type {
+            WIRE_VARINT => WireValue::Varint(read_varint(input)?),
+            WIRE_I64 => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(read_bytes(input, 8)?);
+                WireValue::I64(u64::from_le_bytes(bytes))
+            }
+            WIRE_LEN => {
+                let len = usize::try_from(read_varint(input)?)
+                    .map_err(|_| de::DeserializeError::UnexpectedEof)?;
+                WireValue::Len(read_bytes(input, len)?)
+            }
+            WIRE_START_GROUP => {
+                self.skip_group(input, field_number)?;
+                return Ok(None);
+            }
+            WIRE_I32 => {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(read_bytes(input, 4)?);
+                WireValue::I32(u32::from_le_bytes(bytes))
+            }
+            _ => return Err(de::DeserializeError::ParsingError),
+        };
+        Ok(Some(value))
+    }
+
+    fn skip_group(
+        &mut self,
+        input: &mut &'a [u8],
+        field_number: u32,
+    ) -> Result<(), de::DeserializeError> {
+        self.enter()?;
+        loop {
+            match read_tag(input)? {
+                (number, WIRE_END_GROUP) if number == field_number => break,
+                (_, WIRE_END_GROUP) => return Err(de::DeserializeError::ParsingError),
+                (number, wire_type) => {
+                    self.read_value(input, number, wire_type)?;
+                }
+            }
+        }
+        self.limits.leave();
+        Ok(())
+    }
+
+    fn visit_message(
+        &mut self,
+        mut input: &'a [u8],
+        builder: &mut dyn de::StructBuilder,
+    ) -> Result<(), de::DeserializeError> {
+        // The elements of repeated fields may be spread over the message, the
+        // values of each field are gathered first.
+        let mut fields: Vec<(u32, Vec<WireValue<'a>>)> = Vec::new();
+        while !input.is_empty() {
+            let (field_number, wire_type) = read_tag(&mut input)?;
+            if let Some(value) = self.read_value(&mut input, field_number, wire_type)? {
+                match fields
+                    .iter_mut()
+                    .find(|(number, _)| *number == field_number)
+                {
+                    Some((_, values)) => values.push(value),
+                    None => fields.push((field_number, vec![value])),
+                }
+            }
+        }
+
+        for (field_number, values) in &fields {
+            match builder.member(Some(*field_number), None) {
+                Ok(visitor) => self.visit_field(values, visitor)?,
+                Err(de::DeserializeError::UnknownField) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        builder.finish()
+    }
+
+    fn visit_field(
+        &mut self,
+        values: &[WireValue<'a>],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        // Optional values only visit messages and repeated fields through the
+        // visitor of their content. Missing fields are left to their default.
+        if visitor.hint() == de::Hint::Option {
+            return self.visit_field(values, visitor.visit_some()?);
+        }
+
+        if let [value] = values {
+            return match self.visit_value(value, visitor) {
+                Err(de::DeserializeError::UnimplementedVisit) => {
+                    self.visit_single_element(value, visitor)
+                }
+                result => result,
+            };
+        }
+
+        self.check_collection_len(values.len())?;
+        match visitor.visit_seq(Some(values.len())) {
+            Ok(mut builder) => {
+                for value in values {
+                    self.visit_value(value, builder.element()?)?;
+                }
+                return builder.finish();
+            }
+            Err(de::DeserializeError::UnimplementedVisit) => {}
+            Err(e) => return Err(e),
+        }
+        // The last value of a non repeated field wins.
+        self.visit_value(values.last().unwrap(), visitor)
+    }
+
+    // A repeated field with a single value, either a single element, packed
+    // varints or packed floats.
+    fn visit_single_element(
+        &mut self,
+        value: &WireValue<'a>,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let data = match value {
+            WireValue::Len(data) => *data,
+            value => {
+                let mut builder = visitor.visit_seq(Some(1))?;
+                self.visit_value(value, builder.element()?)?;
+                return builder.finish();
+            }
+        };
+
+        let mut builder = visitor.visit_seq(None)?;
+        let element = builder.element()?;
+        if element.hint() == de::Hint::Float {
+            let width = match self.packed_float_encoding {
+                FloatEncoding::Double => 8,
+                FloatEncoding::Float => 4,
+            };
+            let mut chunks = data.chunks_exact(width);
+            if !chunks.remainder().is_empty() {
+                return Err(de::DeserializeError::ParsingError);
+            }
+            if let Some(chunk) = chunks.next() {
+                self.check_collection_growth(1)?;
+                element.visit_float(read_float(chunk))?;
+            }
+            for (index, chunk) in chunks.enumerate() {
+                self.check_collection_growth(index + 2)?;
+                builder.element()?.visit_float(read_float(chunk))?;
+            }
+            return builder.finish();
+        }
+        match self.visit_len(data, element) {
+            // Enumerations also take strings, packed enumerations are rejected
+            // as unknown names.
+            Err(de::DeserializeError::UnimplementedVisit)
+            | Err(de::DeserializeError::UnknownEnumVariant) => {
+                let mut packed = data;
+                if !packed.is_empty() {
+                    self.check_collection_growth(1)?;
+                    self.visit_varint(read_varint(&mut packed)?, element)?;
+                }
+                let mut len = 1;
+                while !packed.is_empty() {
+                    len += 1;
+                    self.check_collection_growth(len)?;
+                    self.visit_varint(read_varint(&mut packed)?, builder.element()?)?;
+                }
+            }
+            result => result?,
+        }
+        builder.finish()
+    }
+
+    fn visit_value(
+        &mut self,
+        value: &WireValue<'a>,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match *value {
+            WireValue::Varint(value) => self.visit_varint(value, visitor),
+            WireValue::I64(bits) => visitor.visit_float(f64::from_bits(bits)),
+            WireValue::I32(bits) => visitor.visit_float(f32::from_bits(bits) as f64),
+            WireValue::Len(data) => self.visit_len(data, visitor),
+        }
+    }
+
+    fn visit_varint(
+        &mut self,
+        value: u64,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match visitor.visit_enum(u32::try_from(value).ok(), None) {
+            Err(de::DeserializeError::UnimplementedVisit) => {}
+            result => return result,
+        }
+        match self.signed_encoding {
+            SignedEncoding::ZigZag => visitor.visit_signed(unzigzag(value)),
+            SignedEncoding::TwosComplement if value <= i64::MAX as u64 => {
+                visitor.visit_unsigned(value)
+            }
+            // Either a negative integer or an unsigned one above `i64::MAX`.
+            SignedEncoding::TwosComplement => match visitor.visit_signed(value as i64) {
+                Err(de::DeserializeError::IncompatibleNumericType) => visitor.visit_unsigned(value),
+                result => result,
+            },
+        }
+    }
+
+    fn visit_len(
+        &mut self,
+        data: &'a [u8],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        if let Ok(s) = std::str::from_utf8(data) {
+            match visitor.visit_str(s) {
+                Err(de::DeserializeError::UnimplementedVisit) => {}
+                result => return result,
+            }
+        }
+        match visitor.visit_bytes(data) {
+            Err(de::DeserializeError::UnimplementedVisit) => {}
+            result => return result,
+        }
+        match visitor.visit_struct() {
+            Ok(mut builder) => {
+                self.enter()?;
+                self.visit_message(data, &mut *builder)?;
+                self.limits.leave();
+                return Ok(());
+            }
+            Err(de::DeserializeError::UnimplementedVisit) => {}
+            Err(e) => return Err(e),
+        }
+        // Unit union variants are written as empty messages.
+        if data.is_empty() {
+            return visitor.visit_null();
+        }
+        Err(de::DeserializeError::UnimplementedVisit)
+    }
+}
+
+impl<'a> de::Deserializer for Deserializer<'a> {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_struct()?;
+        self.enter()?;
+        self.visit_message(self.input, &mut *builder)?;
+        self.limits.leave();
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+// Handwritten messages: packed repeated fields, oneofs and unknown fields.
+
+use fixtures::{contacts, person, Contact, Date, Month, Occupation, Person};
+use macroserde::de::*;
+use macroserde::macroserde;
+use macroserde::ser::*;
+use macroserde_protobuf::{FloatEncoding, SignedEncoding};
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut ser = macroserde_protobuf::Serializer::new(Vec::new());
+    value.serialize(&mut ser).unwrap();
+    ser.finish().unwrap()
+}
+
+fn decode<T: Deserialize>(bytes: &[u8]) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_protobuf::Deserializer::new(bytes))
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Employment {
+        occupation: Occupation = 1,
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Numbers {
+        ints: Vec<i32> = 1,
+        months: Vec<Month> = 2,
+    }
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Floats {
+        xs: Vec<f64> = 1,
+    }
+}
+
+// `{ 1: 19, 2: 10, 3: 1993 }`
+const DATE: &[u8] = &[0x08, 0x13, 0x10, 0x0a, 0x18, 0xc9, 0x0f];
+
+fn birthday() -> Date {
+    Date {
+        day: 19,
+        month: Month::October,
+        year: 1993,
+    }
+}
+
+#[test]
+fn readme_person() {
+    let mut bytes = vec![0x0a, 0x06];
+    bytes.extend_from_slice(b"Steven");
+    bytes.extend_from_slice(&[0x10, 0x1b, 0x1a, 0x07]);
+    bytes.extend_from_slice(DATE);
+    bytes.extend_from_slice(&[0x22, 0x07]);
+    bytes.extend_from_slice(b"Bouboul");
+    bytes.extend_from_slice(&[0x22, 0x0e]);
+    bytes.extend_from_slice(b"Monsieur Puppy");
+    // A `float` height, and the oneof of the occupation.
+    bytes.push(0x2d);
+    bytes.extend_from_slice(&1.735f32.to_le_bytes());
+    bytes.extend_from_slice(&[0x30, 0x01, 0x3a, 0x0a, 0x12, 0x08]);
+    bytes.extend_from_slice(b"Engineer");
+    assert_eq!(decode::<Person>(&bytes).unwrap(), person());
+}
+
+#[test]
+fn optional_message() {
+    let mut some = vec![0x0a, 0x06];
+    some.extend_from_slice(b"Steven");
+    some.extend_from_slice(&[0x1a, 0x07]);
+    some.extend_from_slice(DATE);
+    // Field 88 is a two bytes tag.
+    some.extend_from_slice(&[0xc2, 0x05, 0x00]);
+    assert_eq!(decode::<Contact>(&some).unwrap(), contacts()[0]);
+    assert_eq!(encode(&contacts()[0]), some);
+
+    assert_eq!(decode::<Contact>(&[0x0a, 0x00]).unwrap(), contacts()[1]);
+    assert_eq!(encode(&contacts()[1]), [0x0a, 0x00]);
+}
+
+#[test]
+fn packed_repeated_fields() {
+    let expected = Numbers {
+        ints: vec![1, 150, -1],
+        months: vec![Month::January, Month::October],
+    };
+    let minus_one = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+
+    let mut packed = vec![0x0a, 0x0d, 0x01, 0x96, 0x01];
+    packed.extend_from_slice(&minus_one);
+    packed.extend_from_slice(&[0x12, 0x02, 0x01, 0x0a]);
+    assert_eq!(decode::<Numbers>(&packed).unwrap(), expected);
+    assert_eq!(encode(&expected), packed);
+
+    // Parsers must accept both forms, even spread over the message.
+    let mut unpacked = vec![0x08, 0x01, 0x10, 0x01, 0x08, 0x96, 0x01, 0x08];
+    unpacked.extend_from_slice(&minus_one);
+    unpacked.extend_from_slice(&[0x10, 0x0a]);
+    assert_eq!(decode::<Numbers>(&unpacked).unwrap(), expected);
+
+    let single = Numbers {
+        ints: vec![150],
+        months: Vec::new(),
+    };
+    assert_eq!(decode::<Numbers>(&[0x08, 0x96, 0x01]).unwrap(), single);
+    assert_eq!(
+        decode::<Numbers>(&[0x0a, 0x02, 0x96, 0x01]).unwrap(),
+        single
+    );
+
+    let zigzag = [0x0a, 0x04, 0x02, 0xac, 0x02, 0x01];
+    let mut de = macroserde_protobuf::Deserializer::new(&zigzag)
+        .with_signed_encoding(SignedEncoding::ZigZag);
+    assert_eq!(
+        Numbers::deserialize(&mut de).unwrap().ints,
+        vec![1, 150, -1]
+    );
+}
+
+#[test]
+fn packed_floats() {
+    let mut doubles = vec![0x0a, 0x10];
+    doubles.extend_from_slice(&1.5f64.to_le_bytes());
+    doubles.extend_from_slice(&2.5f64.to_le_bytes());
+    assert_eq!(
+        decode::<Floats>(&doubles).unwrap(),
+        Floats { xs: vec![1.5, 2.5] }
+    );
+
+    let mut floats = vec![0x0a, 0x08];
+    floats.extend_from_slice(&1.5f32.to_le_bytes());
+    floats.extend_from_slice(&2.5f32.to_le_bytes());
+    let mut de = macroserde_protobuf::Deserializer::new(&floats)
+        .with_packed_float_encoding(FloatEncoding::Float);
+    assert_eq!(
+        Floats::deserialize(&mut de).unwrap(),
+        Floats { xs: vec![1.5, 2.5] }
+    );
+
+    assert!(matches!(
+        decode::<Floats>(&[0x0a, 0x04, 0, 0, 0, 0]),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn oneof() {
+    let employed = Employment {
+        occupation: Occupation::Employed("Engineer".to_owned()),
+    };
+    let mut bytes = vec![0x0a, 0x0a, 0x12, 0x08];
+    bytes.extend_from_slice(b"Engineer");
+    assert_eq!(decode::<Employment>(&bytes).unwrap(), employed);
+    assert_eq!(encode(&employed), bytes);
+
+    // Unit variants are empty messages.
+    let unemployed = Employment {
+        occupation: Occupation::Unemployed,
+    };
+    assert_eq!(
+        decode::<Employment>(&[0x0a, 0x02, 0x0a, 0x00]).unwrap(),
+        unemployed
+    );
+    assert_eq!(encode(&unemployed), [0x0a, 0x02, 0x0a, 0x00]);
+
+    assert!(decode::<Employment>(&[0x0a, 0x02, 0x18, 0x00]).is_err());
+}
+
+#[test]
+fn unknown_fields() {
+    let mut bytes = DATE.to_vec();
+    // A varint, a fixed64, a length delimited value, a group holding a varint
+    // and a fixed32.
+    bytes.extend_from_slice(&[0x48, 0x96, 0x01]);
+    bytes.push(0x51);
+    bytes.extend_from_slice(&[0; 8]);
+    bytes.extend_from_slice(&[0x5a, 0x02, b'a', b'b']);
+    bytes.extend_from_slice(&[0x63, 0x08, 0x05, 0x64]);
+    bytes.push(0x6d);
+    bytes.extend_from_slice(&[0; 4]);
+    assert_eq!(decode::<Date>(&bytes).unwrap(), birthday());
+
+    // Groups must end with their own field number.
+    let mut unbalanced = DATE.to_vec();
+    unbalanced.extend_from_slice(&[0x63, 0x08, 0x05, 0x6c]);
+    assert!(matches!(
+        decode::<Date>(&unbalanced),
+        Err(DeserializeError::ParsingError)
+    ));
+
+    // Wire types 6 and 7 don't exist.
+    let mut invalid = DATE.to_vec();
+    invalid.push(0x4e);
+    assert!(matches!(
+        decode::<Date>(&invalid),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn only_field() {
+    // Null fields are left out, but not a unit variant that is the only field.
+    assert_eq!(encode(&Contact::default()), [0x0a, 0x00]);
+    assert_eq!(encode(&Employment::default()), [0x0a, 0x02, 0x0a, 0x00]);
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_collection_len: 2,
+        ..DeserializeLimits::default()
+    };
+    // Three packed doubles.
+    let mut encoded = vec![0x0a, 0x18];
+    encoded.resize(26, 0);
+    let mut de = macroserde_protobuf::Deserializer::new(&encoded).with_limits(limits);
+    assert!(matches!(
+        Floats::deserialize(&mut de),
+        Err(DeserializeError::LimitExceeded(Limit::CollectionLen))
+    ));
+}
@@ -0,0 +1,56 @@
+// Round trips of the README types.
+
+use fixtures::{contacts, person, Contact, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use macroserde_protobuf::SignedEncoding;
+use std::fmt::Debug;
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    for &signed_encoding in &[SignedEncoding::TwosComplement, SignedEncoding::ZigZag] {
+        let mut ser =
+            macroserde_protobuf::Serializer::new(Vec::new()).with_signed_encoding(signed_encoding);
+        value.serialize(&mut ser).unwrap();
+        let encoded = ser.finish().unwrap();
+        let mut de =
+            macroserde_protobuf::Deserializer::new(&encoded).with_signed_encoding(signed_encoding);
+        assert_eq!(
+            T::deserialize(&mut de).unwrap(),
+            value,
+            "decoding {:?}",
+            encoded
+        );
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+    check(Person {
+        age: -27,
+        ..person()
+    });
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    for contact in contacts() {
+        check::<Contact>(contact);
+    }
+}
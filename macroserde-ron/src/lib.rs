@@ -0,0 +1,395 @@
+mod parser;
+
+use macroserde::de::{DeserializeLimits, Hint};
+use macroserde::{de, ser};
+use parser::{Node, NodeKind, Parser};
+use std::borrow::Cow;
+use std::io;
+
+pub use parser::{ParseError, ParseErrorKind};
+
+enum Value {
+    Null,
+    Some(Box<Value>),
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Str(String),
+    Enum(Cow<'static, str>),
+    Union(Cow<'static, str>, Box<Value>),
+    Seq(Vec<Value>),
+    Struct(Vec<(Cow<'static, str>, Value)>),
+}
+
+// Structs are written `(name: "Steven", age: 27)`, enumerations and unit union
+// variants as bare identifiers (`OCT`), other union variants as
+// `hasJob("Engineer")` and optional values as `Some(1.8)` or `None`.
+pub struct Serializer {
+    current_value: Value,
+    stack: Vec<Value>,
+    // Number of `Some` wrapping the next value, for each open struct, sequence
+    // or union.
+    somes: Vec<usize>,
+}
+
+fn is_ident(s: &str) -> bool {
+    let mut bytes = s.bytes();
+    bytes
+        .next()
+        .is_some_and(|byte| byte.is_ascii_alphabetic() || byte == b'_')
+        && bytes.all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+        && !matches!(s, "true" | "false" | "None" | "Some" | "inf" | "NaN")
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self {
+            current_value: Value::Null,
+            stack: Vec::new(),
+            somes: vec![0],
+        }
+    }
+
+    pub fn write_pretty<W: io::Write>(&self, writer: &mut W, spaces: u16) -> io::Result<()> {
+        Self::write_value(writer, &self.current_value, Some((spaces as usize, 0)))
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        Self::write_value(writer, &self.current_value, None)
+    }
+
+    fn set(&mut self, mut value: Value) {
+        for _ in 0..std::mem::take(self.somes.last_mut().unwrap()) {
+            value = Value::Some(Box::new(value));
+        }
+        self.current_value = value;
+    }
+
+    fn write_str<W: io::Write>(writer: &mut W, s: &str) -> io::Result<()> {
+        write!(writer, "\"")?;
+        for c in s.chars() {
+            match c {
+                '"' => write!(writer, "\\\"")?,
+                '\\' => write!(writer, "\\\\")?,
+                '\n' => write!(writer, "\\n")?,
+                '\r' => write!(writer, "\\r")?,
+                '\t' => write!(writer, "\\t")?,
+                c if c.is_control() => write!(writer, "\\u{{{:x}}}", c as u32)?,
+                c => write!(writer, "{}", c)?,
+            }
+        }
+        write!(writer, "\"")
+    }
+
+    fn write_name<W: io::Write>(writer: &mut W, name: &str) -> io::Result<()> {
+        match is_ident(name) {
+            true => write!(writer, "{}", name),
+            false => Self::write_str(writer, name),
+        }
+    }
+
+    // Writes each item on its own line when pretty printing, `indent` is the
+    // number of spaces per level and the current level.
+    fn write_items<W: io::Write, T>(
+        writer: &mut W,
+        items: &[T],
+        (open, close): (&str, &str),
+        indent: Option<(usize, usize)>,
+        mut write_item: impl FnMut(&mut W, &T, Option<(usize, usize)>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        write!(writer, "{}", open)?;
+        match indent {
+            Some((spaces, level)) if !items.is_empty() => {
+                for item in items {
+                    write!(writer, "\n{:1$}", "", spaces * (level + 1))?;
+                    write_item(writer, item, Some((spaces, level + 1)))?;
+                    write!(writer, ",")?;
+                }
+                write!(writer, "\n{:1$}", "", spaces * level)?;
+            }
+            _ => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(writer, ", ")?;
+                    }
+                    write_item(writer, item, indent)?;
+                }
+            }
+        }
+        write!(writer, "{}", close)
+    }
+
+    fn write_value<W: io::Write>(
+        writer: &mut W,
+        value: &Value,
+        indent: Option<(usize, usize)>,
+    ) -> io::Result<()> {
+        match value {
+            Value::Null => write!(writer, "None"),
+            Value::Some(value) => {
+                write!(writer, "Some(")?;
+                Self::write_value(writer, value, indent)?;
+                write!(writer, ")")
+            }
+            Value::Bool(b) => write!(writer, "{}", b),
+            Value::Signed(i) => write!(writer, "{}", i),
+            Value::Unsigned(u) => write!(writer, "{}", u),
+            Value::Float(f) => write!(writer, "{:?}", f),
+            Value::Str(s) => Self::write_str(writer, s),
+            Value::Enum(name) => Self::write_name(writer, name),
+            // Variants whose name isn't an identifier are written as a struct
+            // with a single field.
+            Value::Union(name, value) if !is_ident(name) => {
+                write!(writer, "(")?;
+                Self::write_str(writer, name)?;
+                write!(writer, ": ")?;
+                Self::write_value(writer, value, indent)?;
+                write!(writer, ")")
+            }
+            Value::Union(name, value) => {
+                write!(writer, "{}", name)?;
+                if let Value::Null = **value {
+                    return Ok(());
+                }
+                write!(writer, "(")?;
+                Self::write_value(writer, value, indent)?;
+                write!(writer, ")")
+            }
+            Value::Seq(elements) => Self::write_items(
+                writer,
+                elements,
+                ("[", "]"),
+                indent,
+                |writer, value, indent| Self::write_value(writer, value, indent),
+            ),
+            Value::Struct(fields) => Self::write_items(
+                writer,
+                fields,
+                ("(", ")"),
+                indent,
+                |writer, (name, value), indent| {
+                    Self::write_name(writer, name)?;
+                    write!(writer, ": ")?;
+                    Self::write_value(writer, value, indent)
+                },
+            ),
+        }
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ser::Serializer for Serializer {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.set(Value::Null);
+        Ok(())
+    }
+
+    fn serialize_some(&mut self) -> Result<(), ser::SerializeError> {
+        *self.somes.last_mut().unwrap() += 1;
+        Ok(())
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.set(Value::Bool(value));
+        Ok(())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        self.set(Value::Signed(value));
+        Ok(())
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.set(Value::Unsigned(value));
+        Ok(())
+    }
+
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.set(Value::Float(value));
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.set(Value::Str(value.to_owned()));
+        Ok(())
+    }
+
+    fn serialize_enum(
+        &mut self,
+        _value: u32,
+        name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.set(Value::Enum(name));
+        Ok(())
+    }
+
+    fn serialize_union(
+        &mut self,
+        _variant_id: u32,
+        variant_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        self.somes.push(0);
+        value.serialize(self)?;
+        self.somes.pop();
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        self.set(Value::Union(variant_name, Box::new(value)));
+        Ok(())
+    }
+
+    fn start_struct(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Struct(Vec::with_capacity(len)));
+        self.somes.push(0);
+        Ok(())
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        _field_id: u32,
+        field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        if let Some(Value::Struct(fields)) = self.stack.last_mut() {
+            fields.push((field_name, value));
+        }
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        self.somes.pop();
+        let value = self.stack.pop().unwrap();
+        self.set(value);
+        Ok(())
+    }
+
+    fn start_seq(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Seq(Vec::with_capacity(len)));
+        self.somes.push(0);
+        Ok(())
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        if let Some(Value::Seq(elements)) = self.stack.last_mut() {
+            elements.push(value);
+        }
+        Ok(())
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        self.somes.pop();
+        let value = self.stack.pop().unwrap();
+        self.set(value);
+        Ok(())
+    }
+}
+
+pub struct Deserializer {
+    value: Node,
+}
+
+impl Deserializer {
+    pub fn new(s: &str) -> Result<Self, ParseError> {
+        Self::with_limits(s, DeserializeLimits::default())
+    }
+
+    pub fn with_limits(s: &str, limits: DeserializeLimits) -> Result<Self, ParseError> {
+        Ok(Self {
+            value: Parser::new(s, limits).parse()?,
+        })
+    }
+
+    fn visit_value(
+        value: &Node,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match &value.kind {
+            NodeKind::Null => visitor.visit_null(),
+            NodeKind::Some(inner) => match visitor.visit_some() {
+                Ok(visitor) => Self::visit_value(inner, visitor),
+                Err(de::DeserializeError::UnimplementedVisit) => Self::visit_value(inner, visitor),
+                Err(e) => Err(e),
+            },
+            // `Some` can be left out.
+            _ if visitor.hint() == Hint::Option => Self::visit_value(value, visitor.visit_some()?),
+            NodeKind::Bool(b) => visitor.visit_bool(*b),
+            NodeKind::Signed(i) => visitor.visit_signed(*i),
+            NodeKind::Unsigned(u) => visitor.visit_unsigned(*u),
+            NodeKind::Float(f) => visitor.visit_float(*f),
+            NodeKind::Str(s) => visitor.visit_str(s),
+            NodeKind::Ident(name) => match visitor.hint() {
+                Hint::Union(_) => {
+                    Self::visit_variant(name, visitor, |visitor| visitor.visit_null())
+                }
+                _ => match visitor.visit_enum(None, Some(name)) {
+                    Err(de::DeserializeError::UnimplementedVisit) => visitor.visit_str(name),
+                    result => result,
+                },
+            },
+            NodeKind::Union(name, inner) => {
+                Self::visit_variant(name, visitor, |visitor| Self::visit_value(inner, visitor))
+            }
+            NodeKind::Seq(elements) => {
+                let mut builder = visitor.visit_seq(Some(elements.len()))?;
+                for element in elements {
+                    Self::visit_value(element, builder.element()?)?;
+                }
+                builder.finish()
+            }
+            // `hasJob(day: 19)` is the variant of a union holding a struct.
+            NodeKind::Struct(Some(name), entries) if matches!(visitor.hint(), Hint::Union(_)) => {
+                Self::visit_variant(name, visitor, |visitor| {
+                    Self::visit_entries(entries, visitor)
+                })
+            }
+            NodeKind::Struct(_, entries) => Self::visit_entries(entries, visitor),
+        }
+        .map_err(|e| e.at(value.position))
+    }
+
+    fn visit_entries(
+        entries: &[(String, Node)],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_struct()?;
+        for (key, value) in entries {
+            let (id, name) = de::split_key(key);
+            match builder.member(id, name) {
+                Ok(visitor) => Self::visit_value(value, visitor)?,
+                Err(de::DeserializeError::UnknownField) => {}
+                Err(e) => Err(e.at(value.position))?,
+            }
+        }
+        builder.finish()
+    }
+
+    // Unions are visited as a struct with a single field.
+    fn visit_variant(
+        name: &str,
+        visitor: &mut dyn de::Visitor,
+        visit_value: impl FnOnce(&mut dyn de::Visitor) -> Result<(), de::DeserializeError>,
+    ) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_struct()?;
+        let (id, name) = de::split_key(name);
+        visit_value(builder.member(id, name)?)?;
+        builder.finish()
+    }
+}
+
+impl de::Deserializer for Deserializer {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        Self::visit_value(&self.value, visitor)
+    }
+}
@@ -0,0 +1,508 @@
+use macroserde::de::{DeserializeLimits, Limit, LimitTracker, Position};
+use std::fmt;
+
+pub enum NodeKind {
+    // `None`.
+    Null,
+    Some(Box<Node>),
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Str(String),
+    // A bare identifier, an enumeration or a unit union variant.
+    Ident(String),
+    Seq(Vec<Node>),
+    // `Name(key: value, ...)`, the name is optional. Keys are identifiers,
+    // IDs or strings.
+    Struct(Option<String>, Vec<(String, Node)>),
+    // `variant(value)`.
+    Union(String, Box<Node>),
+}
+
+pub struct Node {
+    pub kind: NodeKind,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    InvalidNumber,
+    InvalidEscape,
+    LimitExceeded(Limit),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+    // The source line containing the error.
+    pub snippet: String,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number"),
+            ParseErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            ParseErrorKind::LimitExceeded(Limit::Depth) => write!(f, "too deeply nested"),
+            ParseErrorKind::LimitExceeded(Limit::StrLen) => write!(f, "string too long"),
+            ParseErrorKind::LimitExceeded(Limit::CollectionLen) => {
+                write!(f, "too many elements")
+            }
+            ParseErrorKind::LimitExceeded(Limit::TotalAlloc) => write!(f, "input too large"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.position.line, self.position.column
+        )?;
+        writeln!(f, "{}", self.snippet)?;
+        let caret_offset: String = self
+            .snippet
+            .chars()
+            .take(self.position.column - 1)
+            .map(|c| if c == '\t' { c } else { ' ' })
+            .collect();
+        write!(f, "{}^", caret_offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Copy)]
+struct State {
+    index: usize,
+    line: usize,
+    column: usize,
+    line_start: usize,
+}
+
+pub struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    state: State,
+    limits: LimitTracker,
+}
+
+fn is_ident_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_'
+}
+
+fn is_ident_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(src: &'a str, limits: DeserializeLimits) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            state: State {
+                index: 0,
+                line: 1,
+                column: 1,
+                line_start: 0,
+            },
+            limits: LimitTracker::new(limits),
+        }
+    }
+
+    pub fn parse(mut self) -> Result<Node, ParseError> {
+        let node = self.parse_value()?;
+        self.skip_whitespace()?;
+        match self.peek() {
+            None => Ok(node),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.state.line,
+            column: self.state.column,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        let line_start = self.state.line_start;
+        let line_end = self.src[line_start..]
+            .find('\n')
+            .map(|i| line_start + i)
+            .unwrap_or(self.src.len());
+        ParseError {
+            kind,
+            position: self.position(),
+            snippet: self.src[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_owned(),
+        }
+    }
+
+    fn check_limit(&self, result: Result<(), Limit>) -> Result<(), ParseError> {
+        result.map_err(|limit| self.error(ParseErrorKind::LimitExceeded(limit)))
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.src[self.state.index..].chars().next() {
+            Some(c) => self.error(ParseErrorKind::UnexpectedChar(c)),
+            None => self.error(ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.state.index).copied()
+    }
+
+    #[inline]
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.state.index + offset).copied()
+    }
+
+    fn advance(&mut self) {
+        let byte = self.bytes[self.state.index];
+        self.state.index += 1;
+        if byte == b'\n' {
+            self.state.line += 1;
+            self.state.column = 1;
+            self.state.line_start = self.state.index;
+        } else if byte & 0xc0 != 0x80 {
+            // Columns count characters, not UTF-8 continuation bytes.
+            self.state.column += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        self.skip_whitespace()?;
+        if self.peek() != Some(byte) {
+            return Err(self.unexpected());
+        }
+        self.advance();
+        Ok(())
+    }
+
+    // Skips whitespace, `// line` comments and `/* block */` comments, which
+    // can be nested.
+    fn skip_whitespace(&mut self) -> Result<(), ParseError> {
+        loop {
+            match (self.peek(), self.peek_at(1)) {
+                (Some(b' '), _) | (Some(b'\t'), _) | (Some(b'\r'), _) | (Some(b'\n'), _) => {
+                    self.advance()
+                }
+                (Some(b'/'), Some(b'/')) => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.advance();
+                    }
+                }
+                (Some(b'/'), Some(b'*')) => {
+                    let mut depth = 0;
+                    loop {
+                        match (self.peek(), self.peek_at(1)) {
+                            (None, _) => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                            (Some(b'/'), Some(b'*')) => {
+                                depth += 1;
+                                self.advance();
+                            }
+                            (Some(b'*'), Some(b'/')) => {
+                                depth -= 1;
+                                self.advance();
+                                if depth == 0 {
+                                    self.advance();
+                                    break;
+                                }
+                            }
+                            _ => {}
+                        }
+                        self.advance();
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Node, ParseError> {
+        self.skip_whitespace()?;
+        let position = self.position();
+        let kind = match self.peek() {
+            None => return Err(self.unexpected()),
+            Some(b'"') => NodeKind::Str(self.parse_string()?),
+            Some(b'[') => self.parse_seq()?,
+            Some(b'(') => self.parse_parenthesized(None)?,
+            Some(b'-') | Some(b'+') | Some(b'.') | Some(b'0'..=b'9') => self.parse_number()?,
+            Some(byte) if is_ident_start(byte) => {
+                let ident = self.parse_ident();
+                match ident {
+                    "true" => NodeKind::Bool(true),
+                    "false" => NodeKind::Bool(false),
+                    "None" => NodeKind::Null,
+                    "inf" => NodeKind::Float(f64::INFINITY),
+                    "NaN" => NodeKind::Float(f64::NAN),
+                    "Some" => {
+                        self.expect(b'(')?;
+                        let value = self.parse_nested(Self::parse_value)?;
+                        self.skip_trailing_comma()?;
+                        self.expect(b')')?;
+                        NodeKind::Some(Box::new(value))
+                    }
+                    _ => {
+                        let ident = ident.to_owned();
+                        let before = self.state;
+                        self.skip_whitespace()?;
+                        if self.peek() == Some(b'(') {
+                            self.parse_parenthesized(Some(ident))?
+                        } else {
+                            self.state = before;
+                            NodeKind::Ident(ident)
+                        }
+                    }
+                }
+            }
+            Some(_) => return Err(self.unexpected()),
+        };
+        Ok(Node { kind, position })
+    }
+
+    fn parse_nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let limit = self.limits.enter();
+        self.check_limit(limit)?;
+        let result = parse(self);
+        self.limits.leave();
+        result
+    }
+
+    fn skip_trailing_comma(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(b',') {
+            self.advance();
+        }
+        Ok(())
+    }
+
+    fn parse_ident(&mut self) -> &'a str {
+        let start = self.state.index;
+        while self.peek().is_some_and(is_ident_char) {
+            self.advance();
+        }
+        &self.src[start..self.state.index]
+    }
+
+    fn parse_seq(&mut self) -> Result<NodeKind, ParseError> {
+        self.advance();
+        let elements = self.parse_nested(|parser| {
+            let mut elements = Vec::new();
+            loop {
+                parser.skip_whitespace()?;
+                if parser.peek() == Some(b']') {
+                    break;
+                }
+                elements.push(parser.parse_value()?);
+                parser.skip_whitespace()?;
+                match parser.peek() {
+                    Some(b',') => parser.advance(),
+                    Some(b']') => break,
+                    _ => return Err(parser.unexpected()),
+                }
+            }
+            Ok(elements)
+        })?;
+        self.advance();
+        let limit = self.limits.check_collection_len(elements.len());
+        self.check_limit(limit)?;
+        Ok(NodeKind::Seq(elements))
+    }
+
+    // `(key: value, ...)` is a struct, `name(value)` a union variant.
+    fn parse_parenthesized(&mut self, name: Option<String>) -> Result<NodeKind, ParseError> {
+        self.advance();
+        self.skip_whitespace()?;
+        let before = self.state;
+        let is_struct =
+            self.peek() == Some(b')') || (self.parse_key().is_ok() && self.expect(b':').is_ok());
+        self.state = before;
+
+        match (is_struct, name) {
+            (true, name) => {
+                let entries = self.parse_nested(Self::parse_struct_entries)?;
+                self.advance();
+                let limit = self.limits.check_collection_len(entries.len());
+                self.check_limit(limit)?;
+                Ok(NodeKind::Struct(name, entries))
+            }
+            (false, Some(name)) => {
+                let value = self.parse_nested(Self::parse_value)?;
+                self.skip_trailing_comma()?;
+                self.expect(b')')?;
+                Ok(NodeKind::Union(name, Box::new(value)))
+            }
+            (false, None) => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_struct_entries(&mut self) -> Result<Vec<(String, Node)>, ParseError> {
+        let mut entries = Vec::new();
+        loop {
+            self.skip_whitespace()?;
+            if self.peek() == Some(b')') {
+                break;
+            }
+            let key = self.parse_key()?;
+            self.expect(b':')?;
+            entries.push((key, self.parse_value()?));
+            self.skip_whitespace()?;
+            match self.peek() {
+                Some(b',') => self.advance(),
+                Some(b')') => break,
+                _ => return Err(self.unexpected()),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn parse_key(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(b'"') => self.parse_string(),
+            Some(byte) if is_ident_char(byte) => Ok(self.parse_ident().to_owned()),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        self.advance();
+        let mut s = String::new();
+        let mut start = self.state.index;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    s.push_str(&self.src[start..self.state.index]);
+                    self.advance();
+                    s.push(self.parse_escape()?);
+                    start = self.state.index;
+                }
+                Some(_) => self.advance(),
+            }
+        }
+        s.push_str(&self.src[start..self.state.index]);
+        self.advance();
+        let limit = self.limits.check_str_len(s.len());
+        self.check_limit(limit)?;
+        Ok(s)
+    }
+
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let escaped = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\'') => '\'',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'0') => '\0',
+            // `\u{1F600}`.
+            Some(b'u') if self.peek_at(1) == Some(b'{') => {
+                self.advance();
+                self.advance();
+                let start = self.state.index;
+                while self.peek().is_some_and(|byte| byte.is_ascii_hexdigit()) {
+                    self.advance();
+                }
+                let c = u32::from_str_radix(&self.src[start..self.state.index], 16)
+                    .ok()
+                    .and_then(char::from_u32);
+                return match (c, self.peek()) {
+                    (Some(c), Some(b'}')) => {
+                        self.advance();
+                        Ok(c)
+                    }
+                    _ => Err(self.error(ParseErrorKind::InvalidEscape)),
+                };
+            }
+            _ => return Err(self.error(ParseErrorKind::InvalidEscape)),
+        };
+        self.advance();
+        Ok(escaped)
+    }
+
+    // Integers are decimal or prefixed by `0x`, `0o` or `0b`, digits can be
+    // separated by underscores.
+    fn parse_number(&mut self) -> Result<NodeKind, ParseError> {
+        let position = self.position();
+        let invalid = |parser: &Self| ParseError {
+            position,
+            ..parser.error(ParseErrorKind::InvalidNumber)
+        };
+
+        let negative = self.peek() == Some(b'-');
+        if matches!(self.peek(), Some(b'-') | Some(b'+')) {
+            self.advance();
+        }
+        if self.src[self.state.index..].starts_with("inf") {
+            self.parse_ident();
+            return match negative {
+                true => Ok(NodeKind::Float(f64::NEG_INFINITY)),
+                false => Ok(NodeKind::Float(f64::INFINITY)),
+            };
+        }
+
+        let start = self.state.index;
+        let mut is_float = false;
+        let radix = match (self.peek(), self.peek_at(1)) {
+            (Some(b'0'), Some(b'x')) => 16,
+            (Some(b'0'), Some(b'o')) => 8,
+            (Some(b'0'), Some(b'b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            self.advance();
+            self.advance();
+        }
+        let digits_start = self.state.index;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'0'..=b'9' | b'_' => {}
+                b'a'..=b'f' | b'A'..=b'F' if radix == 16 => {}
+                b'.' | b'e' | b'E' if radix == 10 => is_float = true,
+                b'+' | b'-'
+                    if is_float && matches!(self.bytes[self.state.index - 1], b'e' | b'E') => {}
+                _ => break,
+            }
+            self.advance();
+        }
+        let digits: String = self.src[digits_start..self.state.index]
+            .chars()
+            .filter(|c| *c != '_')
+            .collect();
+        if self.state.index == start || digits.is_empty() {
+            return Err(invalid(self));
+        }
+
+        if is_float {
+            let value: f64 = digits.parse().map_err(|_| invalid(self))?;
+            return Ok(NodeKind::Float(if negative { -value } else { value }));
+        }
+        let value = u64::from_str_radix(&digits, radix).map_err(|_| invalid(self))?;
+        match negative {
+            false => Ok(NodeKind::Unsigned(value)),
+            true if value <= i64::MAX as u64 + 1 => {
+                Ok(NodeKind::Signed((value as i64).wrapping_neg()))
+            }
+            true => Err(invalid(self)),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// Handwritten RON: comments, type names, unions and keys by ID.
+
+use fixtures::{contacts, person, Contact, Date, Month, Occupation, Person};
+use macroserde::de::*;
+use macroserde_ron::ParseErrorKind;
+
+fn decode<T: Deserialize>(s: &str) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_ron::Deserializer::new(s).unwrap())
+}
+
+fn parse_error(s: &str) -> ParseErrorKind {
+    match macroserde_ron::Deserializer::new(s) {
+        Ok(_) => panic!("{:?} parsed", s),
+        Err(e) => e.kind,
+    }
+}
+
+#[test]
+fn request_example() {
+    let ron =
+        r#"Person(name: "Steven", occupation: hasJob("Engineer"), birth_date: Date(month: OCT))"#;
+    assert_eq!(
+        decode::<Person>(ron).unwrap(),
+        Person {
+            name: "Steven".to_owned(),
+            occupation: Occupation::Employed("Engineer".to_owned()),
+            birth_date: Date {
+                month: Month::October,
+                ..Date::default()
+            },
+            ..Person::default()
+        }
+    );
+}
+
+#[test]
+fn readme_person() {
+    let ron = r#"
+// The person of the README.
+Person(
+    name: "Steven",
+    age: 27,
+    /* Fields may be named, numbered or both, /* and block comments nest. */ */
+    3: Date(day: 19, month: OCT, "3:year": 1993),
+    pets: ["Bouboul", "Monsieur Puppy",], // Trailing commas are allowed.
+    height: Some(1.735),
+    carBrand: None,
+    IsCool: true,
+    occupation: hasJob("Engineer"),
+)
+"#;
+    assert_eq!(decode::<Person>(ron).unwrap(), person());
+}
+
+#[test]
+fn unions_and_options() {
+    let ron = "(occupation: Unemployed, height: 1.5)";
+    let unemployed = decode::<Person>(ron).unwrap();
+    assert_eq!(unemployed.occupation, Occupation::Unemployed);
+    // `Some` can be left out.
+    assert_eq!(unemployed.height, Some(1.5));
+
+    let ron = r#"[
+        Contact(name: "Steven", birth_date: Some((day: 19, month: 10, year: 1993)), carBrand: Some("")),
+        Contact(name: "", birth_date: None),
+    ]"#;
+    assert_eq!(decode::<Vec<Contact>>(ron).unwrap(), contacts());
+
+    assert!(decode::<Person>("(occupation: isRetired)").is_err());
+}
+
+#[test]
+fn syntax_errors() {
+    assert_eq!(
+        parse_error("(name: \"Steven\" /* unterminated"),
+        ParseErrorKind::UnexpectedEof
+    );
+    assert_eq!(
+        parse_error("(name: \"Ste\\qven\")"),
+        ParseErrorKind::InvalidEscape
+    );
+    assert_eq!(
+        parse_error("(age: 2x7)"),
+        ParseErrorKind::UnexpectedChar('x')
+    );
+    let error = macroserde_ron::Deserializer::new("(\n  name: \"Steven\",\n  age: ,\n)").err();
+    assert_eq!(error.map(|e| e.position.line), Some(3));
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    let error = macroserde_ron::Deserializer::with_limits("(name: \"Steven\")", limits).err();
+    assert!(
+        matches!(error, Some(ref e) if e.kind == ParseErrorKind::LimitExceeded(Limit::StrLen)),
+        "{:?}",
+        error
+    );
+    assert_eq!(
+        parse_error(&"[".repeat(1000)),
+        ParseErrorKind::LimitExceeded(Limit::Depth)
+    );
+}
@@ -0,0 +1,48 @@
+// Round trips of the README types.
+
+use fixtures::{contacts, person, Contact, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use std::fmt::Debug;
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    let mut ser = macroserde_ron::Serializer::new();
+    value.serialize(&mut ser).unwrap();
+    for &spaces in &[0, 4] {
+        let mut out = Vec::new();
+        if spaces == 0 {
+            ser.write(&mut out).unwrap();
+        } else {
+            ser.write_pretty(&mut out, spaces).unwrap();
+        }
+        let encoded = String::from_utf8(out).unwrap();
+        let decoded = T::deserialize(&mut macroserde_ron::Deserializer::new(&encoded).unwrap());
+        assert_eq!(decoded.unwrap(), value, "decoding {}", encoded);
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    check::<Vec<Contact>>(contacts());
+}
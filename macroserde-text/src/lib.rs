@@ -0,0 +1,422 @@
+mod parser;
+
+use macroserde::de::{DeserializeLimits, Hint};
+use macroserde::ext::Timestamp;
+use macroserde::{de, ser};
+use parser::{Node, NodeKind, Parser};
+use std::borrow::Cow;
+use std::io;
+
+pub use parser::{ParseError, ParseErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRepr {
+    /// `10: "Peugeot"`
+    Id,
+    /// `carBrand: "Peugeot"`
+    Name,
+    /// `10:carBrand: "Peugeot"`
+    IdAndName,
+}
+
+enum Value {
+    Null,
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Enum(Cow<'static, str>),
+    Ext(i8, Vec<u8>),
+    Timestamp(Timestamp),
+    Seq(Vec<Value>),
+    Struct(Vec<(u32, Cow<'static, str>, Value)>),
+}
+
+// Fields are written `1:name: "Steven"` and `3:birth_date { 1:day: 19 }`, like
+// the protobuf text format. The fields of the root struct aren't enclosed in
+// braces. Sequences are `["Bouboul", "Monsieur Puppy"]`, bytes `b"\x00\x01"`
+// and enumerations bare identifiers.
+pub struct Serializer {
+    current_value: Value,
+    stack: Vec<Value>,
+    key_repr: KeyRepr,
+}
+
+fn is_ident(s: &str) -> bool {
+    let mut bytes = s.bytes();
+    bytes
+        .next()
+        .is_some_and(|byte| byte.is_ascii_alphabetic() || byte == b'_')
+        && bytes.all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+        && !matches!(s, "null" | "true" | "false" | "inf" | "nan")
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self {
+            current_value: Value::Null,
+            stack: Vec::new(),
+            key_repr: KeyRepr::IdAndName,
+        }
+    }
+
+    pub fn with_key_repr(mut self, key_repr: KeyRepr) -> Self {
+        self.key_repr = key_repr;
+        self
+    }
+
+    pub fn write_pretty<W: io::Write>(&self, writer: &mut W, spaces: u16) -> io::Result<()> {
+        self.write_root(writer, Some((spaces as usize, 0)))
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_root(writer, None)
+    }
+
+    fn write_root<W: io::Write>(
+        &self,
+        writer: &mut W,
+        indent: Option<(usize, usize)>,
+    ) -> io::Result<()> {
+        match &self.current_value {
+            Value::Struct(fields) => {
+                for (i, field) in fields.iter().enumerate() {
+                    match (i, indent) {
+                        (0, _) => {}
+                        (_, Some(_)) => writeln!(writer)?,
+                        (_, None) => write!(writer, " ")?,
+                    }
+                    self.write_field(writer, field, indent)?;
+                }
+                Ok(())
+            }
+            value => self.write_value(writer, value, indent),
+        }
+    }
+
+    fn write_str<W: io::Write>(writer: &mut W, s: &str) -> io::Result<()> {
+        write!(writer, "\"")?;
+        for c in s.chars() {
+            match c {
+                '"' => write!(writer, "\\\"")?,
+                '\\' => write!(writer, "\\\\")?,
+                '\n' => write!(writer, "\\n")?,
+                '\r' => write!(writer, "\\r")?,
+                '\t' => write!(writer, "\\t")?,
+                c if c.is_control() => write!(writer, "\\u{{{:x}}}", c as u32)?,
+                c => write!(writer, "{}", c)?,
+            }
+        }
+        write!(writer, "\"")
+    }
+
+    fn write_bytes<W: io::Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+        write!(writer, "b\"")?;
+        for byte in bytes {
+            match byte {
+                b'"' => write!(writer, "\\\"")?,
+                b'\\' => write!(writer, "\\\\")?,
+                b' '..=b'~' => write!(writer, "{}", *byte as char)?,
+                _ => write!(writer, "\\x{:02x}", byte)?,
+            }
+        }
+        write!(writer, "\"")
+    }
+
+    fn write_field<W: io::Write>(
+        &self,
+        writer: &mut W,
+        (id, name, value): &(u32, Cow<'static, str>, Value),
+        indent: Option<(usize, usize)>,
+    ) -> io::Result<()> {
+        match self.key_repr {
+            KeyRepr::Id => write!(writer, "{}", id)?,
+            KeyRepr::Name if is_ident(name) => write!(writer, "{}", name)?,
+            KeyRepr::Name => Self::write_str(writer, name)?,
+            KeyRepr::IdAndName if is_ident(name) => write!(writer, "{}:{}", id, name)?,
+            KeyRepr::IdAndName => Self::write_str(writer, &format!("{}:{}", id, name))?,
+        }
+        match value {
+            Value::Struct(_) => write!(writer, " ")?,
+            _ => write!(writer, ": ")?,
+        }
+        self.write_value(writer, value, indent)
+    }
+
+    // Writes each item on its own line when pretty printing, `indent` is the
+    // number of spaces per level and the current level.
+    fn write_items<W: io::Write, T>(
+        writer: &mut W,
+        items: &[T],
+        (open, separator, close): (&str, &str, &str),
+        indent: Option<(usize, usize)>,
+        mut write_item: impl FnMut(&mut W, &T, Option<(usize, usize)>) -> io::Result<()>,
+    ) -> io::Result<()> {
+        write!(writer, "{}", open)?;
+        match indent {
+            Some((spaces, level)) if !items.is_empty() => {
+                for item in items {
+                    write!(writer, "\n{:1$}", "", spaces * (level + 1))?;
+                    write_item(writer, item, Some((spaces, level + 1)))?;
+                    write!(writer, "{}", separator.trim_end())?;
+                }
+                write!(writer, "\n{:1$}", "", spaces * level)?;
+            }
+            _ => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(writer, "{}", separator)?;
+                    }
+                    write_item(writer, item, indent)?;
+                }
+            }
+        }
+        write!(writer, "{}", close)
+    }
+
+    fn write_value<W: io::Write>(
+        &self,
+        writer: &mut W,
+        value: &Value,
+        indent: Option<(usize, usize)>,
+    ) -> io::Result<()> {
+        match value {
+            Value::Null => write!(writer, "null"),
+            Value::Bool(b) => write!(writer, "{}", b),
+            Value::Signed(i) => write!(writer, "{}", i),
+            Value::Unsigned(u) => write!(writer, "{}", u),
+            Value::Float(f) if f.is_nan() => write!(writer, "nan"),
+            Value::Float(f) if f.is_infinite() => {
+                write!(writer, "{}", if *f > 0.0 { "inf" } else { "-inf" })
+            }
+            Value::Float(f) => write!(writer, "{:?}", f),
+            Value::Str(s) => Self::write_str(writer, s),
+            Value::Bytes(bytes) => Self::write_bytes(writer, bytes),
+            // Names that aren't identifiers are written as strings, which
+            // enumerations also read.
+            Value::Enum(name) if is_ident(name) => write!(writer, "{}", name),
+            Value::Enum(name) => Self::write_str(writer, name),
+            Value::Ext(type_id, data) => {
+                write!(writer, "ext({}, ", type_id)?;
+                Self::write_bytes(writer, data)?;
+                write!(writer, ")")
+            }
+            Value::Timestamp(timestamp) => write!(writer, "timestamp(\"{}\")", timestamp),
+            Value::Seq(elements) => Self::write_items(
+                writer,
+                elements,
+                ("[", ", ", "]"),
+                indent,
+                |writer, value, indent| self.write_value(writer, value, indent),
+            ),
+            Value::Struct(fields) if fields.is_empty() => write!(writer, "{{}}"),
+            Value::Struct(fields) => match indent {
+                Some(_) => Self::write_items(
+                    writer,
+                    fields,
+                    ("{", "", "}"),
+                    indent,
+                    |writer, field, indent| self.write_field(writer, field, indent),
+                ),
+                None => Self::write_items(
+                    writer,
+                    fields,
+                    ("{ ", " ", " }"),
+                    indent,
+                    |writer, field, indent| self.write_field(writer, field, indent),
+                ),
+            },
+        }
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ser::Serializer for Serializer {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Null;
+        Ok(())
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Bool(value);
+        Ok(())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Signed(value);
+        Ok(())
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Unsigned(value);
+        Ok(())
+    }
+
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Float(value);
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Str(value.to_owned());
+        Ok(())
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Bytes(value.to_vec());
+        Ok(())
+    }
+
+    fn serialize_enum(
+        &mut self,
+        _value: u32,
+        name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Enum(name);
+        Ok(())
+    }
+
+    fn serialize_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Ext(type_id, data.to_vec());
+        Ok(())
+    }
+
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), ser::SerializeError> {
+        if value.to_rfc3339().is_none() {
+            return Err(ser::SerializeError);
+        }
+        self.current_value = Value::Timestamp(value);
+        Ok(())
+    }
+
+    fn start_struct(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Struct(Vec::with_capacity(len)));
+        Ok(())
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        if let Some(Value::Struct(fields)) = self.stack.last_mut() {
+            fields.push((field_id, field_name, value));
+        }
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = self.stack.pop().unwrap();
+        Ok(())
+    }
+
+    fn start_seq(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Seq(Vec::with_capacity(len)));
+        Ok(())
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        if let Some(Value::Seq(elements)) = self.stack.last_mut() {
+            elements.push(value);
+        }
+        Ok(())
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = self.stack.pop().unwrap();
+        Ok(())
+    }
+}
+
+pub struct Deserializer {
+    value: Node,
+}
+
+impl Deserializer {
+    pub fn new(s: &str) -> Result<Self, ParseError> {
+        Self::with_limits(s, DeserializeLimits::default())
+    }
+
+    pub fn with_limits(s: &str, limits: DeserializeLimits) -> Result<Self, ParseError> {
+        Ok(Self {
+            value: Parser::new(s, limits).parse()?,
+        })
+    }
+
+    fn visit_value(
+        value: &Node,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match &value.kind {
+            NodeKind::Null => visitor.visit_null(),
+            _ if visitor.hint() == Hint::Option => Self::visit_value(value, visitor.visit_some()?),
+            NodeKind::Bool(b) => visitor.visit_bool(*b),
+            NodeKind::Signed(i) => visitor.visit_signed(*i),
+            NodeKind::Unsigned(u) => visitor.visit_unsigned(*u),
+            NodeKind::Float(f) => visitor.visit_float(*f),
+            NodeKind::Str(s) => visitor.visit_str(s),
+            NodeKind::Bytes(bytes) => match visitor.visit_bytes(bytes) {
+                Err(de::DeserializeError::UnimplementedVisit) => {
+                    let mut builder = visitor.visit_seq(Some(bytes.len()))?;
+                    for byte in bytes {
+                        builder.element()?.visit_unsigned(*byte as u64)?;
+                    }
+                    builder.finish()
+                }
+                result => result,
+            },
+            NodeKind::Ident(name) => match visitor.visit_enum(None, Some(name)) {
+                Err(de::DeserializeError::UnimplementedVisit) => visitor.visit_str(name),
+                result => result,
+            },
+            NodeKind::Ext(type_id, data) => visitor.visit_ext(*type_id, data),
+            NodeKind::Timestamp(timestamp) => match visitor.visit_timestamp(*timestamp) {
+                Err(de::DeserializeError::UnimplementedVisit) => {
+                    visitor.visit_str(&timestamp.to_string())
+                }
+                result => result,
+            },
+            NodeKind::Seq(elements) => {
+                let mut builder = visitor.visit_seq(Some(elements.len()))?;
+                for element in elements {
+                    Self::visit_value(element, builder.element()?)?;
+                }
+                builder.finish()
+            }
+            NodeKind::Struct(fields) => {
+                let mut builder = visitor.visit_struct()?;
+                for (key, value) in fields {
+                    let (id, name) = de::split_key(key);
+                    match builder.member(id, name) {
+                        Ok(visitor) => Self::visit_value(value, visitor)?,
+                        Err(de::DeserializeError::UnknownField) => {}
+                        Err(e) => Err(e.at(value.position))?,
+                    }
+                }
+                builder.finish()
+            }
+        }
+        .map_err(|e| e.at(value.position))
+    }
+}
+
+impl de::Deserializer for Deserializer {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        Self::visit_value(&self.value, visitor)
+    }
+}
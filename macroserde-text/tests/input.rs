@@ -0,0 +1,198 @@
+// Handwritten text: keys, comments, bytes and malformed input.
+
+use fixtures::{contacts, person, AddressBook, Contact, Date, Month, Person};
+use macroserde::bytes::Bytes;
+use macroserde::de::*;
+use macroserde::ext::{Ext, Timestamp};
+use macroserde::macroserde;
+use macroserde_text::{KeyRepr, ParseErrorKind};
+
+fn decode<T: Deserialize>(s: &str) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_text::Deserializer::new(s).unwrap())
+}
+
+fn parse_error(s: &str) -> ParseErrorKind {
+    macroserde_text::Deserializer::new(s).err().unwrap().kind
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Values {
+        data: Bytes = 1,
+        ext: Option<Ext> = 2,
+        at: Option<Timestamp> = 3,
+        numbers: Vec<i32> = 4,
+    }
+}
+
+#[test]
+fn readme_example() {
+    let contact = decode::<Contact>(r#"1:name: "Steven" 3:birth_date { 1:day: 19 }"#).unwrap();
+    assert_eq!(
+        contact,
+        Contact {
+            name: "Steven".to_owned(),
+            birth_date: Some(Date {
+                day: 19,
+                ..Date::default()
+            }),
+            car_brand: None,
+        }
+    );
+
+    let contact = decode::<Contact>(
+        r#"1:name: "Steven" 3:birth_date { 1:day: 19 2:month: OCT 3:year: 1993 } carBrand: """#,
+    );
+    assert_eq!(contact.unwrap(), contacts()[0]);
+}
+
+#[test]
+fn keys() {
+    // IDs, names, both, and quoted names.
+    let expected = Date {
+        day: 19,
+        month: Month::October,
+        year: 1993,
+    };
+    assert_eq!(decode::<Date>("1: 19 2: OCT 3: 1993").unwrap(), expected);
+    assert_eq!(
+        decode::<Date>("day: 19 month: OCT year: 1993").unwrap(),
+        expected
+    );
+    assert_eq!(
+        decode::<Date>("1:day: 19 2:month: OCT 3:year: 1993").unwrap(),
+        expected
+    );
+    assert_eq!(
+        decode::<Date>(r#""day": 19, "month": OCT, "year": 1993"#).unwrap(),
+        expected
+    );
+
+    // `1: OCT` is the ID 1 holding `OCT`, not a key.
+    assert!(decode::<Date>("1: OCT").is_err());
+
+    // Unknown fields are skipped.
+    assert_eq!(
+        decode::<Date>("9: [1, { a: 2 }] day: 19 month: OCT year: 1993").unwrap(),
+        expected
+    );
+
+    let mut ser = macroserde_text::Serializer::new().with_key_repr(KeyRepr::IdAndName);
+    macroserde::ser::Serialize::serialize(&expected, &mut ser).unwrap();
+    let mut out = Vec::new();
+    ser.write(&mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "1:day: 19 2:month: OCT 3:year: 1993"
+    );
+}
+
+#[test]
+fn comments_and_nesting() {
+    let text = r#"
+        # Steven's contacts.
+        contacts: [
+            {
+                name: "Steven"  # Not Stephen.
+                birth_date { day: 19 month: OCT year: 1993 }
+                carBrand: ""
+            },
+            { name: "" birth_date: null },
+        ]
+    "#;
+    assert_eq!(decode::<AddressBook>(text).unwrap().contacts, contacts());
+
+    let text = r#"
+        name: "Steven" age: 27 birth_date { day: 19 month: OCT year: 1993 }
+        pets: ["Bouboul", "Monsieur Puppy"] height: 1.735 IsCool: true
+        occupation { hasJob: "Engineer" }
+    "#;
+    assert_eq!(decode::<Person>(text).unwrap(), person());
+}
+
+#[test]
+fn bytes_and_functions() {
+    let text = r#"data: b"\x00\xffa\n" ext: ext(-3, b"\x09") at: timestamp("1993-10-19T00:00:00.5Z") numbers: []"#;
+    assert_eq!(
+        decode::<Values>(text).unwrap(),
+        Values {
+            data: Bytes(vec![0, 255, b'a', b'\n']),
+            ext: Some(Ext {
+                type_id: -3,
+                data: vec![9],
+            }),
+            at: Some(Timestamp {
+                seconds: 750_988_800,
+                nanoseconds: 500_000_000,
+            }),
+            numbers: Vec::new(),
+        }
+    );
+
+    assert_eq!(
+        parse_error(r#"at: date("1993-10-19")"#),
+        ParseErrorKind::UnknownFunction("date".to_owned())
+    );
+    assert_eq!(
+        parse_error(r#"at: timestamp("1993-13-19T00:00:00Z")"#),
+        ParseErrorKind::InvalidTimestamp
+    );
+    assert_eq!(parse_error(r#"data: b"\q""#), ParseErrorKind::InvalidEscape);
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(
+        parse_error(r#"name: "Steven"#),
+        ParseErrorKind::UnexpectedEof
+    );
+    assert_eq!(
+        parse_error("birth_date { day: 19"),
+        ParseErrorKind::UnexpectedEof
+    );
+    assert_eq!(parse_error("numbers: [1, 2"), ParseErrorKind::UnexpectedEof);
+    assert_eq!(
+        parse_error("day: 19 }"),
+        ParseErrorKind::UnexpectedChar('}')
+    );
+    assert_eq!(parse_error("day: 1.2.3"), ParseErrorKind::InvalidNumber);
+
+    let error = macroserde_text::Deserializer::new("day: 19\nmonth: ?")
+        .err()
+        .unwrap();
+    assert_eq!(error.kind, ParseErrorKind::UnexpectedChar('?'));
+    assert_eq!(error.snippet, "month: ?");
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    let error = macroserde_text::Deserializer::with_limits(r#"name: "Steven""#, limits).err();
+    assert_eq!(
+        error.unwrap().kind,
+        ParseErrorKind::LimitExceeded(Limit::StrLen)
+    );
+
+    let limits = DeserializeLimits {
+        max_depth: 3,
+        ..DeserializeLimits::default()
+    };
+    let error = macroserde_text::Deserializer::with_limits("a { b { c { d: 1 } } }", limits).err();
+    assert_eq!(
+        error.unwrap().kind,
+        ParseErrorKind::LimitExceeded(Limit::Depth)
+    );
+
+    let limits = DeserializeLimits {
+        max_collection_len: 2,
+        ..DeserializeLimits::default()
+    };
+    let error = macroserde_text::Deserializer::with_limits("numbers: [1, 2, 3]", limits).err();
+    assert_eq!(
+        error.unwrap().kind,
+        ParseErrorKind::LimitExceeded(Limit::CollectionLen)
+    );
+}
@@ -0,0 +1,52 @@
+// Round trips of the README types.
+
+use fixtures::{address_book, person, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use macroserde_text::KeyRepr;
+use std::fmt::Debug;
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    for &key_repr in &[KeyRepr::Id, KeyRepr::Name, KeyRepr::IdAndName] {
+        let mut ser = macroserde_text::Serializer::new().with_key_repr(key_repr);
+        value.serialize(&mut ser).unwrap();
+        for &spaces in &[0, 4] {
+            let mut out = Vec::new();
+            if spaces == 0 {
+                ser.write(&mut out).unwrap();
+            } else {
+                ser.write_pretty(&mut out, spaces).unwrap();
+            }
+            let encoded = String::from_utf8(out).unwrap();
+            let decoded =
+                T::deserialize(&mut macroserde_text::Deserializer::new(&encoded).unwrap());
+            assert_eq!(decoded.unwrap(), value, "decoding {}", encoded);
+        }
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    check(address_book());
+}
@@ -0,0 +1,431 @@
+mod parser;
+
+use macroserde::de::DeserializeLimits;
+use macroserde::ext::Timestamp;
+use macroserde::{de, ser};
+use parser::{Node, NodeKind, Parser};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io;
+
+pub use parser::{ParseError, ParseErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRepr {
+    /// `carBrand`, enumerations are written as `"OCT"`.
+    Name,
+    /// `"10:carBrand"`, enumerations are written as `"10:OCT"`.
+    IdAndName,
+    /// `10`, enumerations are written as `10`.
+    Id,
+}
+
+enum Value {
+    // TOML has no null, null struct fields are left out.
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Str(String),
+    Datetime(Timestamp),
+    Array(Vec<Value>),
+    Table(Vec<(Cow<'static, str>, Value)>),
+}
+
+impl Value {
+    fn is_table(&self) -> bool {
+        matches!(self, Value::Table(_))
+    }
+
+    // Non-empty tables are written as `[name]` sections.
+    fn is_section(&self) -> bool {
+        matches!(self, Value::Table(entries) if !entries.is_empty())
+    }
+
+    // Non-empty arrays of tables are written as `[[name]]` sections.
+    fn is_table_array(&self) -> bool {
+        match self {
+            Value::Array(elements) => !elements.is_empty() && elements.iter().all(Value::is_table),
+            _ => false,
+        }
+    }
+}
+
+pub struct Serializer {
+    current_value: Value,
+    stack: Vec<Value>,
+    // Number of fields of each open struct.
+    struct_lens: Vec<usize>,
+    key_repr: KeyRepr,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self {
+            current_value: Value::Null,
+            stack: Vec::new(),
+            struct_lens: Vec::new(),
+            key_repr: KeyRepr::Name,
+        }
+    }
+
+    pub fn with_key_repr(mut self, key_repr: KeyRepr) -> Self {
+        self.key_repr = key_repr;
+        self
+    }
+
+    // Fails if the serialized value isn't a struct, a TOML document is a table.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match &self.current_value {
+            Value::Table(entries) => Self::write_table(writer, &mut Vec::new(), entries),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only structs can be written as TOML documents",
+            )),
+        }
+    }
+
+    fn write_table<'a, W: io::Write>(
+        writer: &mut W,
+        path: &mut Vec<&'a str>,
+        entries: &'a [(Cow<'static, str>, Value)],
+    ) -> io::Result<()> {
+        // Key/value pairs come first, then the sections, which would
+        // otherwise capture the pairs that follow them.
+        for (key, value) in entries {
+            if !value.is_section() && !value.is_table_array() {
+                Self::write_key(writer, key)?;
+                write!(writer, " = ")?;
+                Self::write_inline_value(writer, value)?;
+                writeln!(writer)?;
+            }
+        }
+        for (key, value) in entries {
+            if let (true, Value::Table(entries)) = (value.is_section(), value) {
+                path.push(key);
+                writeln!(writer)?;
+                write!(writer, "[")?;
+                Self::write_path(writer, path)?;
+                writeln!(writer, "]")?;
+                Self::write_table(writer, path, entries)?;
+                path.pop();
+            }
+        }
+        for (key, value) in entries {
+            if let (true, Value::Array(elements)) = (value.is_table_array(), value) {
+                path.push(key);
+                for element in elements {
+                    if let Value::Table(entries) = element {
+                        writeln!(writer)?;
+                        write!(writer, "[[")?;
+                        Self::write_path(writer, path)?;
+                        writeln!(writer, "]]")?;
+                        Self::write_table(writer, path, entries)?;
+                    }
+                }
+                path.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn write_path<W: io::Write>(writer: &mut W, path: &[&str]) -> io::Result<()> {
+        for (i, key) in path.iter().enumerate() {
+            if i > 0 {
+                write!(writer, ".")?;
+            }
+            Self::write_key(writer, key)?;
+        }
+        Ok(())
+    }
+
+    fn write_key<W: io::Write>(writer: &mut W, key: &str) -> io::Result<()> {
+        let is_bare = !key.is_empty()
+            && key
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-');
+        if is_bare {
+            write!(writer, "{}", key)
+        } else {
+            Self::write_str(writer, key)
+        }
+    }
+
+    fn write_str<W: io::Write>(writer: &mut W, s: &str) -> io::Result<()> {
+        write!(writer, "\"")?;
+        for c in s.chars() {
+            match c {
+                '"' => write!(writer, "\\\"")?,
+                '\\' => write!(writer, "\\\\")?,
+                '\n' => write!(writer, "\\n")?,
+                '\r' => write!(writer, "\\r")?,
+                '\t' => write!(writer, "\\t")?,
+                '\u{8}' => write!(writer, "\\b")?,
+                '\u{c}' => write!(writer, "\\f")?,
+                '\u{0}'..='\u{1f}' | '\u{7f}' => write!(writer, "\\u{:04X}", c as u32)?,
+                _ => write!(writer, "{}", c)?,
+            }
+        }
+        write!(writer, "\"")
+    }
+
+    fn write_inline_value<W: io::Write>(writer: &mut W, value: &Value) -> io::Result<()> {
+        match value {
+            // Only null struct fields make it here and they are skipped.
+            Value::Null => Ok(()),
+            Value::Bool(b) => write!(writer, "{}", b),
+            Value::Integer(i) => write!(writer, "{}", i),
+            Value::Float(f) if f.is_nan() => write!(writer, "nan"),
+            Value::Float(f) if f.is_infinite() && *f > 0.0 => write!(writer, "inf"),
+            Value::Float(f) if f.is_infinite() => write!(writer, "-inf"),
+            Value::Float(f) => write!(writer, "{:?}", f),
+            Value::Str(s) => Self::write_str(writer, s),
+            Value::Datetime(timestamp) => write!(writer, "{}", timestamp),
+            Value::Array(elements) => {
+                write!(writer, "[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        write!(writer, ", ")?;
+                    }
+                    Self::write_inline_value(writer, element)?;
+                }
+                write!(writer, "]")
+            }
+            Value::Table(entries) => {
+                write!(writer, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    write!(writer, "{}", if i > 0 { ", " } else { " " })?;
+                    Self::write_key(writer, key)?;
+                    write!(writer, " = ")?;
+                    Self::write_inline_value(writer, value)?;
+                }
+                write!(writer, "{}}}", if entries.is_empty() { "" } else { " " })
+            }
+        }
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ser::Serializer for Serializer {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Null;
+        Ok(())
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Bool(value);
+        Ok(())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Integer(value);
+        Ok(())
+    }
+
+    // TOML integers are 64 bits signed.
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Integer(i64::try_from(value).map_err(|_| ser::SerializeError)?);
+        Ok(())
+    }
+
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Float(value);
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Str(value.to_owned());
+        Ok(())
+    }
+
+    fn serialize_enum(
+        &mut self,
+        value: u32,
+        name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.current_value = match self.key_repr {
+            KeyRepr::Name => Value::Str(name.into_owned()),
+            KeyRepr::IdAndName => Value::Str(format!("{}:{}", value, name)),
+            KeyRepr::Id => Value::Integer(value as i64),
+        };
+        Ok(())
+    }
+
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), ser::SerializeError> {
+        if value.to_rfc3339().is_none() {
+            return Err(ser::SerializeError);
+        }
+        self.current_value = Value::Datetime(value);
+        Ok(())
+    }
+
+    fn start_struct(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Table(Vec::new()));
+        self.struct_lens.push(len);
+        Ok(())
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = match std::mem::replace(&mut self.current_value, Value::Null) {
+            // Null fields are left out and read back as their default, except
+            // for the only field of a struct (e.g. a unit union variant) which
+            // would be lost.
+            Value::Null if self.struct_lens.last() == Some(&1) => Value::Table(Vec::new()),
+            Value::Null => return Ok(()),
+            value => value,
+        };
+        let key = match self.key_repr {
+            KeyRepr::Name => field_name,
+            KeyRepr::IdAndName => format!("{}:{}", field_id, field_name).into(),
+            KeyRepr::Id => field_id.to_string().into(),
+        };
+        if let Some(Value::Table(entries)) = self.stack.last_mut() {
+            entries.push((key, value));
+        }
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = self.stack.pop().unwrap();
+        self.struct_lens.pop();
+        Ok(())
+    }
+
+    fn start_seq(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Array(Vec::new()));
+        Ok(())
+    }
+
+    // TOML arrays can't hold nulls.
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = match std::mem::replace(&mut self.current_value, Value::Null) {
+            Value::Null => return Err(ser::SerializeError),
+            value => value,
+        };
+        if let Some(Value::Array(array)) = self.stack.last_mut() {
+            array.push(value);
+        }
+        Ok(())
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = self.stack.pop().unwrap();
+        Ok(())
+    }
+}
+
+pub struct Deserializer {
+    value: Node,
+}
+
+impl Deserializer {
+    pub fn new(s: &str) -> Result<Self, ParseError> {
+        Self::with_limits(s, DeserializeLimits::default())
+    }
+
+    pub fn with_limits(s: &str, limits: DeserializeLimits) -> Result<Self, ParseError> {
+        Ok(Self {
+            value: Parser::new(s, limits).parse()?,
+        })
+    }
+
+    fn visit_value(
+        &self,
+        value: &Node,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match &value.kind {
+            // Optional values only visit containers through the visitor of
+            // their content. Empty tables stand for null.
+            NodeKind::Array(_) | NodeKind::TableArray(_) | NodeKind::Table(_)
+                if matches!(visitor.hint(), de::Hint::Option) && !is_empty_table(value) =>
+            {
+                self.visit_value(value, visitor.visit_some()?)
+            }
+            NodeKind::Bool(val) => visitor.visit_bool(*val),
+            NodeKind::Integer(i) if *i >= 0 => visitor.visit_unsigned(*i as u64),
+            NodeKind::Integer(i) => visitor.visit_signed(*i),
+            NodeKind::Float(f) => visitor.visit_float(*f),
+            NodeKind::Str(s) => visitor.visit_str(s),
+            NodeKind::Datetime(timestamp, s) => match visitor.visit_timestamp(*timestamp) {
+                Err(de::DeserializeError::UnimplementedVisit) => visitor.visit_str(s),
+                result => result,
+            },
+            NodeKind::Array(elements) | NodeKind::TableArray(elements) => {
+                self.visit_array(elements, visitor)
+            }
+            NodeKind::Table(table) => self.visit_table(&table.entries, visitor),
+        }
+        .map_err(|e| e.at(value.position))
+    }
+
+    fn visit_array(
+        &self,
+        elements: &[Node],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_seq(Some(elements.len()))?;
+        for element in elements {
+            let visitor = builder.element()?;
+            self.visit_value(element, visitor)?;
+        }
+        builder.finish()
+    }
+
+    fn visit_table(
+        &self,
+        entries: &[(String, Node)],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        // The serializer writes null as an empty table where it can't be left
+        // out.
+        if entries.is_empty() {
+            let result = visitor
+                .visit_struct()
+                .and_then(|mut builder| builder.finish());
+            return match result {
+                Err(de::DeserializeError::UnimplementedVisit) => visitor.visit_null(),
+                result => result,
+            };
+        }
+
+        let mut builder = visitor.visit_struct()?;
+        let mut null_visitor = de::NullVisitor;
+        for entry in entries {
+            let (id, name) = de::split_key(&entry.0);
+            match builder.member(id, name) {
+                Ok(visitor) => self.visit_value(&entry.1, visitor)?,
+                Err(de::DeserializeError::UnknownField) => {
+                    self.visit_value(&entry.1, &mut null_visitor)?
+                }
+                Err(e) => Err(e.at(entry.1.position))?,
+            }
+        }
+        builder.finish()
+    }
+}
+
+fn is_empty_table(node: &Node) -> bool {
+    matches!(&node.kind, NodeKind::Table(table) if table.entries.is_empty())
+}
+
+impl de::Deserializer for Deserializer {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        self.visit_value(&self.value, visitor)
+    }
+}
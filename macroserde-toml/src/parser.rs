@@ -0,0 +1,854 @@
+use macroserde::de::{DeserializeLimits, Limit, LimitTracker, Position};
+use macroserde::ext::Timestamp;
+use std::fmt;
+
+pub enum NodeKind {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Str(String),
+    // Offset date-times along with their source text. Local dates and times
+    // aren't instants and are read as strings.
+    Datetime(Timestamp, String),
+    Array(Vec<Node>),
+    Table(Table),
+    // `[[name]]` sections.
+    TableArray(Vec<Node>),
+}
+
+pub struct Node {
+    pub kind: NodeKind,
+    pub position: Position,
+}
+
+impl Node {
+    fn table(kind: TableKind, position: Position) -> Self {
+        Node {
+            kind: NodeKind::Table(Table {
+                entries: Vec::new(),
+                kind,
+            }),
+            position,
+        }
+    }
+}
+
+// How a table was defined, which decides whether it can be extended later.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TableKind {
+    // Parent of a `[a.b]` header, or the root.
+    Implicit,
+    Header,
+    // Created by a dotted key such as `a.b = 1`.
+    Dotted,
+    Inline,
+}
+
+pub struct Table {
+    pub entries: Vec<(String, Node)>,
+    kind: TableKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    InvalidNumber,
+    InvalidDatetime,
+    InvalidEscape,
+    InvalidUnicodeEscape,
+    ControlCharInString,
+    DuplicateKey(String),
+    LimitExceeded(Limit),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+    // The source line containing the error.
+    pub snippet: String,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number"),
+            ParseErrorKind::InvalidDatetime => write!(f, "invalid date or time"),
+            ParseErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            ParseErrorKind::InvalidUnicodeEscape => write!(f, "invalid unicode escape"),
+            ParseErrorKind::ControlCharInString => write!(f, "control character in string"),
+            ParseErrorKind::DuplicateKey(key) => write!(f, "duplicate key {:?}", key),
+            ParseErrorKind::LimitExceeded(Limit::Depth) => write!(f, "too deeply nested"),
+            ParseErrorKind::LimitExceeded(Limit::StrLen) => write!(f, "string too long"),
+            ParseErrorKind::LimitExceeded(Limit::CollectionLen) => {
+                write!(f, "too many elements")
+            }
+            ParseErrorKind::LimitExceeded(Limit::TotalAlloc) => write!(f, "input too large"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.position.line, self.position.column
+        )?;
+        writeln!(f, "{}", self.snippet)?;
+        let caret_offset: String = self
+            .snippet
+            .chars()
+            .take(self.position.column - 1)
+            .map(|c| if c == '\t' { c } else { ' ' })
+            .collect();
+        write!(f, "{}^", caret_offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    index: usize,
+    line: usize,
+    column: usize,
+    line_start: usize,
+    limits: LimitTracker,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(src: &'a str, limits: DeserializeLimits) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            index: 0,
+            line: 1,
+            column: 1,
+            line_start: 0,
+            limits: LimitTracker::new(limits),
+        }
+    }
+
+    pub fn parse(mut self) -> Result<Node, ParseError> {
+        let position = self.position();
+        let mut root = Node::table(TableKind::Implicit, position);
+        let mut current = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            match self.peek() {
+                None => break,
+                Some(b'[') => current = self.parse_header(&mut root)?,
+                Some(_) => {
+                    let table = Self::table_at(&mut root, &current);
+                    self.parse_key_value(table)?;
+                }
+            }
+            self.expect_line_end()?;
+        }
+        Ok(root)
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        let line_end = self.src[self.line_start..]
+            .find('\n')
+            .map(|i| self.line_start + i)
+            .unwrap_or(self.src.len());
+        ParseError {
+            kind,
+            position: self.position(),
+            snippet: self.src[self.line_start..line_end]
+                .trim_end_matches('\r')
+                .to_owned(),
+        }
+    }
+
+    fn error_at(&self, kind: ParseErrorKind, position: Position) -> ParseError {
+        let mut error = self.error(kind);
+        error.position = position;
+        error
+    }
+
+    fn check_limit(&self, result: Result<(), Limit>) -> Result<(), ParseError> {
+        result.map_err(|limit| self.error(ParseErrorKind::LimitExceeded(limit)))
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.src[self.index..].chars().next() {
+            Some(c) => self.error(ParseErrorKind::UnexpectedChar(c)),
+            None => self.error(ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.index).copied()
+    }
+
+    #[inline]
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.index + offset).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.src[self.index..].starts_with(s)
+    }
+
+    #[inline]
+    fn bump(&mut self) {
+        let byte = self.bytes[self.index];
+        self.index += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+            self.line_start = self.index;
+        } else if byte & 0xc0 != 0x80 {
+            self.column += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        if self.peek() == Some(byte) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        for &byte in keyword.as_bytes() {
+            self.expect(byte)?;
+        }
+        Ok(())
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(b' ') | Some(b'\t') = self.peek() {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        while self.peek().is_some_and(|byte| byte != b'\n') {
+            self.bump();
+        }
+    }
+
+    fn skip_newline(&mut self) {
+        if self.peek() == Some(b'\n') {
+            self.bump();
+        } else if self.peek() == Some(b'\r') && self.peek_at(1) == Some(b'\n') {
+            self.bump();
+            self.bump();
+        }
+    }
+
+    fn skip_blank_lines(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => self.bump(),
+                Some(b'#') => self.skip_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    fn expect_line_end(&mut self) -> Result<(), ParseError> {
+        self.skip_spaces();
+        if self.peek() == Some(b'#') {
+            self.skip_comment();
+        }
+        match self.peek() {
+            None | Some(b'\n') => Ok(()),
+            Some(b'\r') if self.peek_at(1) == Some(b'\n') => Ok(()),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    // Tables of an array of tables are extended through the last one.
+    fn last_table(node: &mut Node) -> &mut Table {
+        match &mut node.kind {
+            NodeKind::Table(table) => table,
+            NodeKind::TableArray(elements) => Self::last_table(elements.last_mut().unwrap()),
+            _ => unreachable!(),
+        }
+    }
+
+    // The table a `[header]` opened, it exists since the header created it.
+    fn table_at<'t>(root: &'t mut Node, path: &[String]) -> &'t mut Table {
+        let mut table = Self::last_table(root);
+        for key in path {
+            let node = &mut table.entries.iter_mut().find(|(k, _)| k == key).unwrap().1;
+            table = Self::last_table(node);
+        }
+        table
+    }
+
+    // Returns the table named `key` in `table`, creating it if needed.
+    fn descend<'t>(
+        &self,
+        table: &'t mut Table,
+        key: &str,
+        position: Position,
+        dotted: bool,
+    ) -> Result<&'t mut Table, ParseError> {
+        let index = match table.entries.iter().position(|(k, _)| k == key) {
+            Some(index) => index,
+            None => {
+                let kind = if dotted {
+                    TableKind::Dotted
+                } else {
+                    TableKind::Implicit
+                };
+                table
+                    .entries
+                    .push((key.to_owned(), Node::table(kind, position)));
+                table.entries.len() - 1
+            }
+        };
+        match &mut table.entries[index].1.kind {
+            NodeKind::Table(table)
+                if table.kind != TableKind::Inline
+                    && !(dotted && table.kind == TableKind::Header) =>
+            {
+                Ok(table)
+            }
+            NodeKind::TableArray(elements) if !dotted => {
+                Ok(Self::last_table(elements.last_mut().unwrap()))
+            }
+            _ => Err(self.error_at(ParseErrorKind::DuplicateKey(key.to_owned()), position)),
+        }
+    }
+
+    fn parse_header(&mut self, root: &mut Node) -> Result<Vec<String>, ParseError> {
+        let position = self.position();
+        self.bump();
+        let is_array = self.peek() == Some(b'[');
+        if is_array {
+            self.bump();
+        }
+        self.skip_spaces();
+        let keys = self.parse_dotted_key()?;
+        self.skip_spaces();
+        self.expect(b']')?;
+        if is_array {
+            self.expect(b']')?;
+        }
+
+        let mut table = Self::table_at(root, &[]);
+        let (last, parents) = keys.split_last().unwrap();
+        for key in parents {
+            table = self.descend(table, key, position, false)?;
+        }
+        match table.entries.iter().position(|(k, _)| k == last) {
+            None => {
+                let node = Node::table(TableKind::Header, position);
+                let node = if is_array {
+                    Node {
+                        kind: NodeKind::TableArray(vec![node]),
+                        position,
+                    }
+                } else {
+                    node
+                };
+                table.entries.push((last.clone(), node));
+            }
+            Some(index) => match &mut table.entries[index].1.kind {
+                NodeKind::Table(table) if !is_array && table.kind == TableKind::Implicit => {
+                    table.kind = TableKind::Header;
+                }
+                NodeKind::TableArray(elements) if is_array => {
+                    elements.push(Node::table(TableKind::Header, position));
+                }
+                _ => {
+                    return Err(self.error_at(ParseErrorKind::DuplicateKey(last.clone()), position))
+                }
+            },
+        }
+        Ok(keys)
+    }
+
+    fn parse_key_value(&mut self, table: &mut Table) -> Result<(), ParseError> {
+        let position = self.position();
+        let keys = self.parse_dotted_key()?;
+        self.skip_spaces();
+        self.expect(b'=')?;
+        self.skip_spaces();
+        let value = self.parse_value()?;
+
+        let mut table = table;
+        let (last, parents) = keys.split_last().unwrap();
+        for key in parents {
+            table = self.descend(table, key, position, true)?;
+        }
+        if table.entries.iter().any(|(k, _)| k == last) {
+            return Err(self.error_at(ParseErrorKind::DuplicateKey(last.clone()), position));
+        }
+        table.entries.push((last.clone(), value));
+        Ok(())
+    }
+
+    fn parse_dotted_key(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut keys = vec![self.parse_key()?];
+        loop {
+            self.skip_spaces();
+            if self.peek() != Some(b'.') {
+                return Ok(keys);
+            }
+            self.bump();
+            self.skip_spaces();
+            keys.push(self.parse_key()?);
+        }
+    }
+
+    fn is_bare_key_byte(byte: u8) -> bool {
+        byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-'
+    }
+
+    fn parse_key(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(b'"') => self.parse_basic_str(),
+            Some(b'\'') => self.parse_literal_str(),
+            Some(byte) if Self::is_bare_key_byte(byte) => {
+                let start = self.index;
+                while self.peek().is_some_and(Self::is_bare_key_byte) {
+                    self.bump();
+                }
+                Ok(self.src[start..self.index].to_owned())
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Node, ParseError> {
+        let position = self.position();
+        let kind = match self.peek() {
+            Some(b'"') | Some(b'\'') => {
+                let s = if self.starts_with("\"\"\"") {
+                    self.parse_multiline_basic_str()?
+                } else if self.starts_with("'''") {
+                    self.parse_multiline_literal_str()?
+                } else if self.peek() == Some(b'"') {
+                    self.parse_basic_str()?
+                } else {
+                    self.parse_literal_str()?
+                };
+                let limit = self.limits.check_str_len(s.len());
+                self.check_limit(limit)?;
+                NodeKind::Str(s)
+            }
+            Some(b't') => {
+                self.expect_keyword("true")?;
+                NodeKind::Bool(true)
+            }
+            Some(b'f') => {
+                self.expect_keyword("false")?;
+                NodeKind::Bool(false)
+            }
+            Some(b'[') => {
+                let limit = self.limits.enter();
+                self.check_limit(limit)?;
+                let elements = self.parse_array()?;
+                let limit = self.limits.check_collection_len(elements.len());
+                self.check_limit(limit)?;
+                self.limits.leave();
+                NodeKind::Array(elements)
+            }
+            Some(b'{') => {
+                let limit = self.limits.enter();
+                self.check_limit(limit)?;
+                let table = self.parse_inline_table()?;
+                let limit = self.limits.check_collection_len(table.entries.len());
+                self.check_limit(limit)?;
+                self.limits.leave();
+                NodeKind::Table(table)
+            }
+            Some(b'0'..=b'9') | Some(b'+') | Some(b'-') | Some(b'i') | Some(b'n') => {
+                self.parse_number_or_datetime()?
+            }
+            _ => return Err(self.unexpected()),
+        };
+        Ok(Node { kind, position })
+    }
+
+    fn parse_array(&mut self) -> Result<Vec<Node>, ParseError> {
+        self.expect(b'[')?;
+        let mut elements = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            if self.peek() == Some(b']') {
+                self.bump();
+                return Ok(elements);
+            }
+            elements.push(self.parse_value()?);
+            self.skip_blank_lines();
+            match self.peek() {
+                Some(b',') => self.bump(),
+                Some(b']') => {
+                    self.bump();
+                    return Ok(elements);
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    // Inline tables fit on a single line and have no trailing comma.
+    fn parse_inline_table(&mut self) -> Result<Table, ParseError> {
+        self.expect(b'{')?;
+        let mut table = Table {
+            entries: Vec::new(),
+            kind: TableKind::Inline,
+        };
+        self.skip_spaces();
+        if self.peek() == Some(b'}') {
+            self.bump();
+            return Ok(table);
+        }
+        loop {
+            self.skip_spaces();
+            self.parse_key_value(&mut table)?;
+            self.skip_spaces();
+            match self.peek() {
+                Some(b',') => self.bump(),
+                Some(b'}') => {
+                    self.bump();
+                    return Ok(table);
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_basic_str(&mut self) -> Result<String, ParseError> {
+        self.bump();
+        let mut result = String::new();
+        let mut run_start = self.index;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                Some(b'"') => {
+                    result.push_str(&self.src[run_start..self.index]);
+                    self.bump();
+                    return Ok(result);
+                }
+                Some(b'\\') => {
+                    result.push_str(&self.src[run_start..self.index]);
+                    self.bump();
+                    result.push(self.parse_escape()?);
+                    run_start = self.index;
+                }
+                Some(b'\t') => self.bump(),
+                Some(0x00..=0x1f) | Some(0x7f) => {
+                    return Err(self.error(ParseErrorKind::ControlCharInString))
+                }
+                Some(_) => self.bump(),
+            }
+        }
+    }
+
+    fn parse_multiline_basic_str(&mut self) -> Result<String, ParseError> {
+        self.expect_keyword("\"\"\"")?;
+        // A newline right after the delimiter isn't part of the string.
+        self.skip_newline();
+        let mut result = String::new();
+        let mut run_start = self.index;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                Some(b'"') if self.starts_with("\"\"\"") => {
+                    result.push_str(&self.src[run_start..self.index]);
+                    // Up to two quotes can end the string right before the
+                    // delimiter.
+                    let mut quotes = 3;
+                    while quotes < 5 && self.peek_at(quotes) == Some(b'"') {
+                        result.push('"');
+                        quotes += 1;
+                    }
+                    for _ in 0..quotes {
+                        self.bump();
+                    }
+                    return Ok(result);
+                }
+                Some(b'\\') => {
+                    result.push_str(&self.src[run_start..self.index]);
+                    self.bump();
+                    if self.is_line_ending_backslash() {
+                        // Trims the newline and the whitespace that follows.
+                        while let Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') = self.peek()
+                        {
+                            self.bump();
+                        }
+                    } else {
+                        result.push(self.parse_escape()?);
+                    }
+                    run_start = self.index;
+                }
+                Some(b'\t') | Some(b'\n') => self.bump(),
+                Some(b'\r') if self.peek_at(1) == Some(b'\n') => self.bump(),
+                Some(0x00..=0x1f) | Some(0x7f) => {
+                    return Err(self.error(ParseErrorKind::ControlCharInString))
+                }
+                Some(_) => self.bump(),
+            }
+        }
+    }
+
+    fn is_line_ending_backslash(&self) -> bool {
+        let mut offset = 0;
+        while let Some(b' ') | Some(b'\t') = self.peek_at(offset) {
+            offset += 1;
+        }
+        match self.peek_at(offset) {
+            Some(b'\n') => true,
+            Some(b'\r') => self.peek_at(offset + 1) == Some(b'\n'),
+            _ => false,
+        }
+    }
+
+    fn parse_literal_str(&mut self) -> Result<String, ParseError> {
+        self.bump();
+        let start = self.index;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                Some(b'\'') => {
+                    let result = self.src[start..self.index].to_owned();
+                    self.bump();
+                    return Ok(result);
+                }
+                Some(b'\t') => self.bump(),
+                Some(0x00..=0x1f) | Some(0x7f) => {
+                    return Err(self.error(ParseErrorKind::ControlCharInString))
+                }
+                Some(_) => self.bump(),
+            }
+        }
+    }
+
+    fn parse_multiline_literal_str(&mut self) -> Result<String, ParseError> {
+        self.expect_keyword("'''")?;
+        self.skip_newline();
+        let start = self.index;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                Some(b'\'') if self.starts_with("'''") => {
+                    let mut quotes = 3;
+                    while quotes < 5 && self.peek_at(quotes) == Some(b'\'') {
+                        quotes += 1;
+                    }
+                    let result = self.src[start..(self.index + quotes - 3)].to_owned();
+                    for _ in 0..quotes {
+                        self.bump();
+                    }
+                    return Ok(result);
+                }
+                Some(b'\t') | Some(b'\n') => self.bump(),
+                Some(b'\r') if self.peek_at(1) == Some(b'\n') => self.bump(),
+                Some(0x00..=0x1f) | Some(0x7f) => {
+                    return Err(self.error(ParseErrorKind::ControlCharInString))
+                }
+                Some(_) => self.bump(),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.bump();
+                return self.parse_unicode_escape(4);
+            }
+            Some(b'U') => {
+                self.bump();
+                return self.parse_unicode_escape(8);
+            }
+            None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+            Some(_) => return Err(self.error(ParseErrorKind::InvalidEscape)),
+        };
+        self.bump();
+        Ok(c)
+    }
+
+    fn parse_unicode_escape(&mut self, len: usize) -> Result<char, ParseError> {
+        let mut value: u32 = 0;
+        for _ in 0..len {
+            let digit = match self.peek() {
+                Some(c @ b'0'..=b'9') => c - b'0',
+                Some(c @ b'a'..=b'f') => c - b'a' + 10,
+                Some(c @ b'A'..=b'F') => c - b'A' + 10,
+                _ => return Err(self.error(ParseErrorKind::InvalidUnicodeEscape)),
+            };
+            value = value * 16 + digit as u32;
+            self.bump();
+        }
+        std::char::from_u32(value).ok_or_else(|| self.error(ParseErrorKind::InvalidUnicodeEscape))
+    }
+
+    fn is_token_byte(byte: u8) -> bool {
+        byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'+' | b'-' | b'.' | b':')
+    }
+
+    fn skip_token(&mut self) {
+        while self.peek().is_some_and(Self::is_token_byte) {
+            self.bump();
+        }
+    }
+
+    fn parse_number_or_datetime(&mut self) -> Result<NodeKind, ParseError> {
+        let src = self.src;
+        let start = self.index;
+        let start_position = self.position();
+        self.skip_token();
+        // A space can separate the date from the time.
+        if is_date(&src[start..self.index])
+            && self.peek() == Some(b' ')
+            && self.peek_at(1).is_some_and(|byte| byte.is_ascii_digit())
+        {
+            self.bump();
+            self.skip_token();
+        }
+
+        let text = &src[start..self.index];
+        if is_date(text) || is_time(text) {
+            parse_datetime(text)
+                .ok_or_else(|| self.error_at(ParseErrorKind::InvalidDatetime, start_position))
+        } else {
+            parse_number(text)
+                .ok_or_else(|| self.error_at(ParseErrorKind::InvalidNumber, start_position))
+        }
+    }
+}
+
+fn all_digits(text: &str) -> bool {
+    text.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+// `1979-05-27`, possibly followed by a time.
+fn is_date(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && all_digits(&text[0..4])
+        && all_digits(&text[5..7])
+        && all_digits(&text[8..10])
+}
+
+// `07:32:00` with optional fractional seconds.
+fn is_time(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes.len() >= 8
+        && bytes[2] == b':'
+        && bytes[5] == b':'
+        && all_digits(&text[0..2])
+        && all_digits(&text[3..5])
+        && all_digits(&text[6..8])
+        && (bytes.len() == 8 || (bytes[8] == b'.' && bytes.len() > 9 && all_digits(&text[9..])))
+}
+
+fn parse_datetime(text: &str) -> Option<NodeKind> {
+    let is_local = if text.len() == 10 {
+        is_date(text)
+    } else if text.len() > 11 && is_date(text) {
+        matches!(text.as_bytes()[10], b'T' | b't' | b' ') && is_time(&text[11..])
+    } else {
+        is_time(text)
+    };
+    if is_local {
+        return Some(NodeKind::Str(text.to_owned()));
+    }
+    let timestamp = Timestamp::parse_rfc3339(text)?;
+    Some(NodeKind::Datetime(timestamp, text.to_owned()))
+}
+
+// Underscores must be between two digits.
+fn strip_underscores(text: &str, is_digit: fn(&u8) -> bool) -> Option<String> {
+    let bytes = text.as_bytes();
+    for (i, byte) in bytes.iter().enumerate() {
+        if *byte == b'_'
+            && (i == 0
+                || i + 1 == bytes.len()
+                || !is_digit(&bytes[i - 1])
+                || !is_digit(&bytes[i + 1]))
+        {
+            return None;
+        }
+    }
+    Some(text.replace('_', ""))
+}
+
+fn parse_number(text: &str) -> Option<NodeKind> {
+    match text {
+        "inf" | "+inf" => return Some(NodeKind::Float(f64::INFINITY)),
+        "-inf" => return Some(NodeKind::Float(f64::NEG_INFINITY)),
+        "nan" | "+nan" | "-nan" => return Some(NodeKind::Float(f64::NAN)),
+        _ => {}
+    }
+
+    let (negative, unsigned) = match text.as_bytes().first() {
+        Some(b'+') => (false, &text[1..]),
+        Some(b'-') => (true, &text[1..]),
+        _ => (false, text),
+    };
+    let radix = match unsigned.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        // Prefixed integers have no sign.
+        if unsigned.len() != text.len() {
+            return None;
+        }
+        let digits = strip_underscores(&unsigned[2..], u8::is_ascii_hexdigit)?;
+        return i64::from_str_radix(&digits, radix)
+            .ok()
+            .map(NodeKind::Integer);
+    }
+
+    let digits = strip_underscores(unsigned, u8::is_ascii_digit)?;
+    let integer_part = digits.split(['.', 'e', 'E']).next()?;
+    if integer_part.is_empty()
+        || !all_digits(integer_part)
+        || (integer_part.len() > 1 && integer_part.starts_with('0'))
+    {
+        return None;
+    }
+    if integer_part.len() == digits.len() {
+        let text = if negative {
+            format!("-{}", digits)
+        } else {
+            digits
+        };
+        return text.parse().ok().map(NodeKind::Integer);
+    }
+
+    // Digits are required on both sides of the decimal point.
+    if digits.ends_with('.') || digits.contains(".e") || digits.contains(".E") {
+        return None;
+    }
+    let value: f64 = digits.parse().ok()?;
+    Some(NodeKind::Float(if negative { -value } else { value }))
+}
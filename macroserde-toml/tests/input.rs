@@ -0,0 +1,133 @@
+// Handwritten TOML: dotted keys, inline tables and arrays of tables.
+
+use fixtures::{address_book, person, AddressBook, Contact, Date, Month, Person};
+use macroserde::de::*;
+
+fn decode<T: Deserialize>(s: &str) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_toml::Deserializer::new(s).unwrap())
+}
+
+fn birthday() -> Date {
+    Date {
+        day: 19,
+        month: Month::October,
+        year: 1993,
+    }
+}
+
+#[test]
+fn readme_person() {
+    let toml = r#"
+# Keys are field names, IDs or both.
+name = "Steven"
+2 = 27
+"4:pets" = ["Bouboul", "Monsieur Puppy"]
+height = 1.735
+IsCool = true
+
+[birth_date]
+day = 19
+month = "OCT"
+year = 1993
+
+[occupation]
+hasJob = "Engineer"
+"#;
+    assert_eq!(decode::<Person>(toml).unwrap(), person());
+}
+
+#[test]
+fn dotted_keys() {
+    let toml = r#"
+name = "Steven"
+age = 27
+pets = ["Bouboul", "Monsieur Puppy"]
+height = 1.735
+IsCool = true
+birth_date.day = 19
+birth_date . month = "OCT"
+"birth_date".year = 1993
+occupation.hasJob = "Engineer"
+"#;
+    assert_eq!(decode::<Person>(toml).unwrap(), person());
+
+    // A table defined by dotted keys can't be reopened by a header.
+    let reopened = "birth_date.day = 19\n[birth_date]\nyear = 1993\n";
+    assert!(macroserde_toml::Deserializer::new(reopened).is_err());
+}
+
+#[test]
+fn inline_tables() {
+    let toml = r#"
+name = "Steven"
+age = 27
+pets = ["Bouboul", "Monsieur Puppy"]
+height = 1.735
+IsCool = true
+birth_date = { day = 19, month = "OCT", year = 1993 }
+occupation = { hasJob = "Engineer" }
+"#;
+    assert_eq!(decode::<Person>(toml).unwrap(), person());
+}
+
+#[test]
+fn arrays_of_tables() {
+    let toml = r#"
+[[contacts]]
+name = "Steven"
+carBrand = ""
+
+[contacts.birth_date]
+day = 19
+month = "OCT"
+year = 1993
+
+# Missing fields are None.
+[[contacts]]
+name = ""
+"#;
+    assert_eq!(decode::<AddressBook>(toml).unwrap(), address_book());
+
+    let inline = r#"
+contacts = [
+    { name = "Steven", birth_date = { day = 19, month = "OCT", year = 1993 }, carBrand = "" },
+    { name = "" },
+]
+"#;
+    assert_eq!(decode::<AddressBook>(inline).unwrap(), address_book());
+
+    // An empty table stands for null.
+    let empty = "[[contacts]]\nname = \"\"\nbirth_date = {}\n";
+    assert_eq!(
+        decode::<AddressBook>(empty).unwrap().contacts,
+        vec![Contact::default()]
+    );
+
+    // A static array can't be extended by a table header.
+    let extended = "contacts = [{ name = \"\" }]\n[[contacts]]\nname = \"\"\n";
+    assert!(macroserde_toml::Deserializer::new(extended).is_err());
+}
+
+#[test]
+fn errors() {
+    let error = decode::<Date>("day = 19\nmonth = \"OCTOBER\"\nyear = 1993\n").unwrap_err();
+    assert_eq!(error.position().map(|p| p.line), Some(2));
+    assert_eq!(
+        decode::<Date>("month = \"OCT\"\nyear = 1993\nday = 19\n").unwrap(),
+        birthday()
+    );
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    let error = macroserde_toml::Deserializer::with_limits("name = \"Steven\"", limits).err();
+    assert!(
+        matches!(error, Some(ref e) if e.kind == macroserde_toml::ParseErrorKind::LimitExceeded(Limit::StrLen)),
+        "{:?}",
+        error
+    );
+}
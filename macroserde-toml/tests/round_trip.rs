@@ -0,0 +1,49 @@
+// Round trips of the README types.
+
+use fixtures::{address_book, person, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use macroserde_toml::KeyRepr;
+use std::fmt::Debug;
+
+fn encode<T: Serialize>(value: &T, key_repr: KeyRepr) -> String {
+    let mut ser = macroserde_toml::Serializer::new().with_key_repr(key_repr);
+    value.serialize(&mut ser).unwrap();
+    let mut out = Vec::new();
+    ser.write(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    for &key_repr in &[KeyRepr::Name, KeyRepr::IdAndName, KeyRepr::Id] {
+        let encoded = encode(&value, key_repr);
+        let decoded = T::deserialize(&mut macroserde_toml::Deserializer::new(&encoded).unwrap());
+        assert_eq!(decoded.unwrap(), value, "decoding {}", encoded);
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    check(address_book());
+}
@@ -0,0 +1,348 @@
+use macroserde::de::{DeserializeLimits, Hint, LimitTracker};
+use macroserde::ext::Timestamp;
+use macroserde::{de, ser};
+use std::borrow::Cow;
+use std::io;
+
+// Writes a struct as `name=Steven&birth_date.day=19&pets=a&pets=b`: nested
+// struct fields have dotted keys, sequences repeat their key and null fields
+// are left out. Bytes are written as a single value, percent-encoded.
+pub struct Serializer {
+    pairs: Vec<(String, Vec<u8>)>,
+    path: Vec<Cow<'static, str>>,
+    // Sequences of scalars only, their elements share the key of the field.
+    in_seq: bool,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self {
+            pairs: Vec::new(),
+            path: Vec::new(),
+            in_seq: false,
+        }
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for (i, (key, value)) in self.pairs.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b"&")?;
+            }
+            write!(writer, "{}={}", encode(key.as_bytes()), encode(value))?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, value: String) -> Result<(), ser::SerializeError> {
+        self.push_bytes(value.into_bytes())
+    }
+
+    fn push_bytes(&mut self, value: Vec<u8>) -> Result<(), ser::SerializeError> {
+        if self.path.is_empty() {
+            return Err(ser::SerializeError);
+        }
+        self.pairs.push((self.path.join("."), value));
+        Ok(())
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ser::Serializer for Serializer {
+    // Elements can't be left out without shifting the ones after them.
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        if self.in_seq {
+            return Err(ser::SerializeError);
+        }
+        Ok(())
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.push(value.to_string())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        self.push(value.to_string())
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.push(value.to_string())
+    }
+
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.push(format!("{:?}", value))
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.push(value.to_owned())
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), ser::SerializeError> {
+        self.push_bytes(value.to_vec())
+    }
+
+    fn serialize_enum(
+        &mut self,
+        _value: u32,
+        name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.push(name.into_owned())
+    }
+
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), ser::SerializeError> {
+        self.push(value.to_rfc3339().ok_or(ser::SerializeError)?)
+    }
+
+    fn start_struct(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        if self.in_seq {
+            return Err(ser::SerializeError);
+        }
+        Ok(())
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        _field_id: u32,
+        field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        self.path.push(field_name);
+        value.serialize(self)?;
+        self.path.pop();
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        Ok(())
+    }
+
+    fn start_seq(&mut self, _len: usize) -> Result<(), ser::SerializeError> {
+        if self.in_seq || self.path.is_empty() {
+            return Err(ser::SerializeError);
+        }
+        self.in_seq = true;
+        Ok(())
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        self.in_seq = false;
+        Ok(())
+    }
+
+    // A variant that writes nothing (e.g. a unit variant) is written with an
+    // empty value, it would be lost otherwise.
+    fn serialize_union(
+        &mut self,
+        variant_id: u32,
+        variant_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        if self.in_seq {
+            return Err(ser::SerializeError);
+        }
+        let len = self.pairs.len();
+        self.serialize_struct_field(variant_id, variant_name.clone(), value)?;
+        if self.pairs.len() == len {
+            self.path.push(variant_name);
+            self.push(String::new())?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+}
+
+// Unreserved characters are kept, spaces become `+`.
+fn encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn decode(s: &str) -> Result<Vec<u8>, de::DeserializeError> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = bytes
+                    .get((i + 1)..(i + 3))
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or(de::DeserializeError::ParsingError)?;
+                decoded.push(hex);
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    Ok(decoded)
+}
+
+fn decode_str(s: &str) -> Result<String, de::DeserializeError> {
+    String::from_utf8(decode(s)?).map_err(|_| de::DeserializeError::ParsingError)
+}
+
+enum Node {
+    // The values of a key, in order. They are only checked to be UTF-8 when
+    // visited as something other than bytes.
+    Values(Vec<Vec<u8>>),
+    Struct(Vec<(String, Node)>),
+}
+
+pub struct Deserializer {
+    root: Node,
+}
+
+impl Deserializer {
+    pub fn new(s: &str) -> Result<Self, de::DeserializeError> {
+        Self::with_limits(s, DeserializeLimits::default())
+    }
+
+    pub fn with_limits(s: &str, limits: DeserializeLimits) -> Result<Self, de::DeserializeError> {
+        let mut limits = LimitTracker::new(limits);
+        let mut root = Vec::new();
+        let pairs: Vec<&str> = s
+            .strip_prefix('?')
+            .unwrap_or(s)
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .collect();
+        limits
+            .check_collection_len(pairs.len())
+            .map_err(de::DeserializeError::LimitExceeded)?;
+        for pair in pairs {
+            let (key, value) = match pair.find('=') {
+                Some(sep) => (&pair[..sep], &pair[(sep + 1)..]),
+                None => (pair, ""),
+            };
+            let (key, value) = (decode_str(key)?, decode(value)?);
+            limits
+                .check_str_len(value.len())
+                .map_err(de::DeserializeError::LimitExceeded)?;
+            let path: Vec<&str> = key.split('.').collect();
+            for _ in &path {
+                limits
+                    .enter()
+                    .map_err(de::DeserializeError::LimitExceeded)?;
+            }
+            for _ in &path {
+                limits.leave();
+            }
+            Self::insert(&mut root, &path, value)?;
+        }
+        Ok(Self {
+            root: Node::Struct(root),
+        })
+    }
+
+    // A key can't both have values and nested keys.
+    fn insert(
+        entries: &mut Vec<(String, Node)>,
+        path: &[&str],
+        value: Vec<u8>,
+    ) -> Result<(), de::DeserializeError> {
+        let index = match entries.iter().position(|(key, _)| key == path[0]) {
+            Some(index) => index,
+            None => {
+                let node = match path.len() {
+                    1 => Node::Values(Vec::new()),
+                    _ => Node::Struct(Vec::new()),
+                };
+                entries.push((path[0].to_owned(), node));
+                entries.len() - 1
+            }
+        };
+        match (&mut entries[index].1, path.len()) {
+            (Node::Values(values), 1) => values.push(value),
+            (Node::Struct(entries), len) if len > 1 => Self::insert(entries, &path[1..], value)?,
+            _ => return Err(de::DeserializeError::ParsingError),
+        }
+        Ok(())
+    }
+
+    fn visit_node(node: &Node, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        match node {
+            Node::Values(values) => Self::visit_values(values, visitor),
+            Node::Struct(entries) => {
+                if visitor.hint() == Hint::Option {
+                    return Self::visit_node(node, visitor.visit_some()?);
+                }
+                let mut builder = visitor.visit_struct()?;
+                for (key, node) in entries {
+                    let (id, name) = de::split_key(key);
+                    match builder.member(id, name) {
+                        Ok(visitor) => Self::visit_node(node, visitor)?,
+                        Err(de::DeserializeError::UnknownField) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                builder.finish()
+            }
+        }
+    }
+
+    // Values are text, the visitor's hint tells what to convert them to. The
+    // last one wins when a single value is expected. Null fields are left out,
+    // so a present key is never null, even with an empty value.
+    fn visit_values(
+        values: &[Vec<u8>],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match visitor.hint() {
+            Hint::Option => Self::visit_values(values, visitor.visit_some()?),
+            Hint::Seq(_) => Self::visit_seq(values, visitor),
+            Hint::Any if values.len() > 1 => Self::visit_seq(values, visitor),
+            _ => Self::visit_value(values.last().map_or(&[], Vec::as_slice), visitor),
+        }
+    }
+
+    fn visit_seq(
+        values: &[Vec<u8>],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_seq(Some(values.len()))?;
+        for value in values {
+            Self::visit_value(value, builder.element()?)?;
+        }
+        builder.finish()
+    }
+
+    fn visit_value(
+        value: &[u8],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        if visitor.hint() == Hint::Bytes {
+            return visitor.visit_bytes(value);
+        }
+        let value = std::str::from_utf8(value).map_err(|_| de::DeserializeError::ParsingError)?;
+        de::visit_hinted_str(visitor, value)
+    }
+}
+
+impl de::Deserializer for Deserializer {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        Self::visit_node(&self.root, visitor)
+    }
+}
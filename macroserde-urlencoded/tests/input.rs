@@ -0,0 +1,97 @@
+// Handwritten query strings: nested keys, repeated keys and percent-encoding.
+
+use fixtures::{person, Contact, Date, Month, Occupation, Person};
+use macroserde::de::*;
+
+fn decode<T: Deserialize>(s: &str) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_urlencoded::Deserializer::new(s)?)
+}
+
+#[test]
+fn readme_person() {
+    let query = "?name=Steven&age=27&birth_date.day=19&birth_date.month=OCT&birth_date.year=1993\
+                 &pets=Bouboul&pets=Monsieur+Puppy&height=1.735&IsCool=true&occupation.hasJob=Engineer";
+    assert_eq!(decode::<Person>(query).unwrap(), person());
+
+    // Keys can be IDs, in any order, and unknown keys are skipped.
+    let query = "7.2=Engineer&6=true&5=1.735&4=Bouboul&3.3=1993&3.2=10&3.1=19&2=27&1=Steven\
+                 &4=Monsieur%20Puppy&utm_source=mail";
+    assert_eq!(decode::<Person>(query).unwrap(), person());
+}
+
+#[test]
+fn nested_keys() {
+    let contact =
+        decode::<Contact>("name=&birth_date.year=1993&birth_date.day=19&birth_date.month=OCT")
+            .unwrap();
+    assert_eq!(
+        contact.birth_date,
+        Some(Date {
+            day: 19,
+            month: Month::October,
+            year: 1993,
+        })
+    );
+    assert_eq!(contact.car_brand, None);
+
+    let unemployed = decode::<Person>("name=Steven&occupation.Unemployed=").unwrap();
+    assert_eq!(unemployed.occupation, Occupation::Unemployed);
+
+    // A key can't both have a value and nested keys.
+    assert!(matches!(
+        decode::<Contact>("birth_date=1&birth_date.day=19"),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn percent_encoding() {
+    let contact = decode::<Contact>("%6Eame=Zo%C3%A9+%26+co%3D%25&carBrand=").unwrap();
+    assert_eq!(contact.name, "Zoé & co=%");
+    // A present key is never null.
+    assert_eq!(contact.car_brand, Some(String::new()));
+
+    assert!(matches!(
+        decode::<Contact>("name=%ZZ"),
+        Err(DeserializeError::ParsingError)
+    ));
+    assert!(matches!(
+        decode::<Contact>("name=%4"),
+        Err(DeserializeError::ParsingError)
+    ));
+    // Only bytes may be invalid UTF-8.
+    assert!(matches!(
+        decode::<Contact>("name=%FF"),
+        Err(DeserializeError::ParsingError)
+    ));
+}
+
+#[test]
+fn repeated_keys() {
+    // The last value wins for single values.
+    assert_eq!(decode::<Contact>("name=a&name=b").unwrap().name, "b");
+    assert_eq!(
+        decode::<Person>("pets=a&name=Steven&pets=b").unwrap().pets,
+        vec!["a", "b"]
+    );
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    assert!(matches!(
+        macroserde_urlencoded::Deserializer::with_limits("name=Steven", limits).err(),
+        Some(DeserializeError::LimitExceeded(Limit::StrLen))
+    ));
+    let limits = DeserializeLimits {
+        max_depth: 2,
+        ..DeserializeLimits::default()
+    };
+    assert!(matches!(
+        macroserde_urlencoded::Deserializer::with_limits("a.b.c=1", limits).err(),
+        Some(DeserializeError::LimitExceeded(Limit::Depth))
+    ));
+}
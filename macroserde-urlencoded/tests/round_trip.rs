@@ -0,0 +1,93 @@
+// Round trips of the README types.
+
+use fixtures::{contacts, person, Contact, Occupation, Person};
+use macroserde::bytes::Bytes;
+use macroserde::de::*;
+use macroserde::macroserde;
+use macroserde::ser::*;
+use std::fmt::Debug;
+
+fn encode<T: Serialize>(value: &T) -> Result<String, SerializeError> {
+    let mut ser = macroserde_urlencoded::Serializer::new();
+    value.serialize(&mut ser)?;
+    let mut out = Vec::new();
+    ser.write(&mut out).unwrap();
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    let encoded = encode(&value).unwrap();
+    let decoded = T::deserialize(&mut macroserde_urlencoded::Deserializer::new(&encoded).unwrap());
+    assert_eq!(decoded.unwrap(), value, "decoding {:?}", encoded);
+}
+
+macroserde! {
+    #[derive(Debug, PartialEq, Default)]
+    struct Values {
+        numbers: Vec<Option<u32>> = 1,
+        data: Bytes = 2,
+        chunks: Vec<Bytes> = 3,
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    for contact in contacts() {
+        check::<Contact>(contact);
+    }
+}
+
+#[test]
+fn seq_of_options() {
+    check(Values {
+        numbers: vec![Some(1), Some(0)],
+        ..Values::default()
+    });
+    // A null element can't be left out like a null field.
+    assert!(encode(&Values {
+        numbers: vec![Some(1), None, Some(2)],
+        ..Values::default()
+    })
+    .is_err());
+}
+
+#[test]
+fn bytes() {
+    let values = Values {
+        data: Bytes(vec![0, 255, 10]),
+        chunks: vec![Bytes(vec![10]), Bytes(b"a b".to_vec())],
+        ..Values::default()
+    };
+    assert_eq!(
+        encode(&values).unwrap(),
+        "data=%00%FF%0A&chunks=%0A&chunks=a+b"
+    );
+    check(values);
+
+    // Bytes are a single value, the last one wins as for strings.
+    let decoded = Values::deserialize(
+        &mut macroserde_urlencoded::Deserializer::new("data=%00%FF%0A&data=10").unwrap(),
+    );
+    assert_eq!(decoded.unwrap().data, Bytes(b"10".to_vec()));
+}
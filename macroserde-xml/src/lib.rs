@@ -0,0 +1,464 @@
+mod parser;
+
+use macroserde::de::{DeserializeLimits, Hint};
+use macroserde::{de, ser};
+use parser::{Element, Parser};
+use std::borrow::Cow;
+use std::io;
+
+pub use parser::{ParseError, ParseErrorKind};
+
+enum Value {
+    Null,
+    Text(String),
+    Seq(Vec<Value>),
+    Struct(Vec<(u32, Cow<'static, str>, Value)>),
+    // The variant of a union, written even when null.
+    Union(Vec<(u32, Cow<'static, str>, Value)>),
+}
+
+// Structs are elements holding an element per field, named after the field
+// and with its ID as `id` attribute: `<birth_date id="3"><day id="1">19</day>
+// </birth_date>`. Sequence fields repeat their element, other sequences hold
+// `<item>` elements. Unions are a struct with a single field, null fields are
+// left out and bytes are written in hexadecimal.
+pub struct Serializer {
+    current_value: Value,
+    stack: Vec<Value>,
+    root_name: String,
+    // Fields written as attributes of their struct's element when they are
+    // scalars.
+    attributes: Vec<String>,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self {
+            current_value: Value::Null,
+            stack: Vec::new(),
+            root_name: "root".to_owned(),
+            attributes: Vec::new(),
+        }
+    }
+
+    pub fn with_root_name(mut self, name: &str) -> Self {
+        self.root_name = name.to_owned();
+        self
+    }
+
+    // Writes the fields of this name as attributes, e.g. `<date day="19">`.
+    // `id` can't be used as it holds the ID of fields.
+    pub fn with_attribute(mut self, field_name: &str) -> Self {
+        self.attributes.push(field_name.to_owned());
+        self
+    }
+
+    pub fn write_pretty<W: io::Write>(&self, writer: &mut W, spaces: u16) -> io::Result<()> {
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        let indent = Some((spaces as usize, 0));
+        self.write_element(writer, &self.root_name, None, &self.current_value, indent)
+    }
+
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        self.write_element(writer, &self.root_name, None, &self.current_value, None)
+    }
+
+    fn write_escaped<W: io::Write>(writer: &mut W, s: &str) -> io::Result<()> {
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            let entity = match c {
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '&' => "&amp;",
+                '"' => "&quot;",
+                '\'' => "&apos;",
+                '\r' => "&#13;",
+                _ => continue,
+            };
+            write!(writer, "{}{}", &s[start..i], entity)?;
+            start = i + c.len_utf8();
+        }
+        write!(writer, "{}", &s[start..])
+    }
+
+    fn is_attribute(&self, name: &str, value: &Value) -> bool {
+        matches!(value, Value::Text(_)) && self.attributes.iter().any(|attr| attr == name)
+    }
+
+    // `indent` is the number of spaces per level and the current level when
+    // pretty printing.
+    fn write_element<W: io::Write>(
+        &self,
+        writer: &mut W,
+        name: &str,
+        id: Option<u32>,
+        value: &Value,
+        indent: Option<(usize, usize)>,
+    ) -> io::Result<()> {
+        if !name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"_-.".contains(&byte))
+            || !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} isn't a valid element name", name),
+            ));
+        }
+        if let Some((spaces, level)) = indent {
+            write!(writer, "{:1$}", "", spaces * level)?;
+        }
+        write!(writer, "<{}", name)?;
+        if let Some(id) = id {
+            write!(writer, " id=\"{}\"", id)?;
+        }
+        let child_indent = indent.map(|(spaces, level)| (spaces, level + 1));
+
+        let has_children = match value {
+            Value::Null => false,
+            Value::Text(text) => {
+                write!(writer, ">")?;
+                Self::write_escaped(writer, text)?;
+                write!(writer, "</{}>", name)?;
+                if indent.is_some() {
+                    writeln!(writer)?;
+                }
+                return Ok(());
+            }
+            Value::Seq(elements) => {
+                if !elements.is_empty() {
+                    write!(writer, ">")?;
+                    if indent.is_some() {
+                        writeln!(writer)?;
+                    }
+                }
+                for element in elements {
+                    self.write_element(writer, "item", None, element, child_indent)?;
+                }
+                !elements.is_empty()
+            }
+            Value::Struct(fields) | Value::Union(fields) => {
+                let is_union = matches!(value, Value::Union(_));
+                for (_, field_name, value) in fields {
+                    if let (true, Value::Text(text)) = (self.is_attribute(field_name, value), value)
+                    {
+                        write!(writer, " {}=\"", field_name)?;
+                        Self::write_escaped(writer, text)?;
+                        write!(writer, "\"")?;
+                    }
+                }
+                let mut has_children = false;
+                for (field_id, field_name, value) in fields {
+                    if self.is_attribute(field_name, value) {
+                        continue;
+                    }
+                    // Null fields are left out and read back as their default,
+                    // except for unit union variants which would be lost.
+                    let elements = match value {
+                        Value::Null if !is_union => continue,
+                        Value::Seq(elements) => elements.iter().collect(),
+                        value => vec![value],
+                    };
+                    for element in elements {
+                        if !has_children {
+                            write!(writer, ">")?;
+                            if indent.is_some() {
+                                writeln!(writer)?;
+                            }
+                            has_children = true;
+                        }
+                        self.write_element(
+                            writer,
+                            field_name,
+                            Some(*field_id),
+                            element,
+                            child_indent,
+                        )?;
+                    }
+                }
+                has_children
+            }
+        };
+
+        match has_children {
+            true => {
+                if let Some((spaces, level)) = indent {
+                    write!(writer, "{:1$}", "", spaces * level)?;
+                }
+                write!(writer, "</{}>", name)?;
+            }
+            false => write!(writer, "/>")?,
+        }
+        if indent.is_some() {
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ser::Serializer for Serializer {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Null;
+        Ok(())
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Text(value.to_string());
+        Ok(())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Text(value.to_string());
+        Ok(())
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Text(value.to_string());
+        Ok(())
+    }
+
+    // Infinities and NaN are written as in XML Schema.
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        let text = match value {
+            _ if value.is_nan() => "NaN".to_owned(),
+            f64::INFINITY => "INF".to_owned(),
+            f64::NEG_INFINITY => "-INF".to_owned(),
+            _ => format!("{:?}", value),
+        };
+        self.current_value = Value::Text(text);
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Text(value.to_owned());
+        Ok(())
+    }
+
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), ser::SerializeError> {
+        let hex = value.iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.current_value = Value::Text(hex);
+        Ok(())
+    }
+
+    fn serialize_enum(
+        &mut self,
+        _value: u32,
+        name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Text(name.into_owned());
+        Ok(())
+    }
+
+    fn start_struct(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Struct(Vec::with_capacity(len)));
+        Ok(())
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        if let Some(Value::Struct(fields)) = self.stack.last_mut() {
+            fields.push((field_id, field_name, value));
+        }
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = self.stack.pop().unwrap();
+        Ok(())
+    }
+
+    fn start_seq(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Seq(Vec::with_capacity(len)));
+        Ok(())
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        if let Some(Value::Seq(elements)) = self.stack.last_mut() {
+            elements.push(value);
+        }
+        Ok(())
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = self.stack.pop().unwrap();
+        Ok(())
+    }
+
+    fn serialize_union(
+        &mut self,
+        variant_id: u32,
+        variant_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        self.current_value = Value::Union(vec![(variant_id, variant_name, value)]);
+        Ok(())
+    }
+}
+
+pub struct Deserializer {
+    root: Element,
+}
+
+impl Deserializer {
+    pub fn new(s: &str) -> Result<Self, ParseError> {
+        Self::with_limits(s, DeserializeLimits::default())
+    }
+
+    pub fn with_limits(s: &str, limits: DeserializeLimits) -> Result<Self, ParseError> {
+        Ok(Self {
+            root: Parser::new(s, limits).parse()?,
+        })
+    }
+
+    fn field_attributes(element: &Element) -> impl Iterator<Item = &(String, String)> {
+        element.attributes.iter().filter(|(name, _)| name != "id")
+    }
+
+    fn is_empty(element: &Element) -> bool {
+        element.children.is_empty()
+            && element.text.is_empty()
+            && Self::field_attributes(element).next().is_none()
+    }
+
+    // Visits the content of an element.
+    fn visit_element(
+        element: &Element,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let has_fields =
+            !element.children.is_empty() || Self::field_attributes(element).next().is_some();
+        match visitor.hint() {
+            Hint::Option if Self::is_empty(element) => visitor.visit_null(),
+            Hint::Option => Self::visit_element(element, visitor.visit_some()?),
+            Hint::Seq(_) => {
+                let mut builder = visitor.visit_seq(Some(element.children.len()))?;
+                for child in &element.children {
+                    Self::visit_element(child, builder.element()?)?;
+                }
+                builder.finish()
+            }
+            // A unit union variant can be written as its name alone.
+            Hint::Union(_) if !has_fields && !element.text.trim().is_empty() => {
+                let mut builder = visitor.visit_struct()?;
+                builder
+                    .member(None, Some(element.text.trim()))?
+                    .visit_null()?;
+                builder.finish()
+            }
+            Hint::Struct(_) | Hint::Union(_) | Hint::Ext => Self::visit_fields(element, visitor),
+            Hint::Any if has_fields => Self::visit_fields(element, visitor),
+            _ => Self::visit_text(&element.text, visitor),
+        }
+        .map_err(|e| e.at(element.position))
+    }
+
+    // Attributes are fields found by name, child elements by name and `id`.
+    // Repeated elements are the elements of a sequence.
+    fn visit_fields(
+        element: &Element,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let mut builder = visitor.visit_struct()?;
+        for (name, value) in Self::field_attributes(element) {
+            match builder.member(None, Some(name)) {
+                Ok(visitor) => Self::visit_text(value, visitor)?,
+                Err(de::DeserializeError::UnknownField) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut groups: Vec<(&str, Vec<&Element>)> = Vec::new();
+        for child in &element.children {
+            match groups.iter_mut().find(|(name, _)| *name == child.name) {
+                Some((_, elements)) => elements.push(child),
+                None => groups.push((&child.name, vec![child])),
+            }
+        }
+        for (name, elements) in groups {
+            let id = elements[0].attribute("id").and_then(|id| id.parse().ok());
+            match builder.member(id, Some(name)) {
+                Ok(visitor) => Self::visit_group(&elements, visitor)?,
+                Err(de::DeserializeError::UnknownField) => {}
+                Err(e) => return Err(e.at(elements[0].position)),
+            }
+        }
+        builder.finish()
+    }
+
+    fn visit_group(
+        elements: &[&Element],
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        match visitor.hint() {
+            // Null fields are left out, an empty element is an empty value.
+            Hint::Option => Self::visit_group(elements, visitor.visit_some()?),
+            Hint::Seq(_) => {
+                let mut builder = visitor.visit_seq(Some(elements.len()))?;
+                for element in elements {
+                    Self::visit_element(element, builder.element()?)?;
+                }
+                builder.finish()
+            }
+            Hint::Any if elements.len() > 1 => {
+                let mut builder = visitor.visit_seq(Some(elements.len()))?;
+                for element in elements {
+                    Self::visit_element(element, builder.element()?)?;
+                }
+                builder.finish()
+            }
+            // The last one wins when a single value is expected.
+            _ => Self::visit_element(elements[elements.len() - 1], visitor),
+        }
+    }
+
+    // Text is converted to what the visitor expects, surrounding whitespace
+    // is only kept for strings. Null fields are left out, so text that is
+    // there is never null, even when empty (`carBrand=""`).
+    fn visit_text(text: &str, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        match visitor.hint() {
+            Hint::Str => visitor.visit_str(text),
+            Hint::Option => Self::visit_text(text, visitor.visit_some()?),
+            Hint::Bytes => {
+                let digit = |byte: u8| (byte as char).to_digit(16);
+                let bytes = text
+                    .trim()
+                    .as_bytes()
+                    .chunks(2)
+                    .map(|pair| match *pair {
+                        [high, low] => Some((digit(high)? * 16 + digit(low)?) as u8),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or(de::DeserializeError::ParsingError)?;
+                visitor.visit_bytes(&bytes)
+            }
+            _ => de::visit_hinted_str(visitor, text.trim()),
+        }
+    }
+}
+
+impl de::Deserializer for Deserializer {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        Self::visit_element(&self.root, visitor)
+    }
+}
@@ -0,0 +1,390 @@
+use macroserde::de::{DeserializeLimits, Limit, LimitTracker, Position};
+use std::fmt;
+
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    // Text and CDATA content, ignored when the element has children.
+    pub text: String,
+    pub position: Position,
+}
+
+impl Element {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    InvalidEntity,
+    // An end tag doesn't close the open element of this name.
+    MismatchedTag(String),
+    LimitExceeded(Limit),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+    // The source line containing the error.
+    pub snippet: String,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::InvalidEntity => write!(f, "invalid entity reference"),
+            ParseErrorKind::MismatchedTag(name) => write!(f, "expected </{}>", name),
+            ParseErrorKind::LimitExceeded(Limit::Depth) => write!(f, "too deeply nested"),
+            ParseErrorKind::LimitExceeded(Limit::StrLen) => write!(f, "text too long"),
+            ParseErrorKind::LimitExceeded(Limit::CollectionLen) => {
+                write!(f, "too many elements")
+            }
+            ParseErrorKind::LimitExceeded(Limit::TotalAlloc) => write!(f, "input too large"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.position.line, self.position.column
+        )?;
+        writeln!(f, "{}", self.snippet)?;
+        let caret_offset: String = self
+            .snippet
+            .chars()
+            .take(self.position.column - 1)
+            .map(|c| if c == '\t' { c } else { ' ' })
+            .collect();
+        write!(f, "{}^", caret_offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn is_name_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.' | b':') || byte >= 0x80
+}
+
+// Parses a document into its root element. The prolog, comments and
+// processing instructions are skipped, DTDs aren't read and only the
+// predefined and numeric entities are known.
+pub struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    index: usize,
+    line: usize,
+    column: usize,
+    line_start: usize,
+    limits: LimitTracker,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(src: &'a str, limits: DeserializeLimits) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            index: 0,
+            line: 1,
+            column: 1,
+            line_start: 0,
+            limits: LimitTracker::new(limits),
+        }
+    }
+
+    pub fn parse(mut self) -> Result<Element, ParseError> {
+        self.skip_misc()?;
+        if self.peek() != Some(b'<') {
+            return Err(self.unexpected());
+        }
+        let root = self.parse_element()?;
+        self.skip_misc()?;
+        match self.peek() {
+            None => Ok(root),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        self.error_at(kind, self.position())
+    }
+
+    fn error_at(&self, kind: ParseErrorKind, position: Position) -> ParseError {
+        let snippet = self
+            .src
+            .lines()
+            .nth(position.line - 1)
+            .unwrap_or("")
+            .trim_end_matches('\r')
+            .to_owned();
+        ParseError {
+            kind,
+            position,
+            snippet,
+        }
+    }
+
+    fn check_limit(&self, result: Result<(), Limit>) -> Result<(), ParseError> {
+        result.map_err(|limit| self.error(ParseErrorKind::LimitExceeded(limit)))
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.src[self.index..].chars().next() {
+            Some(c) => self.error(ParseErrorKind::UnexpectedChar(c)),
+            None => self.error(ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.index).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.src[self.index..].starts_with(s)
+    }
+
+    fn advance(&mut self) {
+        let byte = self.bytes[self.index];
+        self.index += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+            self.line_start = self.index;
+        } else if byte & 0xc0 != 0x80 {
+            // Columns count characters, not UTF-8 continuation bytes.
+            self.column += 1;
+        }
+    }
+
+    fn advance_by(&mut self, count: usize) {
+        for _ in 0..count {
+            self.advance();
+        }
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), ParseError> {
+        if !self.starts_with(s) {
+            return Err(self.unexpected());
+        }
+        self.advance_by(s.len());
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') = self.peek() {
+            self.advance();
+        }
+    }
+
+    fn skip_until(&mut self, end: &str) -> Result<(), ParseError> {
+        while !self.starts_with(end) {
+            if self.peek().is_none() {
+                return Err(self.unexpected());
+            }
+            self.advance();
+        }
+        self.advance_by(end.len());
+        Ok(())
+    }
+
+    // Skips whitespace, comments, processing instructions (including the XML
+    // declaration) and the document type declaration.
+    fn skip_misc(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if self.starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if self.starts_with("<!DOCTYPE") {
+                let mut depth = 0;
+                loop {
+                    match self.peek() {
+                        None => return Err(self.unexpected()),
+                        Some(b'[') => depth += 1,
+                        Some(b']') => depth -= 1,
+                        Some(b'>') if depth == 0 => break,
+                        _ => {}
+                    }
+                    self.advance();
+                }
+                self.advance();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_name(&mut self) -> Result<String, ParseError> {
+        let start = self.index;
+        while self.peek().is_some_and(is_name_char) {
+            self.advance();
+        }
+        match self.index > start {
+            true => Ok(self.src[start..self.index].to_owned()),
+            false => Err(self.unexpected()),
+        }
+    }
+
+    fn parse_element(&mut self) -> Result<Element, ParseError> {
+        let position = self.position();
+        self.advance();
+        let name = self.parse_name()?;
+        let mut element = Element {
+            name,
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: String::new(),
+            position,
+        };
+
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'/') => {
+                    self.expect("/>")?;
+                    return Ok(element);
+                }
+                Some(b'>') => {
+                    self.advance();
+                    break;
+                }
+                _ => {
+                    let name = self.parse_name()?;
+                    self.skip_whitespace();
+                    self.expect("=")?;
+                    self.skip_whitespace();
+                    let value = self.parse_attribute_value()?;
+                    element.attributes.push((name, value));
+                }
+            }
+        }
+
+        let limit = self.limits.enter();
+        self.check_limit(limit)?;
+        loop {
+            if self.starts_with("</") {
+                let end_position = self.position();
+                self.advance_by(2);
+                let name = self.parse_name()?;
+                self.skip_whitespace();
+                self.expect(">")?;
+                if name != element.name {
+                    let kind = ParseErrorKind::MismatchedTag(element.name);
+                    return Err(self.error_at(kind, end_position));
+                }
+                break;
+            } else if self.starts_with("<!--") {
+                self.skip_until("-->")?;
+            } else if self.starts_with("<![CDATA[") {
+                self.advance_by(9);
+                let start = self.index;
+                self.skip_until("]]>")?;
+                element.text.push_str(&self.src[start..(self.index - 3)]);
+            } else if self.starts_with("<?") {
+                self.skip_until("?>")?;
+            } else if self.peek() == Some(b'<') {
+                element.children.push(self.parse_element()?);
+            } else if self.peek().is_none() {
+                return Err(self.unexpected());
+            } else {
+                let text = self.parse_text(b'<')?;
+                element.text.push_str(&text);
+            }
+        }
+        self.limits.leave();
+
+        let limit = self.limits.check_str_len(element.text.len());
+        self.check_limit(limit)?;
+        let limit = self.limits.check_collection_len(element.children.len());
+        self.check_limit(limit)?;
+        Ok(element)
+    }
+
+    fn parse_attribute_value(&mut self) -> Result<String, ParseError> {
+        let quote = match self.peek() {
+            Some(quote @ b'"') | Some(quote @ b'\'') => quote,
+            _ => return Err(self.unexpected()),
+        };
+        self.advance();
+        let value = self.parse_text(quote)?;
+        if self.peek() != Some(quote) {
+            return Err(self.unexpected());
+        }
+        self.advance();
+        let limit = self.limits.check_str_len(value.len());
+        self.check_limit(limit)?;
+        Ok(value)
+    }
+
+    // Reads text up to `end`, replacing entity references.
+    fn parse_text(&mut self, end: u8) -> Result<String, ParseError> {
+        let mut text = String::new();
+        let mut start = self.index;
+        while let Some(byte) = self.peek() {
+            if byte == end {
+                break;
+            }
+            if byte == b'&' {
+                text.push_str(&self.src[start..self.index]);
+                text.push(self.parse_entity()?);
+                start = self.index;
+            } else {
+                self.advance();
+            }
+        }
+        text.push_str(&self.src[start..self.index]);
+        Ok(text)
+    }
+
+    fn parse_entity(&mut self) -> Result<char, ParseError> {
+        let position = self.position();
+        let end = match self.src[self.index..].find(';') {
+            Some(end) => self.index + end,
+            None => return Err(self.error(ParseErrorKind::InvalidEntity)),
+        };
+        let entity = &self.src[(self.index + 1)..end];
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix('#') {
+                Some(code) => match code.strip_prefix('x') {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => code.parse().ok(),
+                }
+                .and_then(char::from_u32),
+                None => None,
+            },
+        };
+        match c {
+            Some(c) => {
+                self.advance_by(end + 1 - self.index);
+                Ok(c)
+            }
+            None => Err(self.error_at(ParseErrorKind::InvalidEntity, position)),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Handwritten XML: attributes and elements, IDs, entities and CDATA.
+
+use fixtures::{address_book, person, AddressBook, Contact, Date, Month, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::Serialize;
+use macroserde_xml::ParseErrorKind;
+
+fn decode<T: Deserialize>(s: &str) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_xml::Deserializer::new(s).unwrap())
+}
+
+fn birthday() -> Date {
+    Date {
+        day: 19,
+        month: Month::October,
+        year: 1993,
+    }
+}
+
+#[test]
+fn readme_person() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!-- The person of the README. -->
+<person>
+  <name>Steven</name>
+  <age>27</age>
+  <birth_date id="3">
+    <day>19</day>
+    <month>OCT</month>
+    <year>1993</year>
+  </birth_date>
+  <pets>Bouboul</pets>
+  <pets>Monsieur Puppy</pets>
+  <height>1.735</height>
+  <IsCool>true</IsCool>
+  <occupation><hasJob>Engineer</hasJob></occupation>
+</person>"#;
+    assert_eq!(decode::<Person>(xml).unwrap(), person());
+}
+
+#[test]
+fn attributes_and_elements() {
+    // The same fields as attributes, as elements, or both.
+    let xml = [
+        r#"<date day="19" month="OCT" year="1993"/>"#,
+        r#"<date><day>19</day><month>OCT</month><year>1993</year></date>"#,
+        r#"<date day="19"><month>10</month><year> 1993 </year></date>"#,
+        // Elements are matched by `id` when their name is unknown.
+        r#"<date><d id="1">19</d><m id="2">OCT</m><y id="3">1993</y></date>"#,
+    ];
+    for xml in &xml {
+        assert_eq!(decode::<Date>(xml).unwrap(), birthday(), "decoding {}", xml);
+    }
+
+    let mut ser = macroserde_xml::Serializer::new()
+        .with_root_name("date")
+        .with_attribute("day")
+        .with_attribute("year");
+    birthday().serialize(&mut ser).unwrap();
+    let mut out = Vec::new();
+    ser.write(&mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(
+        out.ends_with(r#"<date day="19" year="1993"><month id="2">OCT</month></date>"#),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn options() {
+    let xml = r#"<book>
+  <contacts><name>Steven</name><birth_date day="19" month="OCT" year="1993"/><carBrand></carBrand></contacts>
+  <contacts><name/></contacts>
+</book>"#;
+    assert_eq!(decode::<AddressBook>(xml).unwrap(), address_book());
+
+    // An empty element is an empty string, a missing one null.
+    let contact = decode::<Contact>("<c><name/><carBrand/></c>").unwrap();
+    assert_eq!(contact.car_brand, Some(String::new()));
+    assert_eq!(contact.birth_date, None);
+    let contact = decode::<Contact>(r#"<c name="" carBrand=""/>"#).unwrap();
+    assert_eq!(contact.car_brand, Some(String::new()));
+}
+
+#[test]
+fn unions() {
+    let xml = "<p><name>Steven</name><occupation>Unemployed</occupation></p>";
+    assert_eq!(
+        decode::<Person>(xml).unwrap().occupation,
+        Occupation::Unemployed
+    );
+    let xml = "<p><occupation><Unemployed/></occupation></p>";
+    assert_eq!(
+        decode::<Person>(xml).unwrap().occupation,
+        Occupation::Unemployed
+    );
+    let xml = r#"<p><occupation><job id="2">Engineer</job></occupation></p>"#;
+    assert_eq!(
+        decode::<Person>(xml).unwrap().occupation,
+        Occupation::Employed("Engineer".to_owned())
+    );
+}
+
+#[test]
+fn text() {
+    let xml = "<c><name>Zo&#233; &amp; &lt;co&gt;<![CDATA[ <&> ]]></name></c>";
+    assert_eq!(decode::<Contact>(xml).unwrap().name, "Zoé & <co> <&> ");
+
+    let error = macroserde_xml::Deserializer::new("<c><name>&nbsp;</name></c>").err();
+    assert_eq!(error.map(|e| e.kind), Some(ParseErrorKind::InvalidEntity));
+    assert!(macroserde_xml::Deserializer::new("<c><name>Steven</nom></c>").is_err());
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    let error =
+        macroserde_xml::Deserializer::with_limits("<root><name>Steven</name></root>", limits).err();
+    assert!(
+        matches!(error, Some(ref e) if e.kind == ParseErrorKind::LimitExceeded(Limit::StrLen)),
+        "{:?}",
+        error
+    );
+}
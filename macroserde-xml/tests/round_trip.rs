@@ -0,0 +1,57 @@
+// Round trips of the README types.
+
+use fixtures::{address_book, person, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use std::fmt::Debug;
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    let serializers = vec![
+        macroserde_xml::Serializer::new(),
+        macroserde_xml::Serializer::new()
+            .with_attribute("name")
+            .with_attribute("day")
+            .with_attribute("pets")
+            .with_attribute("carBrand"),
+    ];
+    for mut ser in serializers {
+        value.serialize(&mut ser).unwrap();
+        for &pretty in &[false, true] {
+            let mut out = Vec::new();
+            if pretty {
+                ser.write_pretty(&mut out, 2).unwrap();
+            } else {
+                ser.write(&mut out).unwrap();
+            }
+            let encoded = String::from_utf8(out).unwrap();
+            let decoded = T::deserialize(&mut macroserde_xml::Deserializer::new(&encoded).unwrap());
+            assert_eq!(decoded.unwrap(), value, "decoding {}", encoded);
+        }
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    check(address_book());
+}
@@ -0,0 +1,411 @@
+mod parser;
+
+use macroserde::de::{DeserializeLimits, Hint};
+use macroserde::{de, ser};
+use parser::{Node, NodeKind, Parser};
+use std::borrow::Cow;
+use std::io;
+
+pub use parser::{ParseError, ParseErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRepr {
+    /// `carBrand`, enumerations are written as `OCT`.
+    Name,
+    /// `10:carBrand`, enumerations are written as `10:OCT`.
+    IdAndName,
+    /// `10`, enumerations are written as `10`.
+    Id,
+}
+
+enum Value {
+    Null,
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Str(String),
+    Seq(Vec<Value>),
+    Map(Vec<(Cow<'static, str>, Value)>),
+}
+
+impl Value {
+    // Non-empty collections are written as blocks, on their own lines.
+    fn is_block(&self) -> bool {
+        match self {
+            Value::Seq(elements) => !elements.is_empty(),
+            Value::Map(entries) => !entries.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+// What a plain scalar stands for, following the YAML 1.2 core schema.
+enum Plain {
+    Null,
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Str,
+}
+
+fn resolve_plain(s: &str) -> Plain {
+    match s {
+        "" | "~" | "null" | "Null" | "NULL" => return Plain::Null,
+        "true" | "True" | "TRUE" => return Plain::Bool(true),
+        "false" | "False" | "FALSE" => return Plain::Bool(false),
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => {
+            return Plain::Float(f64::INFINITY)
+        }
+        "-.inf" | "-.Inf" | "-.INF" => return Plain::Float(f64::NEG_INFINITY),
+        ".nan" | ".NaN" | ".NAN" => return Plain::Float(f64::NAN),
+        _ => {}
+    }
+
+    let (negative, digits) = match s.as_bytes()[0] {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let integer = if let Some(hex) = digits.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(octal) = digits.strip_prefix("0o") {
+        u64::from_str_radix(octal, 8).ok()
+    } else if !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        digits.parse().ok()
+    } else {
+        None
+    };
+    match integer {
+        Some(value) if !negative => return Plain::Unsigned(value),
+        Some(value) if value <= i64::MAX as u64 + 1 => {
+            return Plain::Signed((value as i64).wrapping_neg())
+        }
+        _ => {}
+    }
+
+    let is_float_syntax = digits.bytes().any(|byte| byte.is_ascii_digit())
+        && digits
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-'));
+    match s.parse() {
+        Ok(value) if is_float_syntax => Plain::Float(value),
+        _ => Plain::Str,
+    }
+}
+
+// Whether a string can't be written as is, because of its syntax or because
+// it would be read back as something else than a string.
+fn needs_quotes(s: &str, is_key: bool) -> bool {
+    let first = match s.chars().next() {
+        Some(c) => c,
+        None => return true,
+    };
+    (!is_key && !matches!(resolve_plain(s), Plain::Str))
+        || "-?:,[]{}#&*!|>'\"%@`".contains(first)
+        || s.starts_with(char::is_whitespace)
+        || s.ends_with(char::is_whitespace)
+        || s.ends_with(':')
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.contains(char::is_control)
+}
+
+pub struct Serializer {
+    current_value: Value,
+    stack: Vec<Value>,
+    key_repr: KeyRepr,
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Self {
+            current_value: Value::Null,
+            stack: Vec::new(),
+            key_repr: KeyRepr::Name,
+        }
+    }
+
+    pub fn with_key_repr(mut self, key_repr: KeyRepr) -> Self {
+        self.key_repr = key_repr;
+        self
+    }
+
+    // Writes block collections, indented by two spaces.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self.current_value.is_block() {
+            true => Self::write_block(writer, &self.current_value, 0, false),
+            false => {
+                Self::write_scalar(writer, &self.current_value)?;
+                writeln!(writer)
+            }
+        }
+    }
+
+    fn write_str<W: io::Write>(writer: &mut W, s: &str, is_key: bool) -> io::Result<()> {
+        if !needs_quotes(s, is_key) {
+            return write!(writer, "{}", s);
+        }
+        write!(writer, "\"")?;
+        for c in s.chars() {
+            match c {
+                '"' => write!(writer, "\\\"")?,
+                '\\' => write!(writer, "\\\\")?,
+                '\n' => write!(writer, "\\n")?,
+                '\r' => write!(writer, "\\r")?,
+                '\t' => write!(writer, "\\t")?,
+                c if c.is_control() => write!(writer, "\\u{:04x}", c as u32)?,
+                c => write!(writer, "{}", c)?,
+            }
+        }
+        write!(writer, "\"")
+    }
+
+    fn write_scalar<W: io::Write>(writer: &mut W, value: &Value) -> io::Result<()> {
+        match value {
+            Value::Null => write!(writer, "null"),
+            Value::Bool(b) => write!(writer, "{}", b),
+            Value::Signed(i) => write!(writer, "{}", i),
+            Value::Unsigned(u) => write!(writer, "{}", u),
+            Value::Float(f) if f.is_nan() => write!(writer, ".nan"),
+            Value::Float(f) if f.is_infinite() => {
+                write!(writer, "{}.inf", if *f < 0.0 { "-" } else { "" })
+            }
+            Value::Float(f) => write!(writer, "{:?}", f),
+            Value::Str(s) => Self::write_str(writer, s, false),
+            Value::Seq(_) => write!(writer, "[]"),
+            Value::Map(_) => write!(writer, "{{}}"),
+        }
+    }
+
+    // Writes each entry or element on its own line at `indent`, except the
+    // first one which continues the current line after a `- ` when `inline`
+    // is set.
+    fn write_block<W: io::Write>(
+        writer: &mut W,
+        value: &Value,
+        indent: usize,
+        inline: bool,
+    ) -> io::Result<()> {
+        let write_indent = |writer: &mut W, i: usize| match i > 0 || !inline {
+            true => write!(writer, "{:1$}", "", indent),
+            false => Ok(()),
+        };
+        match value {
+            Value::Map(entries) => {
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    write_indent(writer, i)?;
+                    Self::write_str(writer, key, true)?;
+                    write!(writer, ":")?;
+                    if value.is_block() {
+                        writeln!(writer)?;
+                        Self::write_block(writer, value, indent + 2, false)?;
+                    } else {
+                        write!(writer, " ")?;
+                        Self::write_scalar(writer, value)?;
+                        writeln!(writer)?;
+                    }
+                }
+            }
+            Value::Seq(elements) => {
+                for (i, element) in elements.iter().enumerate() {
+                    write_indent(writer, i)?;
+                    write!(writer, "- ")?;
+                    if element.is_block() {
+                        Self::write_block(writer, element, indent + 2, true)?;
+                    } else {
+                        Self::write_scalar(writer, element)?;
+                        writeln!(writer)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ser::Serializer for Serializer {
+    fn serialize_null(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Null;
+        Ok(())
+    }
+
+    fn serialize_bool(&mut self, value: bool) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Bool(value);
+        Ok(())
+    }
+
+    fn serialize_signed(&mut self, value: i64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Signed(value);
+        Ok(())
+    }
+
+    fn serialize_unsigned(&mut self, value: u64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Unsigned(value);
+        Ok(())
+    }
+
+    fn serialize_float(&mut self, value: f64) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Float(value);
+        Ok(())
+    }
+
+    fn serialize_str(&mut self, value: &str) -> Result<(), ser::SerializeError> {
+        self.current_value = Value::Str(value.to_owned());
+        Ok(())
+    }
+
+    fn serialize_enum(
+        &mut self,
+        value: u32,
+        name: Cow<'static, str>,
+    ) -> Result<(), ser::SerializeError> {
+        self.current_value = match self.key_repr {
+            KeyRepr::Name => Value::Str(name.into_owned()),
+            KeyRepr::IdAndName => Value::Str(format!("{}:{}", value, name)),
+            KeyRepr::Id => Value::Unsigned(value as u64),
+        };
+        Ok(())
+    }
+
+    fn start_struct(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Map(Vec::with_capacity(len)));
+        Ok(())
+    }
+
+    fn serialize_struct_field(
+        &mut self,
+        field_id: u32,
+        field_name: Cow<'static, str>,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        let key = match self.key_repr {
+            KeyRepr::Name => field_name,
+            KeyRepr::IdAndName => format!("{}:{}", field_id, field_name).into(),
+            KeyRepr::Id => field_id.to_string().into(),
+        };
+        if let Some(Value::Map(entries)) = self.stack.last_mut() {
+            entries.push((key, value));
+        }
+        Ok(())
+    }
+
+    fn end_struct(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = self.stack.pop().unwrap();
+        Ok(())
+    }
+
+    fn start_seq(&mut self, len: usize) -> Result<(), ser::SerializeError> {
+        self.stack.push(Value::Seq(Vec::with_capacity(len)));
+        Ok(())
+    }
+
+    fn serialize_seq_elmt(
+        &mut self,
+        value: &dyn ser::Serialize,
+    ) -> Result<(), ser::SerializeError> {
+        value.serialize(self)?;
+        let value = std::mem::replace(&mut self.current_value, Value::Null);
+        if let Some(Value::Seq(elements)) = self.stack.last_mut() {
+            elements.push(value);
+        }
+        Ok(())
+    }
+
+    fn end_seq(&mut self) -> Result<(), ser::SerializeError> {
+        self.current_value = self.stack.pop().unwrap();
+        Ok(())
+    }
+}
+
+pub struct Deserializer {
+    value: Node,
+}
+
+impl Deserializer {
+    pub fn new(s: &str) -> Result<Self, ParseError> {
+        Self::with_limits(s, DeserializeLimits::default())
+    }
+
+    pub fn with_limits(s: &str, limits: DeserializeLimits) -> Result<Self, ParseError> {
+        Ok(Self {
+            value: Parser::new(s, limits).parse()?,
+        })
+    }
+
+    fn visit_value(
+        value: &Node,
+        visitor: &mut dyn de::Visitor,
+    ) -> Result<(), de::DeserializeError> {
+        let hint = visitor.hint();
+        match &value.kind {
+            NodeKind::Seq(_) | NodeKind::Map(_) if hint == Hint::Option => {
+                Self::visit_value(value, visitor.visit_some()?)
+            }
+            // A unit union variant can be written as its name alone.
+            NodeKind::Plain(s) | NodeKind::Quoted(s)
+                if matches!(hint, Hint::Union(_)) && !s.is_empty() =>
+            {
+                let mut builder = visitor.visit_struct()?;
+                let (id, name) = de::split_key(s);
+                builder.member(id, name)?.visit_null()?;
+                builder.finish()
+            }
+            NodeKind::Plain(s) if hint != Hint::Str => Self::visit_plain(s, visitor),
+            NodeKind::Plain(s) | NodeKind::Quoted(s) => visitor.visit_str(s),
+            NodeKind::Seq(elements) => {
+                let mut builder = visitor.visit_seq(Some(elements.len()))?;
+                for element in elements {
+                    Self::visit_value(element, builder.element()?)?;
+                }
+                builder.finish()
+            }
+            NodeKind::Map(entries) => {
+                let mut builder = visitor.visit_struct()?;
+                for (key, value) in entries {
+                    let (id, name) = de::split_key(key);
+                    match builder.member(id, name) {
+                        Ok(visitor) => Self::visit_value(value, visitor)?,
+                        Err(de::DeserializeError::UnknownField) => {}
+                        Err(e) => Err(e.at(value.position))?,
+                    }
+                }
+                builder.finish()
+            }
+        }
+        .map_err(|e| e.at(value.position))
+    }
+
+    // Plain scalars that aren't what the visitor expects are read as strings,
+    // e.g. `1993` for a `String` field.
+    fn visit_plain(s: &str, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        let result = match resolve_plain(s) {
+            Plain::Null => visitor.visit_null(),
+            Plain::Bool(b) => visitor.visit_bool(b),
+            Plain::Signed(i) => visitor.visit_signed(i),
+            Plain::Unsigned(u) => visitor.visit_unsigned(u),
+            Plain::Float(f) => visitor.visit_float(f),
+            Plain::Str => return visitor.visit_str(s),
+        };
+        match result {
+            Err(de::DeserializeError::UnimplementedVisit) => visitor.visit_str(s),
+            result => result,
+        }
+    }
+}
+
+impl de::Deserializer for Deserializer {
+    fn deserialize(&mut self, visitor: &mut dyn de::Visitor) -> Result<(), de::DeserializeError> {
+        Self::visit_value(&self.value, visitor)
+    }
+}
@@ -0,0 +1,903 @@
+use macroserde::de::{DeserializeLimits, Limit, LimitTracker, Position};
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+
+#[derive(Clone)]
+pub enum NodeKind {
+    // Plain scalars are resolved when visited (`null`, `true`, `12`, `OCT`),
+    // empty values are empty plain scalars.
+    Plain(String),
+    // Quoted and block scalars are always strings.
+    Quoted(String),
+    Seq(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+#[derive(Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub position: Position,
+}
+
+impl Node {
+    fn count(&self) -> usize {
+        match &self.kind {
+            NodeKind::Plain(_) | NodeKind::Quoted(_) => 1,
+            NodeKind::Seq(elements) => 1 + elements.iter().map(Node::count).sum::<usize>(),
+            NodeKind::Map(entries) => 1 + entries.iter().map(|(_, v)| v.count()).sum::<usize>(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    InvalidEscape,
+    InvalidIndentation,
+    UnknownAnchor(String),
+    LimitExceeded(Limit),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Position,
+    // The source line containing the error.
+    pub snippet: String,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            ParseErrorKind::InvalidIndentation => write!(f, "invalid indentation"),
+            ParseErrorKind::UnknownAnchor(name) => write!(f, "unknown anchor {:?}", name),
+            ParseErrorKind::LimitExceeded(Limit::Depth) => write!(f, "too deeply nested"),
+            ParseErrorKind::LimitExceeded(Limit::StrLen) => write!(f, "string too long"),
+            ParseErrorKind::LimitExceeded(Limit::CollectionLen) => {
+                write!(f, "too many elements")
+            }
+            ParseErrorKind::LimitExceeded(Limit::TotalAlloc) => write!(f, "input too large"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.position.line, self.position.column
+        )?;
+        writeln!(f, "{}", self.snippet)?;
+        let caret_offset: String = self
+            .snippet
+            .chars()
+            .take(self.position.column - 1)
+            .map(|c| if c == '\t' { c } else { ' ' })
+            .collect();
+        write!(f, "{}^", caret_offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Chomping {
+    Clip,
+    Strip,
+    Keep,
+}
+
+fn is_blank_or_end(byte: Option<u8>) -> bool {
+    matches!(
+        byte,
+        None | Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n')
+    )
+}
+
+// Parses the block and flow subset of YAML: mappings, sequences, plain,
+// quoted and block scalars, comments, anchors and aliases. Tags and complex
+// keys aren't supported and only the first document is read.
+pub struct Parser<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    index: usize,
+    line: usize,
+    column: usize,
+    line_start: usize,
+    limits: LimitTracker,
+    // Anchored nodes along with their number of nodes.
+    anchors: HashMap<String, (Node, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(src: &'a str, limits: DeserializeLimits) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            index: 0,
+            line: 1,
+            column: 1,
+            line_start: 0,
+            limits: LimitTracker::new(limits),
+            anchors: HashMap::new(),
+        }
+    }
+
+    pub fn parse(mut self) -> Result<Node, ParseError> {
+        // Directives come before the document start marker.
+        while self.next_content_line()?.is_some() && self.peek() == Some(b'%') {
+            self.skip_line();
+        }
+        let node = if self.at_marker("---") {
+            self.advance_by(3);
+            self.skip_spaces();
+            match self.at_line_end() {
+                true => {
+                    self.skip_line_end()?;
+                    self.parse_block(-1)?
+                }
+                false => {
+                    let indent = self.index - self.line_start;
+                    self.parse_node_at(indent, -1)?
+                }
+            }
+        } else {
+            self.parse_block(-1)?
+        };
+        // Anything left must be the end of the document or another one.
+        match self.next_content_line()? {
+            Some(_) if !self.at_marker("---") && !self.at_marker("...") => Err(self.unexpected()),
+            _ => Ok(node),
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        self.error_at(kind, self.position())
+    }
+
+    fn error_at(&self, kind: ParseErrorKind, position: Position) -> ParseError {
+        let snippet = self
+            .src
+            .lines()
+            .nth(position.line - 1)
+            .unwrap_or("")
+            .trim_end_matches('\r')
+            .to_owned();
+        ParseError {
+            kind,
+            position,
+            snippet,
+        }
+    }
+
+    fn check_limit(&self, result: Result<(), Limit>) -> Result<(), ParseError> {
+        result.map_err(|limit| self.error(ParseErrorKind::LimitExceeded(limit)))
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.src[self.index..].chars().next() {
+            Some(c) => self.error(ParseErrorKind::UnexpectedChar(c)),
+            None => self.error(ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.index).copied()
+    }
+
+    #[inline]
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.index + offset).copied()
+    }
+
+    fn advance(&mut self) {
+        let byte = self.bytes[self.index];
+        self.index += 1;
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 1;
+            self.line_start = self.index;
+        } else if byte & 0xc0 != 0x80 {
+            // Columns count characters, not UTF-8 continuation bytes.
+            self.column += 1;
+        }
+    }
+
+    fn advance_by(&mut self, count: usize) {
+        for _ in 0..count {
+            self.advance();
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), ParseError> {
+        let limit = self.limits.enter();
+        self.check_limit(limit)
+    }
+
+    fn leave(&mut self) {
+        self.limits.leave();
+    }
+
+    // `---` or `...` at the start of a line.
+    fn at_marker(&self, marker: &str) -> bool {
+        self.index == self.line_start
+            && self.src[self.index..].starts_with(marker)
+            && is_blank_or_end(self.peek_at(3))
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(b' ') | Some(b'\t') = self.peek() {
+            self.advance();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while !matches!(self.peek(), None | Some(b'\n')) {
+            self.advance();
+        }
+        if self.peek().is_some() {
+            self.advance();
+        }
+    }
+
+    // After spaces: the end of the line or a comment.
+    fn at_line_end(&self) -> bool {
+        matches!(self.peek(), None | Some(b'\n') | Some(b'#') | Some(b'\r'))
+    }
+
+    // Skips the spaces and comment ending a line and its line break.
+    fn skip_line_end(&mut self) -> Result<(), ParseError> {
+        self.skip_spaces();
+        if self.peek() == Some(b'#') {
+            while !matches!(self.peek(), None | Some(b'\n')) {
+                self.advance();
+            }
+        }
+        if self.peek() == Some(b'\r') && self.peek_at(1) == Some(b'\n') {
+            self.advance();
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some(b'\n') => {
+                self.advance();
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    // Skips blank and comment lines, stopping at the start of the next line
+    // with content and returning its indentation. The end of the input and
+    // document markers end blocks.
+    fn next_content_line(&mut self) -> Result<Option<usize>, ParseError> {
+        loop {
+            let start = self.index;
+            let indent = self.bytes[start..]
+                .iter()
+                .take_while(|byte| **byte == b' ')
+                .count();
+            let mut i = start + indent;
+            while let Some(b' ') | Some(b'\t') = self.bytes.get(i) {
+                i += 1;
+            }
+            match self.bytes.get(i) {
+                None => {
+                    self.advance_by(i - start);
+                    return Ok(None);
+                }
+                Some(b'\n') | Some(b'\r') | Some(b'#') => self.skip_line(),
+                Some(_) if i != start + indent => {
+                    self.advance_by(start + indent - self.index);
+                    return Err(self.error(ParseErrorKind::UnexpectedChar('\t')));
+                }
+                Some(_) if self.at_marker("---") || self.at_marker("...") => return Ok(None),
+                Some(_) => return Ok(Some(indent)),
+            }
+        }
+    }
+
+    fn null(&self) -> Node {
+        Node {
+            kind: NodeKind::Plain(String::new()),
+            position: self.position(),
+        }
+    }
+
+    // Parses the block node on the following lines, which must be more
+    // indented than its parent.
+    fn parse_block(&mut self, parent: isize) -> Result<Node, ParseError> {
+        match self.next_content_line()? {
+            Some(indent) if indent as isize > parent => {
+                self.advance_by(indent);
+                self.parse_node_at(indent, parent)
+            }
+            _ => Ok(self.null()),
+        }
+    }
+
+    fn at_seq_entry(&self) -> bool {
+        self.peek() == Some(b'-') && is_blank_or_end(self.peek_at(1))
+    }
+
+    // Parses the node starting at the current position, which is at the
+    // column `indent`. Ends at the start of a line.
+    fn parse_node_at(&mut self, indent: usize, parent: isize) -> Result<Node, ParseError> {
+        if self.at_seq_entry() {
+            self.parse_block_seq(indent)
+        } else if self.at_mapping_key() {
+            self.parse_block_map(indent)
+        } else {
+            self.parse_inline(parent)
+        }
+    }
+
+    fn parse_block_seq(&mut self, indent: usize) -> Result<Node, ParseError> {
+        let position = self.position();
+        self.enter()?;
+        let mut elements = Vec::new();
+        loop {
+            self.advance();
+            self.skip_spaces();
+            let element = if self.at_line_end() {
+                self.skip_line_end()?;
+                self.parse_block(indent as isize)?
+            } else {
+                let column = self.index - self.line_start;
+                self.parse_node_at(column, indent as isize)?
+            };
+            elements.push(element);
+            match self.next_content_line()? {
+                Some(i) if i == indent && self.bytes[self.index + i] == b'-' => {
+                    self.advance_by(i);
+                    if !self.at_seq_entry() {
+                        return Err(self.unexpected());
+                    }
+                }
+                Some(i) if i > indent => {
+                    self.advance_by(i);
+                    return Err(self.error(ParseErrorKind::InvalidIndentation));
+                }
+                _ => break,
+            }
+        }
+        self.leave();
+        let limit = self.limits.check_collection_len(elements.len());
+        self.check_limit(limit)?;
+        Ok(Node {
+            kind: NodeKind::Seq(elements),
+            position,
+        })
+    }
+
+    fn parse_block_map(&mut self, indent: usize) -> Result<Node, ParseError> {
+        let position = self.position();
+        self.enter()?;
+        let mut entries = Vec::new();
+        loop {
+            let key = self.parse_key()?;
+            self.skip_spaces();
+            let value = if self.at_line_end() {
+                self.skip_line_end()?;
+                // A sequence can be at the same indentation as its key.
+                match self.next_content_line()? {
+                    Some(i) if i == indent && self.bytes.get(self.index + i) == Some(&b'-') => {
+                        self.advance_by(i);
+                        match self.at_seq_entry() {
+                            true => self.parse_block_seq(i)?,
+                            false => return Err(self.unexpected()),
+                        }
+                    }
+                    _ => self.parse_block(indent as isize)?,
+                }
+            } else {
+                self.parse_inline(indent as isize)?
+            };
+            entries.push((key, value));
+            match self.next_content_line()? {
+                Some(i) if i == indent => {
+                    self.advance_by(i);
+                    if !self.at_mapping_key() {
+                        return Err(self.unexpected());
+                    }
+                }
+                Some(i) if i > indent => {
+                    self.advance_by(i);
+                    return Err(self.error(ParseErrorKind::InvalidIndentation));
+                }
+                _ => break,
+            }
+        }
+        self.leave();
+        let limit = self.limits.check_collection_len(entries.len());
+        self.check_limit(limit)?;
+        Ok(Node {
+            kind: NodeKind::Map(entries),
+            position,
+        })
+    }
+
+    // Whether the line has a `key: ` from the current position.
+    fn at_mapping_key(&self) -> bool {
+        let bytes = self.bytes;
+        let mut i = self.index;
+        match bytes.get(i) {
+            Some(quote @ b'"') | Some(quote @ b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    if bytes[i] == b'\\' && *quote == b'"' {
+                        i += 1;
+                    } else if bytes[i] == *quote {
+                        if *quote == b'\'' && bytes.get(i + 1) == Some(&b'\'') {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                while let Some(b' ') | Some(b'\t') = bytes.get(i) {
+                    i += 1;
+                }
+                bytes.get(i) == Some(&b':') && is_blank_or_end(bytes.get(i + 1).copied())
+            }
+            Some(b'[') | Some(b'{') | Some(b'&') | Some(b'*') | Some(b'|') | Some(b'>') => false,
+            _ => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    if bytes[i] == b':' && is_blank_or_end(bytes.get(i + 1).copied()) {
+                        return true;
+                    }
+                    if bytes[i] == b'#' && i > self.index && matches!(bytes[i - 1], b' ' | b'\t') {
+                        return false;
+                    }
+                    i += 1;
+                }
+                false
+            }
+        }
+    }
+
+    // Parses `key:`, quoted or plain.
+    fn parse_key(&mut self) -> Result<String, ParseError> {
+        let key = match self.peek() {
+            Some(b'"') | Some(b'\'') => {
+                let key = self.parse_quoted()?;
+                self.skip_spaces();
+                key
+            }
+            _ => {
+                let src = self.src;
+                let start = self.index;
+                while !(self.peek() == Some(b':') && is_blank_or_end(self.peek_at(1))) {
+                    if self.peek().is_none() {
+                        return Err(self.unexpected());
+                    }
+                    self.advance();
+                }
+                src[start..self.index].trim_end().to_owned()
+            }
+        };
+        if self.peek() != Some(b':') {
+            return Err(self.unexpected());
+        }
+        self.advance();
+        Ok(key)
+    }
+
+    // Parses a value on the current line (or a block scalar, or the block node
+    // after an anchor), then the end of the line.
+    fn parse_inline(&mut self, parent: isize) -> Result<Node, ParseError> {
+        let position = self.position();
+        let node = match self.peek() {
+            Some(b'&') => {
+                self.advance();
+                let name = self.parse_anchor_name()?;
+                self.skip_spaces();
+                let mut node = if self.at_line_end() {
+                    self.skip_line_end()?;
+                    self.parse_block(parent)?
+                } else {
+                    let column = self.index - self.line_start;
+                    self.parse_node_at(column, parent)?
+                };
+                node.position = position;
+                self.anchor(name, &node);
+                return Ok(node);
+            }
+            Some(b'|') | Some(b'>') => {
+                let s = self.parse_block_scalar(parent)?;
+                return Ok(Node {
+                    kind: NodeKind::Quoted(s),
+                    position,
+                });
+            }
+            Some(b'[') | Some(b'{') | Some(b'*') | Some(b'"') | Some(b'\'') => {
+                self.parse_flow_node()?
+            }
+            _ => {
+                let start = self.index;
+                loop {
+                    match self.peek() {
+                        None | Some(b'\n') | Some(b'\r') => break,
+                        // A comment starts with a `#` after a space.
+                        Some(b'#') if matches!(self.bytes[self.index - 1], b' ' | b'\t') => break,
+                        _ => self.advance(),
+                    }
+                }
+                self.plain(start, position)?
+            }
+        };
+        self.skip_line_end()?;
+        Ok(node)
+    }
+
+    fn plain(&mut self, start: usize, position: Position) -> Result<Node, ParseError> {
+        let src = self.src;
+        let s = src[start..self.index].trim();
+        let limit = self.limits.check_str_len(s.len());
+        self.check_limit(limit)?;
+        Ok(Node {
+            kind: NodeKind::Plain(s.to_owned()),
+            position,
+        })
+    }
+
+    // The count is taken once, so that aliases are charged without walking
+    // the node each time.
+    fn anchor(&mut self, name: String, node: &Node) {
+        self.anchors.insert(name, (node.clone(), node.count()));
+    }
+
+    fn parse_anchor_name(&mut self) -> Result<String, ParseError> {
+        let start = self.index;
+        while !is_blank_or_end(self.peek())
+            && !matches!(
+                self.peek(),
+                Some(b',') | Some(b'[') | Some(b']') | Some(b'{') | Some(b'}')
+            )
+        {
+            self.advance();
+        }
+        match self.index > start {
+            true => Ok(self.src[start..self.index].to_owned()),
+            false => Err(self.unexpected()),
+        }
+    }
+
+    // Skips whitespace, line breaks and comments inside flow collections.
+    fn skip_flow_whitespace(&mut self) {
+        loop {
+            match self.peek() {
+                Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') => self.advance(),
+                Some(b'#') => {
+                    while !matches!(self.peek(), None | Some(b'\n')) {
+                        self.advance();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn parse_flow_node(&mut self) -> Result<Node, ParseError> {
+        self.skip_flow_whitespace();
+        let position = self.position();
+        let kind = match self.peek() {
+            Some(b'[') => {
+                self.advance();
+                self.enter()?;
+                let mut elements = Vec::new();
+                loop {
+                    self.skip_flow_whitespace();
+                    if self.peek() == Some(b']') {
+                        break;
+                    }
+                    elements.push(self.parse_flow_node()?);
+                    self.skip_flow_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.advance(),
+                        Some(b']') => break,
+                        _ => return Err(self.unexpected()),
+                    }
+                }
+                self.advance();
+                self.leave();
+                let limit = self.limits.check_collection_len(elements.len());
+                self.check_limit(limit)?;
+                NodeKind::Seq(elements)
+            }
+            Some(b'{') => {
+                self.advance();
+                self.enter()?;
+                let mut entries = Vec::new();
+                loop {
+                    self.skip_flow_whitespace();
+                    if self.peek() == Some(b'}') {
+                        break;
+                    }
+                    let key = match self.parse_flow_node()?.kind {
+                        NodeKind::Plain(key) | NodeKind::Quoted(key) => key,
+                        _ => return Err(self.unexpected()),
+                    };
+                    self.skip_flow_whitespace();
+                    let value = if self.peek() == Some(b':') {
+                        self.advance();
+                        self.skip_flow_whitespace();
+                        match self.peek() {
+                            Some(b',') | Some(b'}') => self.null(),
+                            _ => self.parse_flow_node()?,
+                        }
+                    } else {
+                        self.null()
+                    };
+                    entries.push((key, value));
+                    self.skip_flow_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.advance(),
+                        Some(b'}') => break,
+                        _ => return Err(self.unexpected()),
+                    }
+                }
+                self.advance();
+                self.leave();
+                let limit = self.limits.check_collection_len(entries.len());
+                self.check_limit(limit)?;
+                NodeKind::Map(entries)
+            }
+            Some(b'"') | Some(b'\'') => NodeKind::Quoted(self.parse_quoted()?),
+            Some(b'*') => {
+                self.advance();
+                let name = self.parse_anchor_name()?;
+                let count = match self.anchors.get(&name) {
+                    Some((_, count)) => *count,
+                    None => {
+                        return Err(self.error_at(ParseErrorKind::UnknownAnchor(name), position))
+                    }
+                };
+                // Aliases are copies, whose memory counts against the limits
+                // before they're made to stop a few aliases from expanding
+                // into a huge value.
+                let size = count.saturating_mul(mem::size_of::<Node>());
+                let limit = self.limits.check_collection_len(size);
+                self.check_limit(limit)?;
+                let node = self.anchors[&name].0.clone();
+                return Ok(Node { position, ..node });
+            }
+            Some(b'&') => {
+                self.advance();
+                let name = self.parse_anchor_name()?;
+                let node = self.parse_flow_node()?;
+                self.anchor(name, &node);
+                return Ok(Node { position, ..node });
+            }
+            _ => {
+                // Plain scalars end at an indicator of the flow collection or a
+                // `: `.
+                let start = self.index;
+                loop {
+                    match self.peek() {
+                        None | Some(b'\n') | Some(b'\r') | Some(b',') | Some(b']') | Some(b'}')
+                        | Some(b'[') | Some(b'{') => break,
+                        Some(b':')
+                            if is_blank_or_end(self.peek_at(1))
+                                || matches!(
+                                    self.peek_at(1),
+                                    Some(b',') | Some(b']') | Some(b'}')
+                                ) =>
+                        {
+                            break
+                        }
+                        Some(b'#') if self.index > start && self.bytes[self.index - 1] == b' ' => {
+                            break
+                        }
+                        _ => self.advance(),
+                    }
+                }
+                if self.index == start {
+                    return Err(self.unexpected());
+                }
+                return self.plain(start, position);
+            }
+        };
+        Ok(Node { kind, position })
+    }
+
+    // Line breaks in quoted scalars are folded: a single one is a space,
+    // following ones are kept.
+    fn fold_line_break(&mut self, s: &mut String) {
+        let trimmed = s.trim_end_matches(&[' ', '\t'][..]).len();
+        s.truncate(trimmed);
+        let mut breaks = 0;
+        while let Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') = self.peek() {
+            if self.peek() == Some(b'\n') {
+                breaks += 1;
+            }
+            self.advance();
+        }
+        match breaks {
+            1 => s.push(' '),
+            _ => s.push_str(&"\n".repeat(breaks - 1)),
+        }
+    }
+
+    fn parse_quoted(&mut self) -> Result<String, ParseError> {
+        let quote = self.peek().unwrap();
+        self.advance();
+        let mut s = String::new();
+        let mut start = self.index;
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                Some(b'\'') if quote == b'\'' && self.peek_at(1) == Some(b'\'') => {
+                    s.push_str(&self.src[start..self.index]);
+                    s.push('\'');
+                    self.advance_by(2);
+                    start = self.index;
+                }
+                Some(byte) if byte == quote => break,
+                Some(b'\\') if quote == b'"' => {
+                    s.push_str(&self.src[start..self.index]);
+                    self.advance();
+                    if self.peek() == Some(b'\n') || self.src[self.index..].starts_with("\r\n") {
+                        // An escaped line break joins the lines.
+                        self.skip_line_end()?;
+                        self.skip_spaces();
+                    } else {
+                        s.push(self.parse_escape()?);
+                    }
+                    start = self.index;
+                }
+                Some(b'\n') => {
+                    s.push_str(&self.src[start..self.index]);
+                    self.fold_line_break(&mut s);
+                    start = self.index;
+                }
+                Some(_) => self.advance(),
+            }
+        }
+        s.push_str(&self.src[start..self.index]);
+        self.advance();
+        let limit = self.limits.check_str_len(s.len());
+        self.check_limit(limit)?;
+        Ok(s)
+    }
+
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let hex_len = match self.peek() {
+            Some(b'x') => 2,
+            Some(b'u') => 4,
+            Some(b'U') => 8,
+            _ => 0,
+        };
+        if hex_len > 0 {
+            let c = self
+                .src
+                .get((self.index + 1)..(self.index + 1 + hex_len))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| self.error(ParseErrorKind::InvalidEscape))?;
+            self.advance_by(1 + hex_len);
+            return Ok(c);
+        }
+        let c = match self.peek() {
+            Some(b'0') => '\0',
+            Some(b'a') => '\x07',
+            Some(b'b') => '\x08',
+            Some(b't') | Some(b'\t') => '\t',
+            Some(b'n') => '\n',
+            Some(b'v') => '\x0b',
+            Some(b'f') => '\x0c',
+            Some(b'r') => '\r',
+            Some(b'e') => '\x1b',
+            Some(b' ') => ' ',
+            Some(b'"') => '"',
+            Some(b'/') => '/',
+            Some(b'\\') => '\\',
+            Some(b'N') => '\u{85}',
+            Some(b'_') => '\u{a0}',
+            Some(b'L') => '\u{2028}',
+            Some(b'P') => '\u{2029}',
+            _ => return Err(self.error(ParseErrorKind::InvalidEscape)),
+        };
+        self.advance();
+        Ok(c)
+    }
+
+    // `|` keeps line breaks, `>` folds them. Either can be followed by a
+    // chomping indicator (`-` strips the final line breaks, `+` keeps them
+    // all) and an indentation indicator.
+    fn parse_block_scalar(&mut self, parent: isize) -> Result<String, ParseError> {
+        let literal = self.peek() == Some(b'|');
+        self.advance();
+        let mut chomping = Chomping::Clip;
+        let mut indent = None;
+        for _ in 0..2 {
+            match self.peek() {
+                Some(b'-') => chomping = Chomping::Strip,
+                Some(b'+') => chomping = Chomping::Keep,
+                Some(digit @ b'1'..=b'9') => {
+                    indent = Some(parent.max(0) as usize + (digit - b'0') as usize)
+                }
+                _ => break,
+            }
+            self.advance();
+        }
+        self.skip_line_end()?;
+
+        let src = self.src;
+        let mut lines: Vec<&str> = Vec::new();
+        while self.peek().is_some() {
+            let line_end = src[self.index..]
+                .find('\n')
+                .map_or(src.len(), |i| self.index + i);
+            let line = src[self.index..line_end].trim_end_matches('\r');
+            let spaces = line.bytes().take_while(|byte| *byte == b' ').count();
+            if spaces == line.len() {
+                lines.push("");
+            } else {
+                let indent = *indent.get_or_insert(spaces);
+                if spaces < indent || spaces as isize <= parent {
+                    break;
+                }
+                lines.push(&line[indent..]);
+            }
+            self.advance_by(line_end - self.index);
+            if self.peek().is_some() {
+                self.advance();
+            }
+        }
+
+        let trailing = lines
+            .iter()
+            .rev()
+            .take_while(|line| line.is_empty())
+            .count();
+        let content = &lines[..(lines.len() - trailing)];
+        let mut s = String::new();
+        if literal {
+            s = content.join("\n");
+        } else {
+            let mut breaks = 0;
+            for (i, line) in content.iter().enumerate() {
+                if line.is_empty() {
+                    breaks += 1;
+                    continue;
+                }
+                if i > 0 {
+                    let more_indented = line.starts_with(' ') || content[i - 1].starts_with(' ');
+                    match breaks {
+                        0 if more_indented => s.push('\n'),
+                        0 => s.push(' '),
+                        _ => s.push_str(&"\n".repeat(breaks)),
+                    }
+                }
+                s.push_str(line);
+                breaks = 0;
+            }
+        }
+        if !content.is_empty() {
+            match chomping {
+                Chomping::Strip => {}
+                Chomping::Clip => s.push('\n'),
+                Chomping::Keep => s.push_str(&"\n".repeat(trailing + 1)),
+            }
+        }
+        let limit = self.limits.check_str_len(s.len());
+        self.check_limit(limit)?;
+        Ok(s)
+    }
+}
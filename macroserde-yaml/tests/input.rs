@@ -0,0 +1,130 @@
+// Handwritten YAML: block and flow styles, scalars, anchors and aliases.
+
+use fixtures::{contacts, person, Contact, Date, Month, Person};
+use macroserde::de::*;
+use macroserde_yaml::ParseErrorKind;
+
+fn decode<T: Deserialize>(s: &str) -> Result<T, DeserializeError> {
+    T::deserialize(&mut macroserde_yaml::Deserializer::new(s).unwrap())
+}
+
+fn parse_error(s: &str, limits: DeserializeLimits) -> ParseErrorKind {
+    match macroserde_yaml::Deserializer::with_limits(s, limits) {
+        Ok(_) => panic!("{:?} parsed", s),
+        Err(e) => e.kind,
+    }
+}
+
+#[test]
+fn block_style() {
+    let yaml = r#"
+%YAML 1.2
+---
+# The person of the README.
+name: Steven
+2: 27
+3:birth_date:
+  day: 19
+  month: OCT
+  year: 1993
+pets:
+  - Bouboul
+  - "Monsieur Puppy"
+height: 1.735
+carBrand: ~
+IsCool: true
+occupation:
+  hasJob: 'Engineer' # A quoted scalar.
+...
+ignored: document
+"#;
+    assert_eq!(decode::<Person>(yaml).unwrap(), person());
+}
+
+#[test]
+fn flow_style() {
+    let yaml = r#"{name: Steven, age: 27, birth_date: {day: 19, month: 10, year: 1993},
+  pets: [Bouboul, "Monsieur Puppy"], height: 1.735, carBrand: null, IsCool: true,
+  occupation: {hasJob: Engineer}}"#;
+    assert_eq!(decode::<Person>(yaml).unwrap(), person());
+
+    let yaml = r#"
+- {name: Steven, birth_date: {day: 19, month: OCT, year: 1993}, carBrand: ""}
+- name: ""
+  birth_date: null
+"#;
+    assert_eq!(decode::<Vec<Contact>>(yaml).unwrap(), contacts());
+}
+
+#[test]
+fn block_scalars() {
+    let yaml = "name: |\n  Steven\n  Le Rouzic\ncarBrand: >\n  Renault\n  Twingo\n";
+    let contact = decode::<Contact>(yaml).unwrap();
+    assert_eq!(contact.name, "Steven\nLe Rouzic\n");
+    assert_eq!(contact.car_brand.as_deref(), Some("Renault Twingo\n"));
+}
+
+#[test]
+fn anchors() {
+    let yaml = r#"
+- name: Steven
+  birth_date: &birthday {day: 19, month: OCT, year: 1993}
+  carBrand: &empty ""
+- name: *empty
+  birth_date: *birthday
+"#;
+    let contacts = decode::<Vec<Contact>>(yaml).unwrap();
+    let birthday = Date {
+        day: 19,
+        month: Month::October,
+        year: 1993,
+    };
+    assert_eq!(contacts[1].name, "");
+    assert_eq!(contacts[1].birth_date, Some(birthday));
+
+    let yaml = "pets: &pets\n  - Bouboul\nname: *pets\n";
+    assert!(decode::<Person>(yaml).is_err());
+    assert_eq!(
+        parse_error("name: *nobody\n", DeserializeLimits::default()),
+        ParseErrorKind::UnknownAnchor("nobody".to_owned())
+    );
+}
+
+#[test]
+fn billion_laughs() {
+    let mut yaml = "a: &a [lol, lol, lol, lol, lol, lol, lol, lol, lol]\n".to_owned();
+    for (name, alias) in "bcdefghi".chars().zip("abcdefgh".chars()) {
+        yaml.push_str(&format!("{0}: &{0} [", name));
+        yaml.push_str(&vec![format!("*{}", alias); 9].join(", "));
+        yaml.push_str("]\n");
+    }
+    assert!(matches!(
+        parse_error(&yaml, DeserializeLimits::default()),
+        ParseErrorKind::LimitExceeded(_)
+    ));
+
+    // Aliases are charged for the size of their copy, not once per alias.
+    let limits = DeserializeLimits {
+        max_total_alloc: 64 * 1024,
+        ..DeserializeLimits::default()
+    };
+    let mut yaml = "a: &a [lol, lol, lol, lol, lol, lol, lol, lol, lol]\nb: [".to_owned();
+    yaml.push_str(&vec!["*a"; 200].join(", "));
+    yaml.push_str("]\n");
+    assert_eq!(
+        parse_error(&yaml, limits),
+        ParseErrorKind::LimitExceeded(Limit::TotalAlloc)
+    );
+}
+
+#[test]
+fn limits() {
+    let limits = DeserializeLimits {
+        max_str_len: 4,
+        ..DeserializeLimits::default()
+    };
+    assert_eq!(
+        parse_error("name: \"Steven\"\n", limits),
+        ParseErrorKind::LimitExceeded(Limit::StrLen)
+    );
+}
@@ -0,0 +1,45 @@
+// Round trips of the README types.
+
+use fixtures::{contacts, person, Contact, Occupation, Person};
+use macroserde::de::*;
+use macroserde::ser::*;
+use macroserde_yaml::KeyRepr;
+use std::fmt::Debug;
+
+fn check<T: Serialize + Deserialize + Debug + PartialEq>(value: T) {
+    for &key_repr in &[KeyRepr::Name, KeyRepr::IdAndName, KeyRepr::Id] {
+        let mut ser = macroserde_yaml::Serializer::new().with_key_repr(key_repr);
+        value.serialize(&mut ser).unwrap();
+        let mut out = Vec::new();
+        ser.write(&mut out).unwrap();
+        let encoded = String::from_utf8(out).unwrap();
+        let decoded = T::deserialize(&mut macroserde_yaml::Deserializer::new(&encoded).unwrap());
+        assert_eq!(decoded.unwrap(), value, "decoding {}", encoded);
+    }
+}
+
+#[test]
+fn readme_person() {
+    check(person());
+}
+
+#[test]
+fn unit_variant() {
+    check(Person {
+        occupation: Occupation::Unemployed,
+        ..person()
+    });
+}
+
+#[test]
+fn empty_seq() {
+    check(Person {
+        pets: Vec::new(),
+        ..person()
+    });
+}
+
+#[test]
+fn options() {
+    check::<Vec<Contact>>(contacts());
+}
@@ -0,0 +1,97 @@
+use crate::de::{Deserialize, DeserializeError, Hint, SeqBuilder, Visitor};
+use crate::make_place_type;
+use crate::ser::{Serialize, SerializeError, Serializer};
+use std::ops::{Deref, DerefMut};
+
+make_place_type!(Place);
+
+// `Vec<u8>` is a sequence of integers, this is a byte string (e.g. msgpack bin).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Bytes(pub Vec<u8>);
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(bytes: &[u8]) -> Self {
+        Bytes(bytes.to_owned())
+    }
+}
+
+impl Deref for Bytes {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl DerefMut for Bytes {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize(&self, serializer: &mut dyn Serializer) -> Result<(), SerializeError> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+struct BytesBuilder<'a> {
+    out: &'a mut Option<Bytes>,
+    bytes: Vec<u8>,
+    byte: Option<u8>,
+}
+
+impl<'a> BytesBuilder<'a> {
+    fn shift(&mut self) {
+        if let Some(byte) = self.byte.take() {
+            self.bytes.push(byte);
+        }
+    }
+}
+
+impl<'a> SeqBuilder for BytesBuilder<'a> {
+    fn element(&mut self) -> Result<&mut dyn Visitor, DeserializeError> {
+        self.shift();
+        Ok(u8::begin_deserialize(&mut self.byte))
+    }
+
+    fn finish(&mut self) -> Result<(), DeserializeError> {
+        self.shift();
+        self.out.replace(Bytes(std::mem::take(&mut self.bytes)));
+        Ok(())
+    }
+}
+
+impl Visitor for Place<Bytes> {
+    fn hint(&self) -> Hint {
+        Hint::Bytes
+    }
+
+    fn visit_bytes(&mut self, value: &[u8]) -> Result<(), DeserializeError> {
+        self.out.replace(Bytes(value.to_owned()));
+        Ok(())
+    }
+
+    fn visit_seq<'a>(
+        &'a mut self,
+        size_hint: Option<usize>,
+    ) -> Result<Box<dyn SeqBuilder + 'a>, DeserializeError> {
+        Ok(Box::new(BytesBuilder {
+            out: &mut self.out,
+            bytes: Vec::with_capacity(size_hint.unwrap_or(0).min(4096)),
+            byte: None,
+        }))
+    }
+}
+
+impl Deserialize for Bytes {
+    fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
+        Place::new(out)
+    }
+}
@@ -0,0 +1,77 @@
+use super::{DeserializeError, Visitor};
+use crate::ext::Timestamp;
+
+// What a visitor expects to visit next. Self-describing formats ignore it,
+// formats that don't say which type a value has read the value it describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hint {
+    // The visitor takes any value (e.g. `NullVisitor`).
+    Any,
+    Null,
+    Bool,
+    Signed,
+    Unsigned,
+    Float,
+    Str,
+    Bytes,
+    // Null, or a value visited through `Visitor::visit_some`.
+    Option,
+    Enum,
+    // A sequence, of this many elements when its length is fixed.
+    Seq(Option<usize>),
+    // A struct with the fields of these IDs.
+    Struct(&'static [u32]),
+    // A struct with a single field, one of the variants of these IDs.
+    Union(&'static [u32]),
+    Ext,
+    Timestamp,
+}
+
+// Visits a value read as text by formats where everything is a string (CSV,
+// query strings, environment variables...), converting it to what the visitor
+// expects.
+pub fn visit_hinted_str(visitor: &mut dyn Visitor, value: &str) -> Result<(), DeserializeError> {
+    match visitor.hint() {
+        Hint::Null if value.is_empty() => visitor.visit_null(),
+        Hint::Option if value.is_empty() => visitor.visit_null(),
+        Hint::Option => visit_hinted_str(visitor.visit_some()?, value),
+        Hint::Bool => match value {
+            "true" => visitor.visit_bool(true),
+            "false" => visitor.visit_bool(false),
+            _ => Err(DeserializeError::ParsingError),
+        },
+        Hint::Signed => visitor.visit_signed(parse(value)?),
+        Hint::Unsigned => visitor.visit_unsigned(parse(value)?),
+        Hint::Float => visitor.visit_float(parse(value)?),
+        Hint::Enum => match value.parse() {
+            Ok(id) => visitor.visit_enum(Some(id), None),
+            Err(_) => visitor.visit_enum(None, Some(value)),
+        },
+        Hint::Bytes => visitor.visit_bytes(value.as_bytes()),
+        Hint::Timestamp => match Timestamp::parse_rfc3339(value) {
+            Some(timestamp) => visitor.visit_timestamp(timestamp),
+            None => Err(DeserializeError::ParsingError),
+        },
+        _ => visitor.visit_str(value),
+    }
+}
+
+// Splits a struct key or enumeration written as `id:name`, a bare ID or a bare
+// name, as text formats write them.
+pub fn split_key(key: &str) -> (Option<u32>, Option<&str>) {
+    if let Some(sep) = key.find(':') {
+        if let Ok(id) = key[..sep].parse::<u32>() {
+            return (Some(id), Some(&key[(sep + 1)..]));
+        }
+    }
+    if !key.is_empty() && key.bytes().all(|byte| byte.is_ascii_digit()) {
+        if let Ok(id) = key.parse::<u32>() {
+            return (Some(id), None);
+        }
+    }
+    (None, Some(key))
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, DeserializeError> {
+    value.parse().map_err(|_| DeserializeError::ParsingError)
+}
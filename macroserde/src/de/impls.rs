@@ -1,5 +1,7 @@
-use super::{Deserialize, DeserializeError, SeqBuilder, StructBuilder, Visitor};
+use super::{Deserialize, DeserializeError, Hint, Position, SeqBuilder, StructBuilder, Visitor};
+use crate::ext::Timestamp;
 use crate::make_place_type;
+use std::convert::TryInto;
 
 make_place_type!(Place);
 
@@ -8,6 +10,10 @@ macro_rules! deserialize_signed {
         impl Deserialize for $type {
             fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
                 impl Visitor for Place<$type> {
+                    fn hint(&self) -> Hint {
+                        Hint::Signed
+                    }
+
                     fn visit_signed(&mut self, value: i64) -> Result<(), DeserializeError> {
                         if value < $min as i64 || value > $max as i64 {
                             return Err(DeserializeError::IncompatibleNumericType);
@@ -57,8 +63,12 @@ macro_rules! deserialize_unsigned {
         impl Deserialize for $type {
             fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
                 impl Visitor for Place<$type> {
+                    fn hint(&self) -> Hint {
+                        Hint::Unsigned
+                    }
+
                     fn visit_signed(&mut self, value: i64) -> Result<(), DeserializeError> {
-                        if value < 0 || value > $max as i64 {
+                        if value < 0 || value as u64 > $max as u64 {
                             return Err(DeserializeError::IncompatibleNumericType);
                         } else {
                             self.out.replace(value as $type);
@@ -106,6 +116,10 @@ macro_rules! deserialize_float {
         impl Deserialize for $type {
             fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
                 impl Visitor for Place<$type> {
+                    fn hint(&self) -> Hint {
+                        Hint::Float
+                    }
+
                     fn visit_signed(&mut self, value: i64) -> Result<(), DeserializeError> {
                         self.out.replace(value as $type);
                         Ok(())
@@ -138,6 +152,14 @@ deserialize_float!(f64);
 impl<T: Deserialize> Deserialize for Option<T> {
     fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
         impl<T: Deserialize> Visitor for Place<Option<T>> {
+            fn hint(&self) -> Hint {
+                Hint::Option
+            }
+
+            fn visit_some(&mut self) -> Result<&mut dyn Visitor, DeserializeError> {
+                Ok(T::begin_deserialize(self.out.insert(None)))
+            }
+
             fn visit_null(&mut self) -> Result<(), DeserializeError> {
                 self.out.replace(None);
                 Ok(())
@@ -177,6 +199,38 @@ impl<T: Deserialize> Deserialize for Option<T> {
                 self.out.replace(place);
                 Ok(())
             }
+
+            fn visit_enum(
+                &mut self,
+                id: Option<u32>,
+                name: Option<&str>,
+            ) -> Result<(), DeserializeError> {
+                let mut place = None;
+                T::begin_deserialize(&mut place).visit_enum(id, name)?;
+                self.out.replace(place);
+                Ok(())
+            }
+
+            fn visit_bytes(&mut self, value: &[u8]) -> Result<(), DeserializeError> {
+                let mut place = None;
+                T::begin_deserialize(&mut place).visit_bytes(value)?;
+                self.out.replace(place);
+                Ok(())
+            }
+
+            fn visit_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), DeserializeError> {
+                let mut place = None;
+                T::begin_deserialize(&mut place).visit_ext(type_id, data)?;
+                self.out.replace(place);
+                Ok(())
+            }
+
+            fn visit_timestamp(&mut self, value: Timestamp) -> Result<(), DeserializeError> {
+                let mut place = None;
+                T::begin_deserialize(&mut place).visit_timestamp(value)?;
+                self.out.replace(place);
+                Ok(())
+            }
         }
 
         return Place::new(out);
@@ -186,6 +240,10 @@ impl<T: Deserialize> Deserialize for Option<T> {
 impl Deserialize for bool {
     fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
         impl Visitor for Place<bool> {
+            fn hint(&self) -> Hint {
+                Hint::Bool
+            }
+
             fn visit_signed(&mut self, value: i64) -> Result<(), DeserializeError> {
                 self.out.replace(value != 0);
                 Ok(())
@@ -213,6 +271,10 @@ impl Deserialize for bool {
 impl Deserialize for String {
     fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
         impl Visitor for Place<String> {
+            fn hint(&self) -> Hint {
+                Hint::Str
+            }
+
             fn visit_str(&mut self, value: &str) -> Result<(), DeserializeError> {
                 self.out.replace(value.to_owned());
                 Ok(())
@@ -261,6 +323,10 @@ impl<T: Deserialize> Deserialize for Vec<T> {
         }
 
         impl<T: Deserialize> Visitor for Place<Vec<T>> {
+            fn hint(&self) -> Hint {
+                Hint::Seq(None)
+            }
+
             fn visit_seq(
                 &mut self,
                 _size_hint: Option<usize>,
@@ -272,9 +338,70 @@ impl<T: Deserialize> Deserialize for Vec<T> {
     }
 }
 
+// Unlike the other impls these are at module level: nested in the generic
+// `begin_deserialize`, the impl for `Place<[T; N]>` would be non-local.
+struct ArrayBuilder<'a, T, const N: usize> {
+    out: &'a mut Option<[T; N]>,
+    vec: Vec<T>,
+    elmt: Option<T>,
+}
+
+impl<'a, T, const N: usize> ArrayBuilder<'a, T, N> {
+    fn shift(&mut self) {
+        if let Some(e) = self.elmt.take() {
+            self.vec.push(e);
+        }
+    }
+}
+
+impl<'a, T: Deserialize, const N: usize> SeqBuilder for ArrayBuilder<'a, T, N> {
+    fn element(&mut self) -> Result<&mut dyn Visitor, DeserializeError> {
+        self.shift();
+        if self.vec.len() == N {
+            return Err(DeserializeError::ParsingError);
+        }
+        Ok(T::begin_deserialize(&mut self.elmt))
+    }
+
+    fn finish(&mut self) -> Result<(), DeserializeError> {
+        self.shift();
+        let vec = std::mem::take(&mut self.vec);
+        let array = vec.try_into().map_err(|_| DeserializeError::ParsingError)?;
+        self.out.replace(array);
+        Ok(())
+    }
+}
+
+impl<T: Deserialize, const N: usize> Visitor for Place<[T; N]> {
+    fn hint(&self) -> Hint {
+        Hint::Seq(Some(N))
+    }
+
+    fn visit_seq(
+        &mut self,
+        _size_hint: Option<usize>,
+    ) -> Result<Box<dyn SeqBuilder + '_>, DeserializeError> {
+        Ok(Box::new(ArrayBuilder {
+            out: &mut self.out,
+            vec: Vec::with_capacity(N),
+            elmt: None,
+        }))
+    }
+}
+
+impl<T: Deserialize, const N: usize> Deserialize for [T; N] {
+    fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
+        Place::new(out)
+    }
+}
+
 impl Deserialize for () {
     fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
         impl Visitor for Place<()> {
+            fn hint(&self) -> Hint {
+                Hint::Null
+            }
+
             fn visit_null(&mut self) -> Result<(), DeserializeError> {
                 self.out.replace(());
                 Ok(())
@@ -290,6 +417,24 @@ impl From<std::io::Error> for DeserializeError {
     }
 }
 
+impl DeserializeError {
+    // Attaches the source position of the offending value, errors coming from
+    // nested values keep their own, more precise, position.
+    pub fn at(self, position: Position) -> DeserializeError {
+        match self {
+            DeserializeError::AtPosition(..) => self,
+            _ => DeserializeError::AtPosition(Box::new(self), position),
+        }
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            DeserializeError::AtPosition(_, position) => Some(*position),
+            _ => None,
+        }
+    }
+}
+
 pub struct NullVisitor;
 
 pub struct NullStructBuilder {
@@ -349,6 +494,26 @@ impl Visitor for NullVisitor {
         Ok(())
     }
 
+    fn visit_enum(
+        &mut self,
+        _id: Option<u32>,
+        _name: Option<&str>,
+    ) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    fn visit_bytes(&mut self, _value: &[u8]) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    fn visit_ext(&mut self, _type_id: i8, _data: &[u8]) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
+    fn visit_timestamp(&mut self, _value: Timestamp) -> Result<(), DeserializeError> {
+        Ok(())
+    }
+
     fn visit_seq<'a>(
         &'a mut self,
         _size_hint: Option<usize>,
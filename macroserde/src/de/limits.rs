@@ -0,0 +1,109 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Depth,
+    StrLen,
+    CollectionLen,
+    TotalAlloc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializeLimits {
+    // Nesting of sequences and structs.
+    pub max_depth: usize,
+    // Length in bytes of a single string, bytes or extension value.
+    pub max_str_len: usize,
+    // Number of elements of a single sequence or members of a single struct.
+    pub max_collection_len: usize,
+    // Sum of all the string lengths and collection lengths of the input, which
+    // bounds what deserializing it allocates.
+    pub max_total_alloc: usize,
+}
+
+impl DeserializeLimits {
+    pub fn unlimited() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_str_len: usize::MAX,
+            max_collection_len: usize::MAX,
+            max_total_alloc: usize::MAX,
+        }
+    }
+}
+
+impl Default for DeserializeLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_str_len: 16 * 1024 * 1024,
+            max_collection_len: 16 * 1024 * 1024,
+            max_total_alloc: 256 * 1024 * 1024,
+        }
+    }
+}
+
+pub struct LimitTracker {
+    limits: DeserializeLimits,
+    depth: usize,
+    total_alloc: usize,
+}
+
+impl LimitTracker {
+    pub fn new(limits: DeserializeLimits) -> Self {
+        Self {
+            limits,
+            depth: 0,
+            total_alloc: 0,
+        }
+    }
+
+    pub fn enter(&mut self) -> Result<(), Limit> {
+        if self.depth >= self.limits.max_depth {
+            return Err(Limit::Depth);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    pub fn check_str_len(&mut self, len: usize) -> Result<(), Limit> {
+        if len > self.limits.max_str_len {
+            return Err(Limit::StrLen);
+        }
+        self.alloc(len)
+    }
+
+    pub fn check_collection_len(&mut self, len: usize) -> Result<(), Limit> {
+        if len > self.limits.max_collection_len {
+            return Err(Limit::CollectionLen);
+        }
+        self.alloc(len)
+    }
+
+    // For inputs that don't give lengths upfront: checks the running length of
+    // a string that grows by `added` bytes, before they're allocated.
+    pub fn check_str_growth(&mut self, len: usize, added: usize) -> Result<(), Limit> {
+        if len > self.limits.max_str_len {
+            return Err(Limit::StrLen);
+        }
+        self.alloc(added)
+    }
+
+    // Same for a collection, called with its running length for each element.
+    pub fn check_collection_growth(&mut self, len: usize) -> Result<(), Limit> {
+        if len > self.limits.max_collection_len {
+            return Err(Limit::CollectionLen);
+        }
+        self.alloc(1)
+    }
+
+    fn alloc(&mut self, len: usize) -> Result<(), Limit> {
+        self.total_alloc = self.total_alloc.saturating_add(len);
+        if self.total_alloc > self.limits.max_total_alloc {
+            return Err(Limit::TotalAlloc);
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,16 @@
+mod hint;
 mod impls;
+mod limits;
 mod place;
+mod read;
 mod traits;
 
+pub use hint::{split_key, visit_hinted_str, Hint};
 pub use impls::NullVisitor;
-pub use traits::{Deserialize, DeserializeError, Deserializer, SeqBuilder, StructBuilder, Visitor};
+pub use limits::{DeserializeLimits, Limit, LimitTracker};
+pub use read::{read_bounded, MAX_TRUSTED_LEN};
+pub use traits::{
+    Deserialize, DeserializeError, Deserializer, Position, SeqBuilder, StructBuilder, Visitor,
+};
 
 crate::make_place_type!(pub Place);
@@ -0,0 +1,23 @@
+use super::DeserializeError;
+use std::io::Read;
+
+// Lengths read from the input up to this are allocated upfront.
+pub const MAX_TRUSTED_LEN: usize = 64 * 1024;
+
+// Appends `len` bytes of `read` to `buffer`. Above `MAX_TRUSTED_LEN`, the
+// buffer grows as the data actually arrives instead of trusting a large length
+// read from the input.
+pub fn read_bounded<R: Read>(
+    read: &mut R,
+    len: usize,
+    buffer: &mut Vec<u8>,
+) -> Result<(), DeserializeError> {
+    if len <= MAX_TRUSTED_LEN {
+        let start = buffer.len();
+        buffer.resize(start + len, 0);
+        read.read_exact(&mut buffer[start..])?;
+    } else if read.take(len as u64).read_to_end(buffer)? != len {
+        return Err(DeserializeError::UnexpectedEof);
+    }
+    Ok(())
+}
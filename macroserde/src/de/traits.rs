@@ -1,5 +1,13 @@
+use super::{Hint, Limit};
+use crate::ext::Timestamp;
 use std::io;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
 #[derive(Debug)]
 pub enum DeserializeError {
     UnknownError,
@@ -12,6 +20,12 @@ pub enum DeserializeError {
     MissingField(&'static str),
     UnknownField,
     IoError(io::Error),
+    UnknownExtType(i8),
+    InvalidExtData,
+    LimitExceeded(Limit),
+    // The format needs to know what to read and the visitor gave no hint.
+    MissingHint,
+    AtPosition(Box<DeserializeError>, Position),
 }
 
 pub trait SeqBuilder {
@@ -29,6 +43,10 @@ pub trait StructBuilder {
 }
 
 pub trait Visitor {
+    fn hint(&self) -> Hint {
+        Hint::Any
+    }
+
     fn visit_null(&mut self) -> Result<(), DeserializeError> {
         Err(DeserializeError::UnimplementedVisit)
     }
@@ -53,6 +71,36 @@ pub trait Visitor {
         Err(DeserializeError::UnimplementedVisit)
     }
 
+    // Visitors that aren't enumerations leave this unimplemented, deserializers
+    // then fall back to the plain visit of the value.
+    fn visit_enum(
+        &mut self,
+        _id: Option<u32>,
+        _name: Option<&str>,
+    ) -> Result<(), DeserializeError> {
+        Err(DeserializeError::UnimplementedVisit)
+    }
+
+    // Deserializers fall back to a sequence of unsigned integers when this is
+    // unimplemented.
+    fn visit_bytes(&mut self, _value: &[u8]) -> Result<(), DeserializeError> {
+        Err(DeserializeError::UnimplementedVisit)
+    }
+
+    fn visit_ext(&mut self, _type_id: i8, _data: &[u8]) -> Result<(), DeserializeError> {
+        Err(DeserializeError::UnimplementedVisit)
+    }
+
+    fn visit_timestamp(&mut self, _value: Timestamp) -> Result<(), DeserializeError> {
+        Err(DeserializeError::UnimplementedVisit)
+    }
+
+    // Formats that mark present optional values read the value with the
+    // returned visitor, others visit it directly.
+    fn visit_some(&mut self) -> Result<&mut dyn Visitor, DeserializeError> {
+        Err(DeserializeError::UnimplementedVisit)
+    }
+
     fn visit_seq<'a>(
         &'a mut self,
         _size_hint: Option<usize>,
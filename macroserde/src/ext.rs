@@ -0,0 +1,389 @@
+use crate::de::{Deserialize, DeserializeError, Hint, StructBuilder, Visitor};
+use crate::make_place_type;
+use crate::ser::{Serialize, SerializeError, Serializer};
+use std::fmt;
+
+make_place_type!(Place);
+
+// Application specific extension types use IDs 0 to 127, negative IDs are
+// reserved by the formats (e.g. -1 for msgpack timestamps).
+pub trait ExtType: Sized {
+    const TYPE_ID: i8;
+
+    fn to_ext_data(&self) -> Vec<u8>;
+    fn from_ext_data(data: &[u8]) -> Result<Self, DeserializeError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Ext {
+    pub type_id: i8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Timestamp {
+    pub seconds: i64,
+    pub nanoseconds: u32,
+}
+
+// Formats without native extension values write them as a struct
+// `{ 1:type, 2:data }`, this reads it back.
+pub struct ExtStructBuilder<'a, T> {
+    out: &'a mut Option<T>,
+    type_id: Option<i8>,
+    data: Option<Vec<u8>>,
+    convert: fn(i8, &[u8]) -> Result<T, DeserializeError>,
+}
+
+impl<'a, T> ExtStructBuilder<'a, T> {
+    pub fn new(
+        out: &'a mut Option<T>,
+        convert: fn(i8, &[u8]) -> Result<T, DeserializeError>,
+    ) -> Self {
+        Self {
+            out,
+            type_id: None,
+            data: None,
+            convert,
+        }
+    }
+}
+
+impl<'a, T> StructBuilder for ExtStructBuilder<'a, T> {
+    fn member(
+        &mut self,
+        id: Option<u32>,
+        name: Option<&str>,
+    ) -> Result<&mut dyn Visitor, DeserializeError> {
+        match (id, name) {
+            (Some(1), _) | (None, Some("type")) => Ok(i8::begin_deserialize(&mut self.type_id)),
+            (Some(2), _) | (None, Some("data")) => Ok(Vec::begin_deserialize(&mut self.data)),
+            _ => Err(DeserializeError::UnknownField),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), DeserializeError> {
+        let type_id = self.type_id.ok_or(DeserializeError::MissingField("type"))?;
+        let data = self.data.take().unwrap_or_default();
+        self.out.replace((self.convert)(type_id, &data)?);
+        Ok(())
+    }
+}
+
+impl Serialize for Ext {
+    fn serialize(&self, serializer: &mut dyn Serializer) -> Result<(), SerializeError> {
+        serializer.serialize_ext(self.type_id, &self.data)
+    }
+}
+
+fn ext_from_data(type_id: i8, data: &[u8]) -> Result<Ext, DeserializeError> {
+    Ok(Ext {
+        type_id,
+        data: data.to_owned(),
+    })
+}
+
+impl Visitor for Place<Ext> {
+    fn hint(&self) -> Hint {
+        Hint::Ext
+    }
+
+    fn visit_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), DeserializeError> {
+        self.out.replace(ext_from_data(type_id, data)?);
+        Ok(())
+    }
+
+    fn visit_struct<'a>(&'a mut self) -> Result<Box<dyn StructBuilder + 'a>, DeserializeError> {
+        Ok(Box::new(ExtStructBuilder::new(
+            &mut self.out,
+            ext_from_data,
+        )))
+    }
+}
+
+impl Deserialize for Ext {
+    fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
+        Place::new(out)
+    }
+}
+
+#[macro_export]
+macro_rules! macroserde_ext {
+    ($name:ty) => {
+        $crate::const_assert!(<$name as $crate::ext::ExtType>::TYPE_ID >= 0);
+
+        impl $crate::ser::Serialize for $name {
+            fn serialize(&self, serializer: &mut dyn $crate::ser::Serializer) -> Result<(), $crate::ser::SerializeError> {
+                let data = <$name as $crate::ext::ExtType>::to_ext_data(self);
+                serializer.serialize_ext(<$name as $crate::ext::ExtType>::TYPE_ID, &data)
+            }
+        }
+
+        impl $crate::de::Deserialize for $name {
+            fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn $crate::de::Visitor {
+                fn convert(type_id: i8, data: &[u8]) -> Result<$name, $crate::de::DeserializeError> {
+                    if type_id != <$name as $crate::ext::ExtType>::TYPE_ID {
+                        return Err($crate::de::DeserializeError::UnknownExtType(type_id));
+                    }
+                    <$name as $crate::ext::ExtType>::from_ext_data(data)
+                }
+
+                $crate::make_place_type!(Place);
+
+                impl $crate::de::Visitor for Place<$name> {
+                    fn hint(&self) -> $crate::de::Hint {
+                        $crate::de::Hint::Ext
+                    }
+
+                    fn visit_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), $crate::de::DeserializeError> {
+                        self.out.replace(convert(type_id, data)?);
+                        Ok(())
+                    }
+
+                    fn visit_struct<'a>(&'a mut self) -> Result<Box<dyn $crate::de::StructBuilder + 'a>, $crate::de::DeserializeError> {
+                        Ok(Box::new($crate::ext::ExtStructBuilder::new(&mut self.out, convert)))
+                    }
+                }
+                return Place::new(out);
+            }
+        }
+    };
+}
+
+impl Timestamp {
+    pub const EXT_TYPE_ID: i8 = -1;
+
+    // The msgpack timestamp extension, in its 32, 64 or 96 bits form.
+    pub fn to_ext_data(&self) -> Vec<u8> {
+        if self.seconds >> 34 == 0 {
+            let value = ((self.nanoseconds as u64) << 34) | self.seconds as u64;
+            if value >> 32 == 0 {
+                (value as u32).to_be_bytes().to_vec()
+            } else {
+                value.to_be_bytes().to_vec()
+            }
+        } else {
+            let mut data = self.nanoseconds.to_be_bytes().to_vec();
+            data.extend_from_slice(&self.seconds.to_be_bytes());
+            data
+        }
+    }
+
+    pub fn from_ext_data(data: &[u8]) -> Result<Self, DeserializeError> {
+        let timestamp = match data.len() {
+            4 => Timestamp {
+                seconds: u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as i64,
+                nanoseconds: 0,
+            },
+            8 => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(data);
+                let value = u64::from_be_bytes(bytes);
+                Timestamp {
+                    seconds: (value & 0x3_ffff_ffff) as i64,
+                    nanoseconds: (value >> 34) as u32,
+                }
+            }
+            12 => {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(&data[4..]);
+                Timestamp {
+                    seconds: i64::from_be_bytes(bytes),
+                    nanoseconds: u32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+                }
+            }
+            _ => return Err(DeserializeError::InvalidExtData),
+        };
+        if timestamp.nanoseconds >= 1_000_000_000 {
+            return Err(DeserializeError::InvalidExtData);
+        }
+        Ok(timestamp)
+    }
+
+    // RFC 3339 as written by `Display`, or `None` for years outside of
+    // 0000-9999 which it can't represent.
+    pub fn to_rfc3339(&self) -> Option<String> {
+        let (year, _, _) = civil_from_days(self.seconds.div_euclid(86400));
+        if (0..=9999).contains(&year) {
+            Some(self.to_string())
+        } else {
+            None
+        }
+    }
+
+    // RFC 3339, e.g. `1993-10-19T07:30:00.25Z`.
+    pub fn parse_rfc3339(s: &str) -> Option<Self> {
+        let bytes = s.as_bytes();
+        if bytes.len() < 20
+            || bytes[4] != b'-'
+            || bytes[7] != b'-'
+            || !matches!(bytes[10], b'T' | b't' | b' ')
+            || bytes[13] != b':'
+            || bytes[16] != b':'
+        {
+            return None;
+        }
+        let number = |range: std::ops::Range<usize>| -> Option<i64> {
+            let digits = s.get(range)?;
+            if !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            digits.parse().ok()
+        };
+        let year = number(0..4)?;
+        let month = number(5..7)?;
+        let day = number(8..10)?;
+        let hour = number(11..13)?;
+        let minute = number(14..16)?;
+        let second = number(17..19)?;
+        if !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return None;
+        }
+
+        let mut rest = &s[19..];
+        let mut nanoseconds = 0;
+        if rest.starts_with('.') {
+            let digits = rest[1..].bytes().take_while(|b| b.is_ascii_digit()).count();
+            if digits == 0 {
+                return None;
+            }
+            for (i, digit) in rest[1..=digits].bytes().enumerate() {
+                if i < 9 {
+                    nanoseconds += (digit - b'0') as u32 * 10u32.pow(8 - i as u32);
+                }
+            }
+            rest = &rest[(digits + 1)..];
+        }
+
+        let offset = match rest.as_bytes() {
+            [b'Z'] | [b'z'] => 0,
+            [sign @ b'+', ..] | [sign @ b'-', ..]
+                if rest.len() == 6 && rest.as_bytes()[3] == b':' =>
+            {
+                let offset_hours = number(s.len() - 5..s.len() - 3)?;
+                let offset_minutes = number(s.len() - 2..s.len())?;
+                let offset = offset_hours * 3600 + offset_minutes * 60;
+                if *sign == b'-' {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            _ => return None,
+        };
+
+        let seconds =
+            days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second - offset;
+        Some(Timestamp {
+            seconds,
+            nanoseconds,
+        })
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let days = self.seconds.div_euclid(86400);
+        let seconds_of_day = self.seconds.rem_euclid(86400);
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            seconds_of_day / 3600,
+            seconds_of_day / 60 % 60,
+            seconds_of_day % 60
+        )?;
+        if self.nanoseconds != 0 {
+            let fraction = format!("{:09}", self.nanoseconds);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        write!(f, "Z")
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Howard Hinnant's algorithms, days are counted from 1970-01-01.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl Serialize for Timestamp {
+    fn serialize(&self, serializer: &mut dyn Serializer) -> Result<(), SerializeError> {
+        serializer.serialize_timestamp(*self)
+    }
+}
+
+impl Visitor for Place<Timestamp> {
+    fn hint(&self) -> Hint {
+        Hint::Timestamp
+    }
+
+    fn visit_timestamp(&mut self, value: Timestamp) -> Result<(), DeserializeError> {
+        self.out.replace(value);
+        Ok(())
+    }
+
+    fn visit_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), DeserializeError> {
+        if type_id != Timestamp::EXT_TYPE_ID {
+            return Err(DeserializeError::UnknownExtType(type_id));
+        }
+        self.out.replace(Timestamp::from_ext_data(data)?);
+        Ok(())
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), DeserializeError> {
+        let timestamp = Timestamp::parse_rfc3339(value).ok_or(DeserializeError::ParsingError)?;
+        self.out.replace(timestamp);
+        Ok(())
+    }
+}
+
+impl Deserialize for Timestamp {
+    fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
+        Place::new(out)
+    }
+}
@@ -1,4 +1,7 @@
+pub mod bytes;
 pub mod const_assert;
 pub mod de;
+pub mod ext;
 pub mod macros;
 pub mod ser;
+pub mod value;
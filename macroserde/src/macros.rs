@@ -76,7 +76,7 @@ macro_rules! macroserde {
                 $crate::const_assert!($name::check_unique_ids());
                 serializer.start_struct(Self::FIELD_COUNT)?;
                 $(
-                    serializer.serialize_struct_field($id, $field_name, &self.$field)?;
+                    serializer.serialize_struct_field($id, std::borrow::Cow::Borrowed($field_name), &self.$field)?;
                 )+
                 serializer.end_struct()?;
                 Ok(())
@@ -141,6 +141,10 @@ macro_rules! macroserde {
                 $crate::make_place_type!(Place);
 
                 impl $crate::de::Visitor for Place<$name> {
+                    fn hint(&self) -> $crate::de::Hint {
+                        $crate::de::Hint::Struct($name::IDS)
+                    }
+
                     fn visit_struct<'a>(&'a mut self) -> Result<Box<dyn $crate::de::StructBuilder + 'a>, $crate::de::DeserializeError> {
                         Ok(Box::new(Builder::new(&mut self.out)))
                     }
@@ -155,6 +159,7 @@ macro_rules! macroserde {
         )*
         $enum_vis:vis enum $name:ident {
             $(
+                $(#[$variant_attrib:meta])*
                 $variant:ident = $id:literal $(@ $variant_name:literal)?,
             )+
         }
@@ -165,6 +170,7 @@ macro_rules! macroserde {
             )*
             $enum_vis enum $name {
                 $(
+                    $(#[$variant_attrib])*
                     $variant = $id @ macroserde!(@rename $variant $($variant_name)?),
                 )+
             }
@@ -176,6 +182,7 @@ macro_rules! macroserde {
         )*
         $enum_vis:vis enum $name:ident {
             $(
+                $(#[$variant_attrib:meta])*
                 $variant:ident = $id:literal @ $variant_name:expr,
             )+
         }
@@ -185,6 +192,7 @@ macro_rules! macroserde {
         )*
         $enum_vis enum $name {
             $(
+                $(#[$variant_attrib])*
                 $variant,
             )+
         }
@@ -201,7 +209,7 @@ macro_rules! macroserde {
                 $crate::const_assert!($name::check_unique_ids());
                 match *self {
                     $(
-                        Self::$variant => serializer.serialize_enum($id, $variant_name)?,
+                        Self::$variant => serializer.serialize_enum($id, std::borrow::Cow::Borrowed($variant_name))?,
                     )+
                 }
                 Ok(())
@@ -213,9 +221,13 @@ macro_rules! macroserde {
                 $crate::make_place_type!(Place);
 
                 impl $crate::de::Visitor for Place<$name> {
+                    fn hint(&self) -> $crate::de::Hint {
+                        $crate::de::Hint::Enum
+                    }
+
                     fn visit_str(&mut self, value: &str) -> Result<(), $crate::de::DeserializeError> {
                         let (id, name) = if let Some(colon_index) = value.find(':') {
-                            let id = value[..colon_index].parse::<i64>().ok();
+                            let id = value[..colon_index].parse::<u32>().ok();
                             let name = &value[(colon_index + 1)..];
                             (id, name)
                         }
@@ -223,6 +235,28 @@ macro_rules! macroserde {
                             (None, value)
                         };
 
+                        self.visit_enum(id, Some(name))
+                    }
+
+                    fn visit_signed(&mut self, value: i64) -> Result<(), $crate::de::DeserializeError> {
+                        if value < 0 {
+                            Err($crate::de::DeserializeError::UnknownEnumVariant)
+                        }
+                        else {
+                            self.visit_unsigned(value as u64)
+                        }
+                    }
+
+                    fn visit_unsigned(&mut self, value: u64) -> Result<(), $crate::de::DeserializeError> {
+                        if value > std::u32::MAX as u64 {
+                            Err($crate::de::DeserializeError::UnknownEnumVariant)
+                        }
+                        else {
+                            self.visit_enum(Some(value as u32), None)
+                        }
+                    }
+
+                    fn visit_enum(&mut self, id: Option<u32>, name: Option<&str>) -> Result<(), $crate::de::DeserializeError> {
                         if let Some(id) = id {
                             let variant = match id {
                                 $(
@@ -237,46 +271,21 @@ macro_rules! macroserde {
                             }
                         }
 
-                        let variant = match name {
-                            $(
-                                $variant_name => Some($name::$variant),
-                            )+
-                            _ => None,
-                        };
-
-                        if let Some(variant) = variant {
-                            self.out.replace(variant);
-                            return Ok(());
-                        }
-                        else {
-                            return Err($crate::de::DeserializeError::UnknownEnumVariant);
-                        }
-                    }
+                        if let Some(name) = name {
+                            let variant = match name {
+                                $(
+                                    $variant_name => Some($name::$variant),
+                                )+
+                                _ => None,
+                            };
 
-                    fn visit_signed(&mut self, value: i64) -> Result<(), $crate::de::DeserializeError> {
-                        if value < 0 {
-                            Err($crate::de::DeserializeError::UnknownEnumVariant)
-                        }
-                        else {
-                            self.visit_unsigned(value as u64)
+                            if let Some(variant) = variant {
+                                self.out.replace(variant);
+                                return Ok(());
+                            }
                         }
-                    }
-
-                    fn visit_unsigned(&mut self, value: u64) -> Result<(), $crate::de::DeserializeError> {
-                        let variant = match value {
-                            $(
-                                $id => Some($name::$variant),
-                            )+
-                            _ => None,
-                        };
 
-                        if let Some(variant) = variant {
-                            self.out.replace(variant);
-                            return Ok(());
-                        }
-                        else {
-                            return Err($crate::de::DeserializeError::UnknownEnumVariant);
-                        }
+                        return Err($crate::de::DeserializeError::UnknownEnumVariant);
                     }
                 }
                 return Place::new(out);
@@ -297,6 +306,7 @@ macro_rules! macroserde {
         )*
         $vis:vis union $name:ident {
             $(
+                $(#[$variant_attrib:meta])*
                 $variant:ident$(($type:ty))? = $id:literal $(@ $variant_name:literal)?,
             )+
         }
@@ -307,6 +317,7 @@ macro_rules! macroserde {
             )*
             $vis union $name {
                 $(
+                    $(#[$variant_attrib])*
                     $variant$(($type))? = $id @ macroserde!(@rename $variant $($variant_name)?),
                 )+
             }
@@ -318,6 +329,7 @@ macro_rules! macroserde {
         )*
         $vis:vis union $name:ident {
             $(
+                $(#[$variant_attrib:meta])*
                 $variant:ident$(($type:ty))? = $id:literal @ $variant_name:expr,
             )+
         }
@@ -327,6 +339,7 @@ macro_rules! macroserde {
         )*
         $vis enum $name {
             $(
+                $(#[$variant_attrib])*
                 $variant$(($type))?,
             )+
         }
@@ -346,9 +359,7 @@ macro_rules! macroserde {
                 match self {
                     $(
                         macroserde!(@union_variant_val $variant $(val $type)?) => {
-                            serializer.start_struct(1)?;
-                            serializer.serialize_struct_field($id, $variant_name, macroserde!(@union_variant_serialize $(val $type)?))?;
-                            serializer.end_struct()?;
+                            serializer.serialize_union($id, std::borrow::Cow::Borrowed($variant_name), macroserde!(@union_variant_serialize $(val $type)?))?;
                         },
                     )+
                 }
@@ -425,6 +436,10 @@ macro_rules! macroserde {
 
 
                 impl $crate::de::Visitor for Place<$name> {
+                    fn hint(&self) -> $crate::de::Hint {
+                        $crate::de::Hint::Union($name::IDS)
+                    }
+
                     fn visit_struct<'a>(&'a mut self) -> Result<Box<dyn StructBuilder + 'a>, DeserializeError> {
                         Ok(Box::new(Builder::new(&mut self.out)))
                     }
@@ -54,7 +54,10 @@ impl Serialize for bool {
 impl<T: Serialize> Serialize for Option<T> {
     fn serialize(&self, serializer: &mut dyn Serializer) -> Result<(), SerializeError> {
         match self {
-            Some(s) => s.serialize(serializer),
+            Some(s) => {
+                serializer.serialize_some()?;
+                s.serialize(serializer)
+            }
             None => serializer.serialize_null(),
         }
     }
@@ -92,6 +95,18 @@ impl<T: Serialize> Serialize for [T] {
     }
 }
 
+impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn serialize(&self, serializer: &mut dyn Serializer) -> Result<(), SerializeError> {
+        self[..].serialize(serializer)
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for &T {
+    fn serialize(&self, serializer: &mut dyn Serializer) -> Result<(), SerializeError> {
+        (**self).serialize(serializer)
+    }
+}
+
 impl Serialize for () {
     fn serialize(&self, serializer: &mut dyn Serializer) -> Result<(), SerializeError> {
         serializer.serialize_null()
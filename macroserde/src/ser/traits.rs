@@ -1,6 +1,11 @@
+use crate::ext::Timestamp;
+use std::borrow::Cow;
+
 #[derive(Debug)]
 pub struct SerializeError;
 
+// Names are borrowed string literals in generated code, and owned by values
+// built at runtime, so serializers that keep them don't have to copy literals.
 pub trait Serializer {
     fn serialize_null(&mut self) -> Result<(), SerializeError>;
     fn serialize_bool(&mut self, value: bool) -> Result<(), SerializeError>;
@@ -8,18 +13,59 @@ pub trait Serializer {
     fn serialize_unsigned(&mut self, value: u64) -> Result<(), SerializeError>;
     fn serialize_float(&mut self, value: f64) -> Result<(), SerializeError>;
     fn serialize_str(&mut self, value: &str) -> Result<(), SerializeError>;
-    fn serialize_enum(&mut self, value: u32, name: &'static str) -> Result<(), SerializeError>;
+    fn serialize_enum(&mut self, value: u32, name: Cow<'static, str>)
+        -> Result<(), SerializeError>;
     fn start_struct(&mut self, len: usize) -> Result<(), SerializeError>;
     fn serialize_struct_field(
         &mut self,
         field_id: u32,
-        field_name: &'static str,
+        field_name: Cow<'static, str>,
         value: &dyn Serialize,
     ) -> Result<(), SerializeError>;
     fn end_struct(&mut self) -> Result<(), SerializeError>;
     fn start_seq(&mut self, len: usize) -> Result<(), SerializeError>;
     fn serialize_seq_elmt(&mut self, value: &dyn Serialize) -> Result<(), SerializeError>;
     fn end_seq(&mut self) -> Result<(), SerializeError>;
+
+    // Formats without native byte strings write them as sequences of integers.
+    fn serialize_bytes(&mut self, value: &[u8]) -> Result<(), SerializeError> {
+        self.start_seq(value.len())?;
+        for byte in value {
+            self.serialize_seq_elmt(byte)?;
+        }
+        self.end_seq()
+    }
+
+    // Formats without native extension values write them as `{ 1:type, 2:data }`.
+    fn serialize_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), SerializeError> {
+        self.start_struct(2)?;
+        self.serialize_struct_field(1, "type".into(), &type_id)?;
+        self.serialize_struct_field(2, "data".into(), &data)?;
+        self.end_struct()
+    }
+
+    // Formats without native timestamps write them as RFC 3339 strings.
+    fn serialize_timestamp(&mut self, value: Timestamp) -> Result<(), SerializeError> {
+        self.serialize_str(&value.to_rfc3339().ok_or(SerializeError)?)
+    }
+
+    // Called before the value of a `Some`. Self-describing formats tell it from
+    // `None`, which is null, and write nothing.
+    fn serialize_some(&mut self) -> Result<(), SerializeError> {
+        Ok(())
+    }
+
+    // Self-describing formats write unions as a struct with a single field.
+    fn serialize_union(
+        &mut self,
+        variant_id: u32,
+        variant_name: Cow<'static, str>,
+        value: &dyn Serialize,
+    ) -> Result<(), SerializeError> {
+        self.start_struct(1)?;
+        self.serialize_struct_field(variant_id, variant_name, value)?;
+        self.end_struct()
+    }
 }
 
 pub trait Serialize {
@@ -0,0 +1,300 @@
+use crate::de::{
+    Deserialize, DeserializeError, Deserializer, Hint, SeqBuilder, StructBuilder, Visitor,
+};
+use crate::ext::Timestamp;
+use crate::make_place_type;
+use crate::ser::{Serialize, SerializeError, Serializer};
+
+make_place_type!(Place);
+
+// Struct fields and enumerations are known by their ID, their name or both,
+// depending on the format they were read from.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Key {
+    pub id: Option<u32>,
+    pub name: Option<String>,
+}
+
+// Any value of the data model, read from any `Deserializer` and written to any
+// `Serializer`. It's also a `Deserializer` to read typed values from it, so
+// `Value::deserialize` is ambiguous: a value is read with
+// `<Value as Deserialize>::deserialize(&mut de)`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Signed(i64),
+    Unsigned(u64),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Ext(i8, Vec<u8>),
+    Timestamp(Timestamp),
+    Enum(Key),
+    Seq(Vec<Value>),
+    Struct(Vec<(Key, Value)>),
+}
+
+impl Key {
+    pub fn new(id: Option<u32>, name: Option<&str>) -> Self {
+        Self {
+            id,
+            name: name.map(str::to_owned),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize(&self, serializer: &mut dyn Serializer) -> Result<(), SerializeError> {
+        match self {
+            Value::Null => serializer.serialize_null(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Signed(i) => serializer.serialize_signed(*i),
+            Value::Unsigned(u) => serializer.serialize_unsigned(*u),
+            Value::Float(f) => serializer.serialize_float(*f),
+            Value::Str(s) => serializer.serialize_str(s),
+            Value::Bytes(bytes) => serializer.serialize_bytes(bytes),
+            Value::Ext(type_id, data) => serializer.serialize_ext(*type_id, data),
+            Value::Timestamp(timestamp) => serializer.serialize_timestamp(*timestamp),
+            Value::Enum(Key {
+                id: Some(id),
+                name: Some(name),
+            }) => serializer.serialize_enum(*id, name.clone().into()),
+            // Enumerations also read a lone name or ID.
+            Value::Enum(Key {
+                name: Some(name), ..
+            }) => serializer.serialize_str(name),
+            Value::Enum(Key { id: Some(id), .. }) => serializer.serialize_unsigned(*id as u64),
+            Value::Enum(Key { .. }) => serializer.serialize_null(),
+            Value::Seq(elements) => {
+                serializer.start_seq(elements.len())?;
+                for element in elements {
+                    serializer.serialize_seq_elmt(element)?;
+                }
+                serializer.end_seq()
+            }
+            // Fields can't be written without an ID, which formats keyed by
+            // IDs need. A missing name is written as an empty string.
+            Value::Struct(fields) => {
+                serializer.start_struct(fields.len())?;
+                for (key, value) in fields {
+                    let id = key.id.ok_or(SerializeError)?;
+                    let name = key.name.clone().unwrap_or_default();
+                    serializer.serialize_struct_field(id, name.into(), value)?;
+                }
+                serializer.end_struct()
+            }
+        }
+    }
+}
+
+struct ValueSeqBuilder<'a> {
+    out: &'a mut Option<Value>,
+    elements: Vec<Value>,
+    element: Option<Value>,
+}
+
+impl<'a> ValueSeqBuilder<'a> {
+    fn shift(&mut self) {
+        if let Some(element) = self.element.take() {
+            self.elements.push(element);
+        }
+    }
+}
+
+impl<'a> SeqBuilder for ValueSeqBuilder<'a> {
+    fn element(&mut self) -> Result<&mut dyn Visitor, DeserializeError> {
+        self.shift();
+        Ok(Value::begin_deserialize(&mut self.element))
+    }
+
+    fn finish(&mut self) -> Result<(), DeserializeError> {
+        self.shift();
+        self.out
+            .replace(Value::Seq(std::mem::take(&mut self.elements)));
+        Ok(())
+    }
+}
+
+struct ValueStructBuilder<'a> {
+    out: &'a mut Option<Value>,
+    fields: Vec<(Key, Value)>,
+    key: Option<Key>,
+    value: Option<Value>,
+}
+
+impl<'a> ValueStructBuilder<'a> {
+    fn shift(&mut self) {
+        if let (Some(key), Some(value)) = (self.key.take(), self.value.take()) {
+            self.fields.push((key, value));
+        }
+    }
+}
+
+impl<'a> StructBuilder for ValueStructBuilder<'a> {
+    fn member(
+        &mut self,
+        id: Option<u32>,
+        name: Option<&str>,
+    ) -> Result<&mut dyn Visitor, DeserializeError> {
+        self.shift();
+        self.key = Some(Key::new(id, name));
+        Ok(Value::begin_deserialize(&mut self.value))
+    }
+
+    fn finish(&mut self) -> Result<(), DeserializeError> {
+        self.shift();
+        self.out
+            .replace(Value::Struct(std::mem::take(&mut self.fields)));
+        Ok(())
+    }
+}
+
+impl Visitor for Place<Value> {
+    fn visit_null(&mut self) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Null);
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Bool(value));
+        Ok(())
+    }
+
+    fn visit_signed(&mut self, value: i64) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Signed(value));
+        Ok(())
+    }
+
+    fn visit_unsigned(&mut self, value: u64) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Unsigned(value));
+        Ok(())
+    }
+
+    fn visit_float(&mut self, value: f64) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Float(value));
+        Ok(())
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Str(value.to_owned()));
+        Ok(())
+    }
+
+    fn visit_enum(&mut self, id: Option<u32>, name: Option<&str>) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Enum(Key::new(id, name)));
+        Ok(())
+    }
+
+    fn visit_bytes(&mut self, value: &[u8]) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Bytes(value.to_owned()));
+        Ok(())
+    }
+
+    fn visit_ext(&mut self, type_id: i8, data: &[u8]) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Ext(type_id, data.to_owned()));
+        Ok(())
+    }
+
+    fn visit_timestamp(&mut self, value: Timestamp) -> Result<(), DeserializeError> {
+        self.out.replace(Value::Timestamp(value));
+        Ok(())
+    }
+
+    // Present optional values are the value itself.
+    fn visit_some(&mut self) -> Result<&mut dyn Visitor, DeserializeError> {
+        Ok(Value::begin_deserialize(&mut self.out))
+    }
+
+    fn visit_seq<'a>(
+        &'a mut self,
+        size_hint: Option<usize>,
+    ) -> Result<Box<dyn SeqBuilder + 'a>, DeserializeError> {
+        Ok(Box::new(ValueSeqBuilder {
+            out: &mut self.out,
+            elements: Vec::with_capacity(size_hint.unwrap_or(0).min(4096)),
+            element: None,
+        }))
+    }
+
+    fn visit_struct<'a>(&'a mut self) -> Result<Box<dyn StructBuilder + 'a>, DeserializeError> {
+        Ok(Box::new(ValueStructBuilder {
+            out: &mut self.out,
+            fields: Vec::new(),
+            key: None,
+            value: None,
+        }))
+    }
+}
+
+impl Deserialize for Value {
+    fn begin_deserialize(out: &mut Option<Self>) -> &mut dyn Visitor {
+        Place::new(out)
+    }
+}
+
+impl Value {
+    fn visit(&self, visitor: &mut dyn Visitor) -> Result<(), DeserializeError> {
+        match self {
+            Value::Null => visitor.visit_null(),
+            _ if visitor.hint() == Hint::Option => self.visit(visitor.visit_some()?),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Signed(i) => visitor.visit_signed(*i),
+            Value::Unsigned(u) => visitor.visit_unsigned(*u),
+            Value::Float(f) => visitor.visit_float(*f),
+            Value::Str(s) => visitor.visit_str(s),
+            Value::Bytes(bytes) => match visitor.visit_bytes(bytes) {
+                Err(DeserializeError::UnimplementedVisit) => {
+                    let mut builder = visitor.visit_seq(Some(bytes.len()))?;
+                    for byte in bytes {
+                        builder.element()?.visit_unsigned(*byte as u64)?;
+                    }
+                    builder.finish()
+                }
+                result => result,
+            },
+            Value::Ext(type_id, data) => visitor.visit_ext(*type_id, data),
+            Value::Timestamp(timestamp) => match visitor.visit_timestamp(*timestamp) {
+                Err(DeserializeError::UnimplementedVisit) => {
+                    visitor.visit_str(&timestamp.to_string())
+                }
+                result => result,
+            },
+            Value::Enum(key) => match visitor.visit_enum(key.id, key.name.as_deref()) {
+                Err(DeserializeError::UnimplementedVisit) => match key {
+                    Key {
+                        name: Some(name), ..
+                    } => visitor.visit_str(name),
+                    Key { id: Some(id), .. } => visitor.visit_unsigned(*id as u64),
+                    Key { .. } => visitor.visit_null(),
+                },
+                result => result,
+            },
+            Value::Seq(elements) => {
+                let mut builder = visitor.visit_seq(Some(elements.len()))?;
+                for element in elements {
+                    element.visit(builder.element()?)?;
+                }
+                builder.finish()
+            }
+            Value::Struct(fields) => {
+                let mut builder = visitor.visit_struct()?;
+                for (key, value) in fields {
+                    match builder.member(key.id, key.name.as_deref()) {
+                        Ok(visitor) => value.visit(visitor)?,
+                        Err(DeserializeError::UnknownField) => {}
+                        Err(e) => return Err(e),
+                    }
+                }
+                builder.finish()
+            }
+        }
+    }
+}
+
+impl Deserializer for Value {
+    fn deserialize(&mut self, visitor: &mut dyn Visitor) -> Result<(), DeserializeError> {
+        self.visit(visitor)
+    }
+}
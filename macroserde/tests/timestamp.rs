@@ -0,0 +1,49 @@
+use macroserde::ext::Timestamp;
+
+#[test]
+fn rfc3339() {
+    let timestamp = Timestamp {
+        seconds: 750_994_200,
+        nanoseconds: 250_000_000,
+    };
+    assert_eq!(timestamp.to_string(), "1993-10-19T01:30:00.25Z");
+    assert_eq!(
+        Timestamp::parse_rfc3339("1993-10-19T01:30:00.25Z"),
+        Some(timestamp)
+    );
+    assert_eq!(
+        Timestamp::parse_rfc3339("1993-10-19T03:30:00.250+02:00"),
+        Some(timestamp)
+    );
+    assert_eq!(Timestamp::parse_rfc3339("1993-10-19"), None);
+    assert_eq!(Timestamp::parse_rfc3339("1993-02-29T00:00:00Z"), None);
+}
+
+#[test]
+fn years() {
+    let last = Timestamp {
+        seconds: 253_402_300_799,
+        nanoseconds: 0,
+    };
+    assert_eq!(last.to_rfc3339().unwrap(), "9999-12-31T23:59:59Z");
+    assert_eq!(Timestamp::parse_rfc3339("9999-12-31T23:59:59Z"), Some(last));
+    let first = Timestamp {
+        seconds: -62_167_219_200,
+        nanoseconds: 0,
+    };
+    assert_eq!(first.to_rfc3339().unwrap(), "0000-01-01T00:00:00Z");
+    assert_eq!(
+        Timestamp::parse_rfc3339("0000-01-01T00:00:00Z"),
+        Some(first)
+    );
+
+    // Formats without timestamps write them as RFC 3339 strings, which can't
+    // hold other years.
+    for seconds in [last.seconds + 1, first.seconds - 1, i64::MAX, i64::MIN] {
+        let timestamp = Timestamp {
+            seconds,
+            nanoseconds: 0,
+        };
+        assert_eq!(timestamp.to_rfc3339(), None);
+    }
+}